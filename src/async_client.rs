@@ -0,0 +1,1127 @@
+/// An asynchronous client for Riak's Protocol Buffers API, built on tokio.
+///
+/// `AsyncClient` offers the same operations as `Client`, except that every operation returns a
+/// future and the streaming operations return a `futures::Stream`. Every clone of an
+/// `AsyncClient` shares a single connection: requests are written as soon as they are made and
+/// Riak answers them in order, so many requests can be in flight at once without waiting on each
+/// other.
+///
+/// The connection is driven by a task spawned onto the tokio runtime the client connects from.
+/// It is closed once every clone of the client and every stream created from it is dropped.
+///
+/// This module requires the "async" feature.
+///
+/// # Examples
+///
+/// ```
+/// extern crate futures;
+/// extern crate riak;
+/// extern crate tokio;
+///
+/// use futures::future::join_all;
+/// use riak::async_client::AsyncClient;
+/// use riak::object::FetchObjectReq;
+/// use tokio::runtime::Builder;
+///
+/// # fn main() {
+/// let runtime = Builder::new_current_thread().enable_io().build().unwrap();
+/// let riak = runtime.block_on(AsyncClient::connect("10.0.0.2:8087")).unwrap();
+///
+/// // both requests are sent before either response is received
+/// let first = riak.fetch_object(&FetchObjectReq::new("testbucket", "first"));
+/// let second = riak.fetch_object(&FetchObjectReq::new("testbucket", "second"));
+///
+/// for object in runtime.block_on(join_all(vec![first, second])) {
+///     println!("fetched object {:?}", object.unwrap());
+/// }
+/// # }
+/// ```
+
+use DEFAULT_TIMEOUT;
+use bucket::BucketProps;
+use bytes::{Buf, BufMut, BytesMut};
+use datatypes::{FetchDataTypeReq, FetchDataTypeResp, UpdateDataTypeReq, UpdateDataTypeResp};
+use errors::{RiakErr, ServerError};
+use futures::channel::{mpsc, oneshot};
+use futures::future::{self, BoxFuture, FutureExt};
+use futures::{Sink, Stream, TryStreamExt};
+use index::{IndexQuery, IndexResults};
+use mapreduce::MapReduceJob;
+use object::{DeleteObjectReq, FetchObjectReq, FetchObjectResp, StoreObjectReq, StoreObjectResp};
+use preflist::PreflistItem;
+use private_traits::{BucketPropsPrivate, DeleteObjectReqPrivate, FetchDataTypeRespPrivate,
+                     IndexQueryPrivate, UpdateDataTypeReqPrivate, YokozunaIndexPrivate};
+use protobuf::{Message, MessageStatic, parse_from_bytes};
+use rpb::codes;
+use rpb::riak::{RpbGetBucketReq, RpbGetBucketResp, RpbGetBucketTypeReq, RpbGetServerInfoResp,
+                RpbResetBucketReq};
+use rpb::riak_dt::{DtFetchResp, DtUpdateResp};
+use rpb::riak_kv::{RpbGetBucketKeyPreflistReq, RpbGetBucketKeyPreflistResp, RpbGetResp,
+                   RpbListBucketsReq, RpbListBucketsResp, RpbListKeysReq, RpbListKeysResp,
+                   RpbMapRedResp, RpbPutResp};
+use rpb::riak_search::RpbSearchQueryResp;
+use rpb::riak_yokozuna::{RpbYokozunaIndexDeleteReq, RpbYokozunaIndexGetReq,
+                         RpbYokozunaIndexGetResp, RpbYokozunaSchema, RpbYokozunaSchemaGetReq,
+                         RpbYokozunaSchemaGetResp, RpbYokozunaSchemaPutReq};
+use rpb::utils::{index_response_code, index_response_to_index_results,
+                 rpb_get_resp_to_fetch_object_resp, rpb_put_resp_to_store_object_resp,
+                 rpb_search_query_resp_to_search_results, RpbGenerator};
+use std::collections::VecDeque;
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::net::{TcpStream, ToSocketAddrs};
+use tokio_util::codec::{Decoder, Encoder, Framed};
+use yokozuna::{SearchQuery, SearchResults, YokozunaIndex};
+
+/// `RiakFuture` is the future returned by the operations of an `AsyncClient`
+pub type RiakFuture<T> = BoxFuture<'static, Result<T, RiakErr>>;
+
+/// `RiakCodec` frames the messages of Riak's Protocol Buffers API, which are sent as a 4 byte big
+/// endian length followed by a 1 byte message code and the protobuf encoded message.
+///
+/// Frames are `(code, message)` pairs, so the codec can be used with `tokio_util::codec::Framed`
+/// to talk to Riak directly.
+#[derive(Clone, Copy, Debug)]
+pub struct RiakCodec;
+
+impl RiakCodec {
+    /// constructs a new `RiakCodec`
+    pub fn new() -> RiakCodec {
+        RiakCodec
+    }
+}
+
+impl Decoder for RiakCodec {
+    type Item = (u8, Vec<u8>);
+    type Error = io::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<(u8, Vec<u8>)>, io::Error> {
+        // wait for the whole length header
+        if src.len() < 4 {
+            return Ok(None);
+        }
+
+        // the length includes the message code
+        let length = ((src[0] as usize) << 24) | ((src[1] as usize) << 16) |
+                     ((src[2] as usize) << 8) | (src[3] as usize);
+        if length == 0 {
+            return Err(io::Error::new(io::ErrorKind::InvalidData,
+                                      "received a frame without a message code"));
+        }
+
+        // wait for the rest of the frame
+        if src.len() < 4 + length {
+            let missing = 4 + length - src.len();
+            src.reserve(missing);
+            return Ok(None);
+        }
+
+        src.advance(4);
+        let frame = src.split_to(length);
+        Ok(Some((frame[0], frame[1..].to_vec())))
+    }
+}
+
+impl Encoder<(u8, Vec<u8>)> for RiakCodec {
+    type Error = io::Error;
+
+    fn encode(&mut self, item: (u8, Vec<u8>), dst: &mut BytesMut) -> Result<(), io::Error> {
+        let (code, message) = item;
+        dst.reserve(5 + message.len());
+        dst.put_u32((message.len() + 1) as u32);
+        dst.put_u8(code);
+        dst.extend_from_slice(&message);
+        Ok(())
+    }
+}
+
+// How the responses to a request are passed back to whoever made it.
+#[derive(Debug)]
+enum Responder {
+    // a single response is expected
+    Single(oneshot::Sender<Result<Vec<u8>, RiakErr>>),
+    // responses are expected until one that the function reports as the last one
+    Stream(mpsc::UnboundedSender<Result<Vec<u8>, RiakErr>>, fn(&[u8]) -> bool),
+}
+
+impl Responder {
+    // Passes a response back, returning the `Responder` again if more responses are expected.
+    fn respond(self, response: Result<Vec<u8>, RiakErr>) -> Option<Responder> {
+        match self {
+            Responder::Single(sender) => {
+                let _ = sender.send(response);
+                None
+            }
+            Responder::Stream(sender, is_done) => {
+                let done = match response {
+                    Ok(ref message) => is_done(message),
+                    Err(_) => true,
+                };
+                let _ = sender.unbounded_send(response);
+                if done {
+                    None
+                } else {
+                    Some(Responder::Stream(sender, is_done))
+                }
+            }
+        }
+    }
+}
+
+// A request queued to be written to the connection.
+#[derive(Debug)]
+struct Request {
+    code: u8,
+    message: Vec<u8>,
+    response_code: u8,
+    responder: Responder,
+}
+
+// `Dispatcher` drives the connection of an `AsyncClient`, writing requests as they are queued and
+// passing each response back to the oldest request that is still waiting for one.
+struct Dispatcher {
+    framed: Framed<TcpStream, RiakCodec>,
+    requests: mpsc::UnboundedReceiver<Request>,
+    requests_closed: bool,
+    pending: VecDeque<(u8, Responder)>,
+}
+
+impl Dispatcher {
+    fn poll_dispatch(&mut self, cx: &mut Context) -> Poll<Result<(), io::Error>> {
+        // write every request that has been queued
+        while !self.requests_closed {
+            match Pin::new(&mut self.framed).poll_ready(cx) {
+                Poll::Ready(Ok(())) => (),
+                Poll::Ready(Err(error)) => return Poll::Ready(Err(error)),
+                Poll::Pending => break,
+            };
+
+            match Pin::new(&mut self.requests).poll_next(cx) {
+                Poll::Ready(Some(request)) => {
+                    match Pin::new(&mut self.framed).start_send((request.code, request.message)) {
+                        Ok(()) => (),
+                        Err(error) => return Poll::Ready(Err(error)),
+                    };
+                    self.pending.push_back((request.response_code, request.responder));
+                }
+                Poll::Ready(None) => self.requests_closed = true,
+                Poll::Pending => break,
+            };
+        }
+
+        if let Poll::Ready(Err(error)) = Pin::new(&mut self.framed).poll_flush(cx) {
+            return Poll::Ready(Err(error));
+        }
+
+        // pass back every response that has been received
+        loop {
+            match Pin::new(&mut self.framed).poll_next(cx) {
+                Poll::Ready(Some(Ok((code, message)))) => {
+                    match self.dispatch(code, message) {
+                        Ok(()) => (),
+                        Err(error) => return Poll::Ready(Err(error)),
+                    };
+                }
+                Poll::Ready(Some(Err(error))) => return Poll::Ready(Err(error)),
+                Poll::Ready(None) => {
+                    return Poll::Ready(Err(io::Error::new(io::ErrorKind::UnexpectedEof,
+                                                          "connection closed by Riak")));
+                }
+                Poll::Pending => break,
+            };
+        }
+
+        if self.requests_closed && self.pending.is_empty() {
+            Poll::Ready(Ok(()))
+        } else {
+            Poll::Pending
+        }
+    }
+
+    fn dispatch(&mut self, code: u8, message: Vec<u8>) -> Result<(), io::Error> {
+        let (response_code, responder) = match self.pending.pop_front() {
+            Some(pending) => pending,
+            None => {
+                return Err(io::Error::new(io::ErrorKind::InvalidData,
+                                          "received a response without a request"))
+            }
+        };
+
+        // anything other than the expected response is an error from Riak
+        let response = if code == response_code {
+            Ok(message)
+        } else {
+            Err(RiakErr::ServerError(ServerError::new(code, message)))
+        };
+
+        if let Some(responder) = responder.respond(response) {
+            self.pending.push_front((response_code, responder));
+        }
+
+        Ok(())
+    }
+
+    fn fail(&mut self, error: &io::Error) {
+        for (_, responder) in self.pending.drain(..) {
+            let error = io::Error::new(error.kind(), error.to_string());
+            responder.respond(Err(RiakErr::IoError(error)));
+        }
+    }
+}
+
+impl Future for Dispatcher {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<()> {
+        match self.poll_dispatch(cx) {
+            Poll::Ready(Ok(())) => Poll::Ready(()),
+            Poll::Ready(Err(error)) => {
+                debug!("async connection failed: {:?}", error);
+                self.fail(&error);
+                Poll::Ready(())
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+// The error returned for requests that could not be completed because the connection is gone.
+fn connection_closed() -> RiakErr {
+    RiakErr::IoError(io::Error::new(io::ErrorKind::NotConnected, "connection to Riak is closed"))
+}
+
+// Queues a request to be written to the connection.
+fn queue(requests: &mpsc::UnboundedSender<Request>, request: Request) -> Result<(), RiakErr> {
+    match requests.unbounded_send(request) {
+        Ok(()) => Ok(()),
+        Err(_) => Err(connection_closed()),
+    }
+}
+
+// Encodes a protobuf message.
+fn encode<M: Message>(message: &M) -> Result<Vec<u8>, RiakErr> {
+    match message.write_to_bytes() {
+        Ok(bytes) => Ok(bytes),
+        Err(error) => Err(RiakErr::ProtobufError(error)),
+    }
+}
+
+// Parses a response into a protobuf message.
+fn parse<M: MessageStatic>(response: Result<Vec<u8>, RiakErr>) -> Result<M, RiakErr> {
+    match response {
+        Ok(response) => {
+            match parse_from_bytes::<M>(&response) {
+                Ok(parsed) => Ok(parsed),
+                Err(error) => Err(RiakErr::ProtobufError(error)),
+            }
+        }
+        Err(error) => Err(error),
+    }
+}
+
+/// `AsyncClient` represents a connection to a Riak server's Protocol Buffers API that is used
+/// asynchronously.
+///
+/// Cloning an `AsyncClient` is cheap, the clones share the same connection.
+#[derive(Clone, Debug)]
+pub struct AsyncClient {
+    requests: mpsc::UnboundedSender<Request>,
+}
+
+impl AsyncClient {
+    /// Connects to a Riak server, this must be called from within a tokio runtime.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate riak;
+    /// extern crate tokio;
+    ///
+    /// use riak::async_client::AsyncClient;
+    /// use tokio::runtime::Builder;
+    ///
+    /// # fn main() {
+    /// let runtime = Builder::new_current_thread().enable_io().build().unwrap();
+    /// let riak = runtime.block_on(AsyncClient::connect("10.0.0.2:8087")).unwrap();
+    /// runtime.block_on(riak.ping()).unwrap();
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the connection could not be established.
+    pub fn connect<A>(addr: A) -> RiakFuture<AsyncClient>
+        where A: ToSocketAddrs + Send + 'static
+    {
+        TcpStream::connect(addr)
+            .map(|connected| {
+                let stream = match connected {
+                    Ok(stream) => stream,
+                    Err(error) => return Err(RiakErr::IoError(error)),
+                };
+
+                // requests are small and often pipelined, so send them without delay
+                match stream.set_nodelay(true) {
+                    Ok(()) => (),
+                    Err(error) => return Err(RiakErr::IoError(error)),
+                };
+
+                let (sender, receiver) = mpsc::unbounded();
+                tokio::spawn(Dispatcher {
+                    framed: Framed::new(stream, RiakCodec::new()),
+                    requests: receiver,
+                    requests_closed: false,
+                    pending: VecDeque::new(),
+                });
+
+                Ok(AsyncClient { requests: sender })
+            })
+            .boxed()
+    }
+
+    // Queues a request that expects a single response.
+    fn request(&self,
+               code: u8,
+               response_code: u8,
+               message: Result<Vec<u8>, RiakErr>)
+               -> RiakFuture<Vec<u8>> {
+        let message = match message {
+            Ok(message) => message,
+            Err(error) => return future::err(error).boxed(),
+        };
+
+        let (sender, receiver) = oneshot::channel();
+        let request = Request {
+            code: code,
+            message: message,
+            response_code: response_code,
+            responder: Responder::Single(sender),
+        };
+        match queue(&self.requests, request) {
+            Ok(()) => (),
+            Err(error) => return future::err(error).boxed(),
+        };
+
+        receiver.map(|received| match received {
+                Ok(response) => response,
+                Err(_) => Err(connection_closed()),
+            })
+            .boxed()
+    }
+
+    /// Sends a ping message to Riak and returns a Result.
+    pub fn ping(&self) -> RiakFuture<()> {
+        self.request(codes::RpbPingReq, codes::RpbPingResp, Ok(vec![]))
+            .map(|response| match response {
+                Ok(_) => Ok(()),
+                Err(error) => Err(error),
+            })
+            .boxed()
+    }
+
+    /// Get the node name and server version of the Riak server reached.
+    pub fn server_info(&self) -> RiakFuture<(String, String)> {
+        self.request(codes::RpbGetServerInfoReq, codes::RpbGetServerInfoResp, Ok(vec![]))
+            .map(|response| match parse::<RpbGetServerInfoResp>(response) {
+                Ok(resp) => {
+                    Ok((String::from_utf8_lossy(resp.get_node()).into_owned(),
+                        String::from_utf8_lossy(resp.get_server_version()).into_owned()))
+                }
+                Err(error) => Err(error),
+            })
+            .boxed()
+    }
+
+    /// Produces a stream of bucket names.
+    ///
+    /// Caution: This call can be expensive for the server. Do not use in performance-sensitive code.
+    pub fn stream_buckets(&self) -> AsyncBucketStream {
+        AsyncBucketStream {
+            state: StreamState::Unsent(self.requests.clone()),
+            bucket_type: None,
+            timeout: None,
+        }
+    }
+
+    /// Produces a list of bucket names.
+    ///
+    /// Caution: This call can be expensive for the server. Do not use in performance-sensitive code.
+    pub fn list_buckets(&self) -> RiakFuture<Vec<Vec<u8>>> {
+        self.stream_buckets().try_concat().boxed()
+    }
+
+    /// Sets the properties for a bucket given a bucket name.
+    pub fn set_bucket_properties<T: Into<Vec<u8>>>(&self,
+                                                   bucket_name: T,
+                                                   bucket_props: &BucketProps)
+                                                   -> RiakFuture<()> {
+        let bytes = bucket_props.write_to_bytes(bucket_name.into(), false);
+        self.request(codes::RpbSetBucketReq, codes::RpbSetBucketResp, bytes)
+            .map(|response| match response {
+                Ok(_) => Ok(()),
+                Err(error) => Err(error),
+            })
+            .boxed()
+    }
+
+    /// Retrieves bucket properties for a bucket given a bucket name.
+    pub fn get_bucket_properties<T: Into<Vec<u8>>>(&self,
+                                                   bucket_name: T)
+                                                   -> RiakFuture<BucketProps> {
+        let mut req = RpbGetBucketReq::new();
+        req.set_bucket(bucket_name.into());
+
+        self.request(codes::RpbGetBucketReq, codes::RpbGetBucketResp, encode(&req))
+            .map(get_bucket_resp_to_bucket_props)
+            .boxed()
+    }
+
+    /// Assigns a set of bucket properties to a bucket type.
+    pub fn set_bucket_type_properties<T: Into<Vec<u8>>>(&self,
+                                                        bucket_type_name: T,
+                                                        bucket_type_props: &BucketProps)
+                                                        -> RiakFuture<()> {
+        let bytes = bucket_type_props.write_to_bytes(bucket_type_name.into(), true);
+        self.request(codes::RpbSetBucketTypeReq, codes::RpbSetBucketResp, bytes)
+            .map(|response| match response {
+                Ok(_) => Ok(()),
+                Err(error) => Err(error),
+            })
+            .boxed()
+    }
+
+    /// Gets the bucket properties associated with a bucket type.
+    pub fn get_bucket_type_properties<T: Into<Vec<u8>>>(&self,
+                                                        bucket_type_name: T)
+                                                        -> RiakFuture<BucketProps> {
+        let mut req = RpbGetBucketTypeReq::new();
+        req.set_field_type(bucket_type_name.into());
+
+        self.request(codes::RpbGetBucketTypeReq, codes::RpbGetBucketResp, encode(&req))
+            .map(get_bucket_resp_to_bucket_props)
+            .boxed()
+    }
+
+    /// Resets the properties for a bucket
+    pub fn reset_bucket<T: Into<Vec<u8>>>(&self,
+                                          bucket_type_name: T,
+                                          bucket_name: T)
+                                          -> RiakFuture<()> {
+        let mut req = RpbResetBucketReq::new();
+        req.set_field_type(bucket_type_name.into());
+        req.set_bucket(bucket_name.into());
+
+        self.request(codes::RpbResetBucketReq, codes::RpbResetBucketResp, encode(&req))
+            .map(|response| match response {
+                Ok(_) => Ok(()),
+                Err(error) => Err(error),
+            })
+            .boxed()
+    }
+
+    /// Produces a stream of keys from a bucket given a bucket name.
+    ///
+    /// Note: This operation requires traversing all keys stored in the cluster and should not be used in production.
+    pub fn stream_keys<T: Into<Vec<u8>>>(&self, bucket: T) -> AsyncKeyStream {
+        AsyncKeyStream {
+            state: StreamState::Unsent(self.requests.clone()),
+            bucket: bucket.into(),
+            bucket_type: None,
+            timeout: None,
+        }
+    }
+
+    /// Produces a list of keys provided a bucket name
+    ///
+    /// Note: This operation requires traversing all keys stored in the cluster and should not be used in production.
+    pub fn list_keys<T: Into<Vec<u8>>>(&self, bucket: T) -> RiakFuture<Vec<Vec<u8>>> {
+        self.stream_keys(bucket).try_concat().boxed()
+    }
+
+    /// Stores an object on the Riak server.
+    pub fn store_object(&self, req: &StoreObjectReq) -> RiakFuture<StoreObjectResp> {
+        self.request(codes::RpbPutReq, codes::RpbPutResp, req.write_to_bytes())
+            .map(|response| match parse::<RpbPutResp>(response) {
+                Ok(mut rpb_put_resp) => Ok(rpb_put_resp_to_store_object_resp(&mut rpb_put_resp)),
+                Err(error) => Err(error),
+            })
+            .boxed()
+    }
+
+    /// Fetches an object from the Riak server.
+    pub fn fetch_object(&self, req: &FetchObjectReq) -> RiakFuture<FetchObjectResp> {
+        self.request(codes::RpbGetReq, codes::RpbGetResp, req.write_to_bytes())
+            .map(|response| match parse::<RpbGetResp>(response) {
+                Ok(mut rpb_get_resp) => Ok(rpb_get_resp_to_fetch_object_resp(&mut rpb_get_resp)),
+                Err(error) => Err(error),
+            })
+            .boxed()
+    }
+
+    /// Deletes an object from Riak
+    pub fn delete_object(&self, request: &DeleteObjectReq) -> RiakFuture<()> {
+        self.request(codes::RpbDelReq, codes::RpbDelResp, request.write_to_bytes())
+            .map(|response| match response {
+                Ok(_) => Ok(()),
+                Err(error) => Err(error),
+            })
+            .boxed()
+    }
+
+    /// Fetch the preflist for a bucket/key combination.
+    pub fn fetch_preflist<T: Into<Vec<u8>>>(&self,
+                                            bucket: T,
+                                            key: T)
+                                            -> RiakFuture<Vec<PreflistItem>> {
+        let mut req = RpbGetBucketKeyPreflistReq::new();
+        req.set_bucket(bucket.into());
+        req.set_key(key.into());
+
+        self.request(codes::RpbGetBucketKeyPreflistReq,
+                     codes::RpbGetBucketKeyPreflistResp,
+                     encode(&req))
+            .map(|response| match parse::<RpbGetBucketKeyPreflistResp>(response) {
+                Ok(rpb_preflist_resp) => {
+                    let mut preflist: Vec<PreflistItem> = Vec::new();
+                    for preflist_item in rpb_preflist_resp.get_preflist() {
+                        let node = String::from_utf8_lossy(preflist_item.get_node()).into_owned();
+                        preflist.push(PreflistItem::new(preflist_item.get_partition(),
+                                                        &node,
+                                                        preflist_item.get_primary()));
+                    }
+                    Ok(preflist)
+                }
+                Err(error) => Err(error),
+            })
+            .boxed()
+    }
+
+    /// Create a search schema
+    pub fn set_yokozuna_schema<T: Into<Vec<u8>>>(&self, name: T, content: T) -> RiakFuture<()> {
+        let mut schema = RpbYokozunaSchema::new();
+        schema.set_name(name.into());
+        schema.set_content(content.into());
+        let mut req = RpbYokozunaSchemaPutReq::new();
+        req.set_schema(schema);
+
+        self.request(codes::RpbYokozunaSchemaPutReq, codes::RpbPutResp, encode(&req))
+            .map(|response| match response {
+                Ok(_) => Ok(()),
+                Err(error) => Err(error),
+            })
+            .boxed()
+    }
+
+    /// Retrieve a search schema
+    pub fn get_yokozuna_schema<T: Into<Vec<u8>>>(&self, name: T) -> RiakFuture<Vec<u8>> {
+        let mut req = RpbYokozunaSchemaGetReq::new();
+        req.set_name(name.into());
+
+        self.request(codes::RpbYokozunaSchemaGetReq,
+                     codes::RpbYokozunaSchemaGetResp,
+                     encode(&req))
+            .map(|response| match parse::<RpbYokozunaSchemaGetResp>(response) {
+                Ok(mut resp) => Ok(resp.take_schema().take_content()),
+                Err(error) => Err(error),
+            })
+            .boxed()
+    }
+
+    /// set a search index
+    pub fn set_yokozuna_index(&self, index: YokozunaIndex) -> RiakFuture<()> {
+        self.request(codes::RpbYokozunaIndexPutReq,
+                     codes::RpbPutResp,
+                     index.write_to_bytes())
+            .map(|response| match response {
+                Ok(_) => Ok(()),
+                Err(error) => Err(error),
+            })
+            .boxed()
+    }
+
+    /// get a search index
+    pub fn get_yokozuna_index<T: Into<Vec<u8>>>(&self,
+                                                name: T)
+                                                -> RiakFuture<Vec<YokozunaIndex>> {
+        let mut req = RpbYokozunaIndexGetReq::new();
+        req.set_name(name.into());
+
+        self.request(codes::RpbYokozunaIndexGetReq,
+                     codes::RpbYokozunaIndexGetResp,
+                     encode(&req))
+            .map(|response| match parse::<RpbYokozunaIndexGetResp>(response) {
+                Ok(mut resp) => {
+                    Ok(resp.take_index()
+                        .into_iter()
+                        .map(YokozunaIndex::new_from_rpb_yokozuna_index)
+                        .collect())
+                }
+                Err(error) => Err(error),
+            })
+            .boxed()
+    }
+
+    /// Deletes an index
+    pub fn delete_yokozuna_index<T: Into<Vec<u8>>>(&self, name: T) -> RiakFuture<()> {
+        let mut req = RpbYokozunaIndexDeleteReq::new();
+        req.set_name(name.into());
+
+        self.request(codes::RpbYokozunaIndexDeleteReq, codes::RpbDelResp, encode(&req))
+            .map(|response| match response {
+                Ok(_) => Ok(()),
+                Err(error) => Err(error),
+            })
+            .boxed()
+    }
+
+    /// Performs a search query against a Yokozuna index.
+    pub fn search(&self, query: &SearchQuery) -> RiakFuture<SearchResults> {
+        self.request(codes::RpbSearchQueryReq,
+                     codes::RpbSearchQueryResp,
+                     query.write_to_bytes())
+            .map(|response| match parse::<RpbSearchQueryResp>(response) {
+                Ok(mut resp) => Ok(rpb_search_query_resp_to_search_results(&mut resp)),
+                Err(error) => Err(error),
+            })
+            .boxed()
+    }
+
+    /// Fetches a Data Type (counter, set, map or HyperLogLog) from Riak.
+    pub fn fetch_datatype(&self, req: &FetchDataTypeReq) -> RiakFuture<FetchDataTypeResp> {
+        self.request(codes::DtFetchReq, codes::DtFetchResp, req.write_to_bytes())
+            .map(|response| match parse::<DtFetchResp>(response) {
+                Ok(dt_fetch_resp) => Ok(FetchDataTypeResp::new_from_dt_fetch_resp(dt_fetch_resp)),
+                Err(error) => Err(error),
+            })
+            .boxed()
+    }
+
+    /// Sends the local changes made to a Data Type to Riak.
+    pub fn update_datatype(&self, req: &UpdateDataTypeReq) -> RiakFuture<UpdateDataTypeResp> {
+        let req = req.clone();
+        self.request(codes::DtUpdateReq, codes::DtUpdateResp, req.write_to_bytes())
+            .map(move |response| match parse::<DtUpdateResp>(response) {
+                Ok(dt_update_resp) => Ok(req.new_response(dt_update_resp)),
+                Err(error) => Err(error),
+            })
+            .boxed()
+    }
+
+    /// Queries a secondary index, returning a single page of results.
+    pub fn query_index(&self, query: &IndexQuery) -> RiakFuture<IndexResults> {
+        let return_body = query.get_return_body().unwrap_or(false);
+        self.request(codes::RpbIndexReq,
+                     index_response_code(return_body),
+                     query.write_to_bytes(false))
+            .map(move |response| match response {
+                Ok(response) => {
+                    match index_response_to_index_results(&response, return_body) {
+                        Ok((results, _)) => Ok(results),
+                        Err(error) => Err(error),
+                    }
+                }
+                Err(error) => Err(error),
+            })
+            .boxed()
+    }
+
+    /// Produces a stream of results for a secondary index query.
+    pub fn stream_index(&self, query: &IndexQuery) -> AsyncIndexStream {
+        AsyncIndexStream {
+            state: StreamState::Unsent(self.requests.clone()),
+            query: query.clone(),
+        }
+    }
+
+    /// Runs a MapReduce job, producing a stream of `(phase, response)` chunks where the response
+    /// is the JSON encoded result of that phase.
+    pub fn mapreduce(&self, job: &MapReduceJob) -> AsyncMapReduceStream {
+        AsyncMapReduceStream {
+            state: StreamState::Unsent(self.requests.clone()),
+            job: job.clone(),
+        }
+    }
+}
+
+// Builds the `BucketProps` from the response to a bucket or bucket type properties request.
+fn get_bucket_resp_to_bucket_props(response: Result<Vec<u8>, RiakErr>)
+                                   -> Result<BucketProps, RiakErr> {
+    match parse::<RpbGetBucketResp>(response) {
+        Ok(mut rpb_get_bucket_resp) => {
+            let mut bucket_props = BucketProps::new();
+            bucket_props.set_props(rpb_get_bucket_resp.take_props());
+            Ok(bucket_props)
+        }
+        Err(error) => Err(error),
+    }
+}
+
+// The progress of a streamed request. The request is only queued when the stream is first
+// polled, so that the stream can still be configured after it is created.
+#[derive(Debug)]
+enum StreamState {
+    Unsent(mpsc::UnboundedSender<Request>),
+    Receiving(mpsc::UnboundedReceiver<Result<Vec<u8>, RiakErr>>, fn(&[u8]) -> bool),
+    Done,
+}
+
+impl StreamState {
+    // Queues the request for the stream if that hasn't been done yet.
+    fn start<F>(&mut self,
+                code: u8,
+                response_code: u8,
+                is_done: fn(&[u8]) -> bool,
+                message: F)
+                -> Result<(), RiakErr>
+        where F: FnOnce() -> Result<Vec<u8>, RiakErr>
+    {
+        let queued = match *self {
+            StreamState::Unsent(ref requests) => {
+                let (sender, receiver) = mpsc::unbounded();
+                let queued = match message() {
+                    Ok(message) => {
+                        queue(requests,
+                              Request {
+                                  code: code,
+                                  message: message,
+                                  response_code: response_code,
+                                  responder: Responder::Stream(sender, is_done),
+                              })
+                    }
+                    Err(error) => Err(error),
+                };
+                match queued {
+                    Ok(()) => Ok(receiver),
+                    Err(error) => Err(error),
+                }
+            }
+            _ => return Ok(()),
+        };
+
+        match queued {
+            Ok(receiver) => {
+                *self = StreamState::Receiving(receiver, is_done);
+                Ok(())
+            }
+            Err(error) => {
+                *self = StreamState::Done;
+                Err(error)
+            }
+        }
+    }
+
+    // Polls for the next response, the stream ends after the last response or an error.
+    fn poll_response(&mut self, cx: &mut Context) -> Poll<Option<Result<Vec<u8>, RiakErr>>> {
+        let (polled, is_done) = match *self {
+            StreamState::Receiving(ref mut receiver, is_done) => {
+                (Pin::new(receiver).poll_next(cx), is_done)
+            }
+            _ => return Poll::Ready(None),
+        };
+
+        match polled {
+            Poll::Ready(Some(Ok(response))) => {
+                if is_done(&response) {
+                    *self = StreamState::Done;
+                }
+                Poll::Ready(Some(Ok(response)))
+            }
+            Poll::Ready(Some(Err(error))) => {
+                *self = StreamState::Done;
+                Poll::Ready(Some(Err(error)))
+            }
+            Poll::Ready(None) => {
+                // the connection went away before the last response
+                *self = StreamState::Done;
+                Poll::Ready(Some(Err(connection_closed())))
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+// The timeout (in milliseconds) sent to Riak for a request, the one set on the stream or else the
+// default timeout (in seconds) of a `Client`.
+fn request_timeout(timeout: Option<u32>) -> u32 {
+    match timeout {
+        Some(timeout) => timeout,
+        None => DEFAULT_TIMEOUT.saturating_mul(1000),
+    }
+}
+
+fn list_buckets_done(response: &[u8]) -> bool {
+    match parse_from_bytes::<RpbListBucketsResp>(response) {
+        Ok(resp) => resp.get_done(),
+        Err(_) => true,
+    }
+}
+
+fn list_keys_done(response: &[u8]) -> bool {
+    match parse_from_bytes::<RpbListKeysResp>(response) {
+        Ok(resp) => resp.get_done(),
+        Err(_) => true,
+    }
+}
+
+fn index_done(response: &[u8]) -> bool {
+    match index_response_to_index_results(response, false) {
+        Ok((_, done)) => done,
+        Err(_) => true,
+    }
+}
+
+fn index_body_done(response: &[u8]) -> bool {
+    match index_response_to_index_results(response, true) {
+        Ok((_, done)) => done,
+        Err(_) => true,
+    }
+}
+
+fn mapreduce_done(response: &[u8]) -> bool {
+    match parse_from_bytes::<RpbMapRedResp>(response) {
+        Ok(resp) => resp.get_done(),
+        Err(_) => true,
+    }
+}
+
+/// `AsyncBucketStream` is a `Stream` of groups of bucket names in Riak
+///
+/// The request is sent when the stream is first polled. If the stream is dropped early the rest
+/// of the responses are discarded as they arrive.
+#[derive(Debug)]
+pub struct AsyncBucketStream {
+    state: StreamState,
+    bucket_type: Option<Vec<u8>>,
+    timeout: Option<u32>,
+}
+
+impl AsyncBucketStream {
+    /// get the bucket type that buckets are listed from
+    pub fn get_bucket_type(&self) -> Option<Vec<u8>> {
+        self.bucket_type.clone()
+    }
+
+    /// set the bucket type to list buckets from, this must be set before the stream is polled
+    pub fn set_bucket_type<T: Into<Vec<u8>>>(&mut self, bucket_type: T) {
+        self.bucket_type = Some(bucket_type.into());
+    }
+
+    /// get the timeout sent to Riak for this request
+    pub fn get_timeout(&self) -> Option<u32> {
+        self.timeout
+    }
+
+    /// set the timeout (in milliseconds) sent to Riak for this request, this must be set before
+    /// the stream is polled
+    pub fn set_timeout(&mut self, timeout: u32) {
+        self.timeout = Some(timeout);
+    }
+}
+
+impl Stream for AsyncBucketStream {
+    type Item = Result<Vec<Vec<u8>>, RiakErr>;
+
+    fn poll_next(mut self: Pin<&mut Self>,
+                 cx: &mut Context)
+                 -> Poll<Option<Result<Vec<Vec<u8>>, RiakErr>>> {
+        let this = &mut *self;
+
+        let bucket_type = &this.bucket_type;
+        let timeout = this.timeout;
+        let started = this.state.start(codes::RpbListBucketsReq,
+                                       codes::RpbListBucketsResp,
+                                       list_buckets_done,
+                                       || {
+            let mut request = RpbListBucketsReq::new();
+            request.set_stream(true);
+            request.set_timeout(request_timeout(timeout));
+            if let Some(ref bucket_type) = *bucket_type {
+                request.set_field_type(bucket_type.clone());
+            }
+            encode(&request)
+        });
+        if let Err(error) = started {
+            return Poll::Ready(Some(Err(error)));
+        }
+
+        match this.state.poll_response(cx) {
+            Poll::Ready(Some(response)) => {
+                match parse::<RpbListBucketsResp>(response) {
+                    Ok(mut resp) => Poll::Ready(Some(Ok(resp.take_buckets().into_vec()))),
+                    Err(error) => Poll::Ready(Some(Err(error))),
+                }
+            }
+            Poll::Ready(None) => Poll::Ready(None),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+/// `AsyncKeyStream` is a `Stream` of groups of keys in a Riak bucket
+///
+/// The request is sent when the stream is first polled. If the stream is dropped early the rest
+/// of the responses are discarded as they arrive.
+#[derive(Debug)]
+pub struct AsyncKeyStream {
+    state: StreamState,
+    bucket: Vec<u8>,
+    bucket_type: Option<Vec<u8>>,
+    timeout: Option<u32>,
+}
+
+impl AsyncKeyStream {
+    /// get the bucket type of the bucket the keys are listed from
+    pub fn get_bucket_type(&self) -> Option<Vec<u8>> {
+        self.bucket_type.clone()
+    }
+
+    /// set the bucket type of the bucket to list keys from, this must be set before the stream
+    /// is polled
+    pub fn set_bucket_type<T: Into<Vec<u8>>>(&mut self, bucket_type: T) {
+        self.bucket_type = Some(bucket_type.into());
+    }
+
+    /// get the timeout sent to Riak for this request
+    pub fn get_timeout(&self) -> Option<u32> {
+        self.timeout
+    }
+
+    /// set the timeout (in milliseconds) sent to Riak for this request, this must be set before
+    /// the stream is polled
+    pub fn set_timeout(&mut self, timeout: u32) {
+        self.timeout = Some(timeout);
+    }
+}
+
+impl Stream for AsyncKeyStream {
+    type Item = Result<Vec<Vec<u8>>, RiakErr>;
+
+    fn poll_next(mut self: Pin<&mut Self>,
+                 cx: &mut Context)
+                 -> Poll<Option<Result<Vec<Vec<u8>>, RiakErr>>> {
+        let this = &mut *self;
+
+        let bucket = &this.bucket;
+        let bucket_type = &this.bucket_type;
+        let timeout = this.timeout;
+        let started = this.state.start(codes::RpbListKeysReq,
+                                       codes::RpbListKeysResp,
+                                       list_keys_done,
+                                       || {
+            let mut request = RpbListKeysReq::new();
+            request.set_bucket(bucket.clone());
+            request.set_timeout(request_timeout(timeout));
+            if let Some(ref bucket_type) = *bucket_type {
+                request.set_field_type(bucket_type.clone());
+            }
+            encode(&request)
+        });
+        if let Err(error) = started {
+            return Poll::Ready(Some(Err(error)));
+        }
+
+        match this.state.poll_response(cx) {
+            Poll::Ready(Some(response)) => {
+                match parse::<RpbListKeysResp>(response) {
+                    Ok(mut resp) => Poll::Ready(Some(Ok(resp.take_keys().into_vec()))),
+                    Err(error) => Poll::Ready(Some(Err(error))),
+                }
+            }
+            Poll::Ready(None) => Poll::Ready(None),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+/// `AsyncIndexStream` is a `Stream` of the results of a secondary index query
+///
+/// The request is sent when the stream is first polled. If the stream is dropped early the rest
+/// of the responses are discarded as they arrive.
+#[derive(Debug)]
+pub struct AsyncIndexStream {
+    state: StreamState,
+    query: IndexQuery,
+}
+
+impl Stream for AsyncIndexStream {
+    type Item = Result<IndexResults, RiakErr>;
+
+    fn poll_next(mut self: Pin<&mut Self>,
+                 cx: &mut Context)
+                 -> Poll<Option<Result<IndexResults, RiakErr>>> {
+        let this = &mut *self;
+
+        let query = &this.query;
+        let return_body = query.get_return_body().unwrap_or(false);
+        let is_done: fn(&[u8]) -> bool = if return_body { index_body_done } else { index_done };
+        let started = this.state.start(codes::RpbIndexReq,
+                                       index_response_code(return_body),
+                                       is_done,
+                                       || query.write_to_bytes(true));
+        if let Err(error) = started {
+            return Poll::Ready(Some(Err(error)));
+        }
+
+        match this.state.poll_response(cx) {
+            Poll::Ready(Some(Ok(response))) => {
+                match index_response_to_index_results(&response, return_body) {
+                    Ok((results, _)) => Poll::Ready(Some(Ok(results))),
+                    Err(error) => Poll::Ready(Some(Err(error))),
+                }
+            }
+            Poll::Ready(Some(Err(error))) => Poll::Ready(Some(Err(error))),
+            Poll::Ready(None) => Poll::Ready(None),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+/// `AsyncMapReduceStream` is a `Stream` of the `(phase, response)` chunks of a MapReduce job
+///
+/// The job is sent when the stream is first polled. If the stream is dropped early the rest of
+/// the responses are discarded as they arrive.
+#[derive(Debug)]
+pub struct AsyncMapReduceStream {
+    state: StreamState,
+    job: MapReduceJob,
+}
+
+impl Stream for AsyncMapReduceStream {
+    type Item = Result<(u32, Vec<u8>), RiakErr>;
+
+    fn poll_next(mut self: Pin<&mut Self>,
+                 cx: &mut Context)
+                 -> Poll<Option<Result<(u32, Vec<u8>), RiakErr>>> {
+        let this = &mut *self;
+
+        let job = &this.job;
+        let started = this.state.start(codes::RpbMapRedReq,
+                                       codes::RpbMapRedResp,
+                                       mapreduce_done,
+                                       || job.write_to_bytes());
+        if let Err(error) = started {
+            return Poll::Ready(Some(Err(error)));
+        }
+
+        // responses without any results (such as the final one) are skipped
+        loop {
+            match this.state.poll_response(cx) {
+                Poll::Ready(Some(response)) => {
+                    match parse::<RpbMapRedResp>(response) {
+                        Ok(mut resp) => {
+                            if resp.has_response() {
+                                return Poll::Ready(Some(Ok((resp.get_phase(),
+                                                            resp.take_response()))));
+                            }
+                        }
+                        Err(error) => return Poll::Ready(Some(Err(error))),
+                    };
+                }
+                Poll::Ready(None) => return Poll::Ready(None),
+                Poll::Pending => return Poll::Pending,
+            };
+        }
+    }
+}
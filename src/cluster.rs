@@ -0,0 +1,444 @@
+/// A client for a cluster of Riak nodes.
+///
+/// A `Cluster` keeps a `Pool` of connections for each node and spreads requests across the
+/// nodes that are up. Nodes are marked down when a request to them fails with an I/O error, and
+/// are probed with a ping once the probe interval has passed before being used again. Idempotent
+/// requests that fail with an I/O error are retried on another node.
+
+use Client;
+use datatypes::{FetchDataTypeReq, FetchDataTypeResp, UpdateDataTypeReq, UpdateDataTypeResp};
+use errors::RiakErr;
+use index::{IndexQuery, IndexResults};
+use object::{DeleteObjectReq, FetchObjectReq, FetchObjectResp, StoreObjectReq, StoreObjectResp};
+use pool::{Pool, PoolConfig};
+use preflist::PreflistItem;
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+use yokozuna::{SearchQuery, SearchResults};
+
+// Defaults
+static DEFAULT_MAX_RETRIES: usize = 2;
+static DEFAULT_PROBE_INTERVAL_SECS: u64 = 5;
+
+/// `LoadBalancing` is the strategy used to pick the node for each request.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LoadBalancing {
+    /// send requests to each node in turn
+    RoundRobin,
+    /// send requests to the node with the fewest requests in flight
+    LeastOutstanding,
+}
+
+/// `ClusterConfig` represents the settings used to build a `Cluster`.
+#[derive(Clone, Debug)]
+pub struct ClusterConfig {
+    load_balancing: LoadBalancing,
+    max_retries: usize,
+    probe_interval: Duration,
+    pool_config: PoolConfig,
+}
+
+impl ClusterConfig {
+    /// constructs a new `ClusterConfig` with default settings
+    pub fn new() -> ClusterConfig {
+        ClusterConfig {
+            load_balancing: LoadBalancing::RoundRobin,
+            max_retries: DEFAULT_MAX_RETRIES,
+            probe_interval: Duration::from_secs(DEFAULT_PROBE_INTERVAL_SECS),
+            pool_config: PoolConfig::new(),
+        }
+    }
+
+    /// get the load balancing strategy
+    pub fn get_load_balancing(&self) -> LoadBalancing {
+        self.load_balancing
+    }
+
+    /// set the load balancing strategy
+    pub fn set_load_balancing(&mut self, load_balancing: LoadBalancing) {
+        self.load_balancing = load_balancing;
+    }
+
+    /// get the number of times an idempotent request is retried on another node
+    pub fn get_max_retries(&self) -> usize {
+        self.max_retries
+    }
+
+    /// set the number of times an idempotent request is retried on another node
+    pub fn set_max_retries(&mut self, max_retries: usize) {
+        self.max_retries = max_retries;
+    }
+
+    /// get how long a node stays down before it is probed again
+    pub fn get_probe_interval(&self) -> Duration {
+        self.probe_interval
+    }
+
+    /// set how long a node stays down before it is probed again
+    pub fn set_probe_interval(&mut self, probe_interval: Duration) {
+        self.probe_interval = probe_interval;
+    }
+
+    /// get the settings used for the connection pool of each node
+    pub fn get_pool_config(&self) -> PoolConfig {
+        self.pool_config.clone()
+    }
+
+    /// set the settings used for the connection pool of each node
+    pub fn set_pool_config(&mut self, pool_config: PoolConfig) {
+        self.pool_config = pool_config;
+    }
+}
+
+// The health of a node and its connection pool, which is opened lazily.
+#[derive(Debug)]
+struct NodeState {
+    pool: Option<Pool>,
+    down_since: Option<Instant>,
+}
+
+#[derive(Debug)]
+struct Node {
+    addr: SocketAddr,
+    outstanding: AtomicUsize,
+    state: Mutex<NodeState>,
+}
+
+impl Node {
+    fn lock(&self) -> MutexGuard<'_, NodeState> {
+        match self.state.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+
+    fn is_up(&self) -> bool {
+        self.lock().down_since.is_none()
+    }
+
+    fn mark_down(&self) {
+        let mut state = self.lock();
+        if state.down_since.is_none() {
+            warn!("marking Riak node {} down", self.addr);
+        }
+        state.down_since = Some(Instant::now());
+        state.pool = None;
+    }
+
+    fn mark_up(&self) {
+        let mut state = self.lock();
+        if state.down_since.is_some() {
+            info!("marking Riak node {} up", self.addr);
+        }
+        state.down_since = None;
+    }
+
+    // Whether or not a down node is due to be probed. The probe interval starts again when it
+    // is, so that only one request probes the node.
+    fn probe_due(&self, probe_interval: Duration) -> bool {
+        let mut state = self.lock();
+        match state.down_since {
+            Some(since) if since.elapsed() >= probe_interval => {
+                state.down_since = Some(Instant::now());
+                true
+            }
+            _ => false,
+        }
+    }
+
+    // Get the pool for this node, opening it if needed. The pool is opened without holding the
+    // lock, so that a slow node doesn't hold up every other request selecting it.
+    fn pool(&self, pool_config: &PoolConfig) -> Result<Pool, RiakErr> {
+        if let Some(ref pool) = self.lock().pool {
+            return Ok(pool.clone());
+        }
+
+        let pool = match Pool::new(self.addr, pool_config.clone()) {
+            Ok(pool) => pool,
+            Err(error) => return Err(error),
+        };
+
+        // another request may have opened a pool in the meantime
+        let mut state = self.lock();
+        if let Some(ref pool) = state.pool {
+            return Ok(pool.clone());
+        }
+        state.pool = Some(pool.clone());
+        Ok(pool)
+    }
+}
+
+#[derive(Debug)]
+struct ClusterInner {
+    config: ClusterConfig,
+    nodes: Vec<Node>,
+    next: AtomicUsize,
+}
+
+/// `Cluster` represents a thread-safe client for a set of Riak nodes.
+///
+/// Cloning a `Cluster` is cheap and produces a handle to the same nodes and connections.
+///
+/// # Examples
+///
+/// ```
+/// use riak::cluster::{Cluster, ClusterConfig};
+/// use riak::object::FetchObjectReq;
+///
+/// let nodes = ["10.0.0.2:8087", "10.0.0.3:8087", "10.0.0.4:8087"];
+/// let cluster = Cluster::new(&nodes, ClusterConfig::new()).unwrap();
+///
+/// let req = FetchObjectReq::new("testbucket", "testkey");
+/// let object = cluster.fetch_object(&req).unwrap();
+/// println!("testkey object contained: {:?}", object);
+/// ```
+#[derive(Clone, Debug)]
+pub struct Cluster {
+    inner: Arc<ClusterInner>,
+}
+
+impl Cluster {
+    /// Constructs a new `Cluster` from a list of node addresses.
+    ///
+    /// No connections are opened until the first request is made to each node.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any address can not be resolved, or if no addresses were given.
+    pub fn new<A: ToSocketAddrs>(addrs: &[A], config: ClusterConfig) -> Result<Cluster, RiakErr> {
+        let mut nodes: Vec<Node> = Vec::new();
+        for addr in addrs.iter() {
+            let resolved = match addr.to_socket_addrs() {
+                Ok(resolved) => resolved,
+                Err(error) => return Err(RiakErr::IoError(error)),
+            };
+            for addr in resolved {
+                nodes.push(Node {
+                    addr: addr,
+                    outstanding: AtomicUsize::new(0),
+                    state: Mutex::new(NodeState {
+                        pool: None,
+                        down_since: None,
+                    }),
+                });
+            }
+        }
+
+        if nodes.is_empty() {
+            return Err(RiakErr::NoNodesAvailable);
+        }
+
+        Ok(Cluster {
+            inner: Arc::new(ClusterInner {
+                config: config,
+                nodes: nodes,
+                next: AtomicUsize::new(0),
+            }),
+        })
+    }
+
+    /// Lists the address of every node along with whether or not it is currently up.
+    pub fn nodes(&self) -> Vec<(SocketAddr, bool)> {
+        self.inner.nodes.iter().map(|node| (node.addr, node.is_up())).collect()
+    }
+
+    /// Pings every node that is marked down, marking it up again if it responds.
+    pub fn probe_down_nodes(&self) {
+        for node in self.inner.nodes.iter() {
+            if !node.is_up() {
+                self.probe(node);
+            }
+        }
+    }
+
+    /// Runs `operation` with a client connected to one of the nodes, without retrying.
+    ///
+    /// The node is marked down if the operation fails with an I/O error. Use this for requests
+    /// that are not safe to repeat, such as storing an object without a key.
+    ///
+    /// # Errors
+    ///
+    /// Returns `RiakErr::NoNodesAvailable` if every node is down, or the error of the operation.
+    pub fn execute<T, F>(&self, mut operation: F) -> Result<T, RiakErr>
+        where F: FnMut(&mut Client) -> Result<T, RiakErr>
+    {
+        let mut tried: Vec<usize> = Vec::new();
+        self.attempt(&mut operation, &mut tried)
+    }
+
+    /// Runs `operation` with a client connected to one of the nodes, retrying it on another node
+    /// if it fails with an I/O error.
+    ///
+    /// # Errors
+    ///
+    /// Returns `RiakErr::NoNodesAvailable` if every node is down, or the last error of the
+    /// operation once all retries or all nodes are used up.
+    pub fn execute_idempotent<T, F>(&self, mut operation: F) -> Result<T, RiakErr>
+        where F: FnMut(&mut Client) -> Result<T, RiakErr>
+    {
+        let mut tried: Vec<usize> = Vec::new();
+        let mut last_error: Option<RiakErr> = None;
+
+        for _ in 0..(self.inner.config.max_retries + 1) {
+            let error = match self.attempt(&mut operation, &mut tried) {
+                Err(RiakErr::NoNodesAvailable) => break,
+                Err(RiakErr::IoError(error)) => RiakErr::IoError(error),
+                result => return result,
+            };
+            debug!("retrying request on another node after error: {:?}", error);
+            last_error = Some(error);
+        }
+
+        match last_error {
+            Some(error) => Err(error),
+            None => Err(RiakErr::NoNodesAvailable),
+        }
+    }
+
+    /// Sends a ping to one of the nodes.
+    pub fn ping(&self) -> Result<(), RiakErr> {
+        self.execute_idempotent(|client| client.ping())
+    }
+
+    /// Fetches an object, retrying on another node if needed.
+    pub fn fetch_object(&self, req: &FetchObjectReq) -> Result<FetchObjectResp, RiakErr> {
+        self.execute_idempotent(|client| client.fetch_object(req))
+    }
+
+    /// Stores an object, this is only retried on another node when a key is provided.
+    pub fn store_object(&self, req: &StoreObjectReq) -> Result<StoreObjectResp, RiakErr> {
+        if req.get_key().is_some() {
+            self.execute_idempotent(|client| client.store_object(req))
+        } else {
+            self.execute(|client| client.store_object(req))
+        }
+    }
+
+    /// Deletes an object, retrying on another node if needed.
+    pub fn delete_object(&self, req: &DeleteObjectReq) -> Result<(), RiakErr> {
+        self.execute_idempotent(|client| client.delete_object(req))
+    }
+
+    /// Fetches the preflist for a bucket/key combination, retrying on another node if needed.
+    pub fn fetch_preflist<T: Into<Vec<u8>>>(&self,
+                                            bucket: T,
+                                            key: T)
+                                            -> Result<Vec<PreflistItem>, RiakErr> {
+        let bucket = bucket.into();
+        let key = key.into();
+        self.execute_idempotent(|client| client.fetch_preflist(bucket.clone(), key.clone()))
+    }
+
+    /// Fetches a Data Type, retrying on another node if needed.
+    pub fn fetch_datatype(&self, req: &FetchDataTypeReq) -> Result<FetchDataTypeResp, RiakErr> {
+        self.execute_idempotent(|client| client.fetch_datatype(req))
+    }
+
+    /// Updates a Data Type, this is not retried since the operations may have been applied.
+    pub fn update_datatype(&self, req: &UpdateDataTypeReq) -> Result<UpdateDataTypeResp, RiakErr> {
+        self.execute(|client| client.update_datatype(req))
+    }
+
+    /// Queries a secondary index, retrying on another node if needed.
+    pub fn query_index(&self, query: &IndexQuery) -> Result<IndexResults, RiakErr> {
+        self.execute_idempotent(|client| client.query_index(query))
+    }
+
+    /// Performs a search query, retrying on another node if needed.
+    pub fn search(&self, query: &SearchQuery) -> Result<SearchResults, RiakErr> {
+        self.execute_idempotent(|client| client.search(query))
+    }
+
+    // Run the operation once on a node that hasn't been tried yet.
+    fn attempt<T, F>(&self, operation: &mut F, tried: &mut Vec<usize>) -> Result<T, RiakErr>
+        where F: FnMut(&mut Client) -> Result<T, RiakErr>
+    {
+        let index = match self.select(tried) {
+            Some(index) => index,
+            None => return Err(RiakErr::NoNodesAvailable),
+        };
+        tried.push(index);
+        let node = &self.inner.nodes[index];
+
+        let pool = match node.pool(&self.inner.config.pool_config) {
+            Ok(pool) => pool,
+            Err(error) => {
+                node.mark_down();
+                return Err(error);
+            }
+        };
+
+        let mut client = match pool.get() {
+            Ok(client) => client,
+            Err(RiakErr::IoError(error)) => {
+                node.mark_down();
+                return Err(RiakErr::IoError(error));
+            }
+            Err(error) => return Err(error),
+        };
+
+        node.outstanding.fetch_add(1, Ordering::SeqCst);
+        let result = operation(&mut client);
+        node.outstanding.fetch_sub(1, Ordering::SeqCst);
+
+        if let Err(RiakErr::IoError(_)) = result {
+            client.discard();
+            node.mark_down();
+        }
+
+        result
+    }
+
+    // Pick a node that hasn't been tried yet from the nodes that are up, after probing the nodes
+    // that are down and due to be probed so that the ones which recovered can be picked too.
+    fn select(&self, tried: &[usize]) -> Option<usize> {
+        let nodes = &self.inner.nodes;
+        let start = self.inner.next.fetch_add(1, Ordering::SeqCst);
+        let order: Vec<usize> = (0..nodes.len())
+            .map(|offset| (start + offset) % nodes.len())
+            .filter(|index| !tried.contains(index))
+            .collect();
+
+        let probe_interval = self.inner.config.probe_interval;
+        for &index in order.iter() {
+            if nodes[index].probe_due(probe_interval) {
+                self.probe(&nodes[index]);
+            }
+        }
+
+        let up = order.iter().cloned().filter(|&index| nodes[index].is_up());
+        match self.inner.config.load_balancing {
+            LoadBalancing::RoundRobin => up.take(1).next(),
+            LoadBalancing::LeastOutstanding => {
+                up.min_by_key(|&index| nodes[index].outstanding.load(Ordering::SeqCst))
+            }
+        }
+    }
+
+    // Ping a node, marking it up if it responds and down if it doesn't.
+    fn probe(&self, node: &Node) -> bool {
+        let result = match node.pool(&self.inner.config.pool_config) {
+            Ok(pool) => {
+                match pool.get() {
+                    Ok(mut client) => client.ping(),
+                    Err(error) => Err(error),
+                }
+            }
+            Err(error) => Err(error),
+        };
+
+        match result {
+            Ok(()) => {
+                node.mark_up();
+                true
+            }
+            Err(error) => {
+                debug!("probe of Riak node {} failed: {:?}", node.addr, error);
+                node.mark_down();
+                false
+            }
+        }
+    }
+}
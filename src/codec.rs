@@ -0,0 +1,190 @@
+/// Typed object values, encoded and decoded with serde.
+///
+/// A `Codec` encodes values into the contents of objects and sets their "content_type" and
+/// "charset" to match, so other clients can tell how a value was encoded. Decoding looks at the
+/// "content_type" of an object to pick the codec it was encoded with, so objects written with
+/// different codecs can be read back the same way.
+///
+/// This module requires the "serde" feature.
+///
+/// # Examples
+///
+/// ```
+/// use riak::codec::{Codec, JsonCodec, decode_content};
+///
+/// let content = JsonCodec.to_content(&vec![1, 2, 3]).unwrap();
+/// assert_eq!(content.get_content_type(), Some(b"application/json".to_vec()));
+///
+/// let value: Vec<u32> = decode_content(&content).unwrap();
+/// assert_eq!(value, vec![1, 2, 3]);
+/// ```
+
+use ciborium;
+use errors::RiakErr;
+use object::ObjectContent;
+use rmp_serde;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json;
+
+/// The content type of objects encoded with `JsonCodec`
+pub static JSON_CONTENT_TYPE: &str = "application/json";
+
+/// The content type of objects encoded with `CborCodec`
+pub static CBOR_CONTENT_TYPE: &str = "application/cbor";
+
+/// The content type of objects encoded with `MessagePackCodec`
+pub static MESSAGEPACK_CONTENT_TYPE: &str = "application/msgpack";
+
+// Another content type in common use for MessagePack.
+static MESSAGEPACK_ALT_CONTENT_TYPE: &str = "application/x-msgpack";
+
+/// `Codec` is implemented by types that encode values into the contents of objects and decode
+/// them back.
+pub trait Codec {
+    /// the content type of objects encoded with this codec
+    fn get_content_type(&self) -> &'static str;
+
+    /// the charset of objects encoded with this codec, for text based encodings
+    fn get_charset(&self) -> Option<&'static str> {
+        None
+    }
+
+    /// encodes `value` into bytes
+    fn encode<T: Serialize + ?Sized>(&self, value: &T) -> Result<Vec<u8>, RiakErr>;
+
+    /// decodes a value from `bytes`
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T, RiakErr>;
+
+    /// encodes `value` into new `ObjectContent`, with the content type and charset set
+    fn to_content<T: Serialize + ?Sized>(&self, value: &T) -> Result<ObjectContent, RiakErr> {
+        let mut content = ObjectContent::new(Vec::new());
+        match self.encode_into(value, &mut content) {
+            Ok(()) => Ok(content),
+            Err(error) => Err(error),
+        }
+    }
+
+    /// encodes `value` into existing `ObjectContent`, replacing its value and setting the
+    /// content type and charset, any other metadata such as indexes is kept
+    fn encode_into<T: Serialize + ?Sized>(&self,
+                                          value: &T,
+                                          content: &mut ObjectContent)
+                                          -> Result<(), RiakErr> {
+        let bytes = match self.encode(value) {
+            Ok(bytes) => bytes,
+            Err(error) => return Err(error),
+        };
+
+        content.set_value(bytes);
+        content.set_content_type(self.get_content_type());
+        if let Some(charset) = self.get_charset() {
+            content.set_charset(charset);
+        }
+        Ok(())
+    }
+}
+
+/// `JsonCodec` encodes values as UTF-8 JSON
+#[derive(Clone, Copy, Debug)]
+pub struct JsonCodec;
+
+impl Codec for JsonCodec {
+    fn get_content_type(&self) -> &'static str {
+        JSON_CONTENT_TYPE
+    }
+
+    fn get_charset(&self) -> Option<&'static str> {
+        Some("UTF-8")
+    }
+
+    fn encode<T: Serialize + ?Sized>(&self, value: &T) -> Result<Vec<u8>, RiakErr> {
+        match serde_json::to_vec(value) {
+            Ok(bytes) => Ok(bytes),
+            Err(error) => Err(RiakErr::CodecError(error.to_string())),
+        }
+    }
+
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T, RiakErr> {
+        match serde_json::from_slice(bytes) {
+            Ok(value) => Ok(value),
+            Err(error) => Err(RiakErr::CodecError(error.to_string())),
+        }
+    }
+}
+
+/// `CborCodec` encodes values as CBOR
+#[derive(Clone, Copy, Debug)]
+pub struct CborCodec;
+
+impl Codec for CborCodec {
+    fn get_content_type(&self) -> &'static str {
+        CBOR_CONTENT_TYPE
+    }
+
+    fn encode<T: Serialize + ?Sized>(&self, value: &T) -> Result<Vec<u8>, RiakErr> {
+        let mut bytes: Vec<u8> = Vec::new();
+        match ciborium::ser::into_writer(value, &mut bytes) {
+            Ok(()) => Ok(bytes),
+            Err(error) => Err(RiakErr::CodecError(error.to_string())),
+        }
+    }
+
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T, RiakErr> {
+        match ciborium::de::from_reader(bytes) {
+            Ok(value) => Ok(value),
+            Err(error) => Err(RiakErr::CodecError(error.to_string())),
+        }
+    }
+}
+
+/// `MessagePackCodec` encodes values as MessagePack, with structs encoded as maps so that fields
+/// can be added or reordered later
+#[derive(Clone, Copy, Debug)]
+pub struct MessagePackCodec;
+
+impl Codec for MessagePackCodec {
+    fn get_content_type(&self) -> &'static str {
+        MESSAGEPACK_CONTENT_TYPE
+    }
+
+    fn encode<T: Serialize + ?Sized>(&self, value: &T) -> Result<Vec<u8>, RiakErr> {
+        match rmp_serde::to_vec_named(value) {
+            Ok(bytes) => Ok(bytes),
+            Err(error) => Err(RiakErr::CodecError(error.to_string())),
+        }
+    }
+
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T, RiakErr> {
+        match rmp_serde::from_slice(bytes) {
+            Ok(value) => Ok(value),
+            Err(error) => Err(RiakErr::CodecError(error.to_string())),
+        }
+    }
+}
+
+/// decodes the value of `content` with the codec matching its "content_type"
+///
+/// # Errors
+///
+/// Returns `RiakErr::CodecError` if the content type is missing or not one of the codecs in this
+/// module, or if the value can not be decoded.
+pub fn decode_content<T: DeserializeOwned>(content: &ObjectContent) -> Result<T, RiakErr> {
+    let content_type = match content.get_content_type() {
+        Some(content_type) => String::from_utf8_lossy(&content_type).into_owned(),
+        None => return Err(RiakErr::CodecError("object has no content type".to_string())),
+    };
+
+    // parameters such as "; charset=utf-8" do not change how the value is decoded
+    let media_type = content_type.split(';').next().unwrap_or("").trim().to_lowercase();
+    let value = content.get_value();
+    if media_type == JSON_CONTENT_TYPE {
+        JsonCodec.decode(&value)
+    } else if media_type == CBOR_CONTENT_TYPE {
+        CborCodec.decode(&value)
+    } else if media_type == MESSAGEPACK_CONTENT_TYPE || media_type == MESSAGEPACK_ALT_CONTENT_TYPE {
+        MessagePackCodec.decode(&value)
+    } else {
+        Err(RiakErr::CodecError(format!("no codec for content type {:?}", content_type)))
+    }
+}
@@ -0,0 +1,213 @@
+/// Transparent compression of object values, recorded in their "content_encoding".
+///
+/// With a `CompressionConfig` set on a `Client`, `store_object` compresses values at least as
+/// large as the configured threshold and sets "content_encoding" to the algorithm used.
+/// `fetch_object` decompresses any value with a recognised "content_encoding" and removes the
+/// encoding, whether or not compression is enabled on the `Client`, so every client reading a
+/// bucket only needs this feature to read compressed values.
+///
+/// Responses holding only metadata, fetched with "head", stored with "return_head" and without
+/// "return_body", or fetched with "if_modified" and unchanged, have empty values that are left as
+/// they are, with their "content_encoding" kept so it can be told which values are compressed.
+///
+/// The encodings are the HTTP `Content-Encoding` tokens "gzip" and "zstd", so Riak's HTTP
+/// interface serves compressed objects with a `Content-Encoding` header that HTTP clients decode
+/// on their own. Snappy has no registered token, it is stored as "snappy" in the snappy framing
+/// format. Values that already have a "content_encoding" are stored as they are, and values with
+/// an encoding that is not recognised are fetched as they are.
+///
+/// Values read by other requests, such as `Client::fold_objects`, are not decompressed, use
+/// `decompress_contents` for those.
+///
+/// This module requires the "compression" feature.
+
+use errors::RiakErr;
+use flate2::Compression as GzipLevel;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use object::ObjectContent;
+use snap;
+use std::io::{Read, Write};
+use zstd;
+
+// The compression level used for zstd, its own default.
+static ZSTD_LEVEL: i32 = 0;
+
+/// `Compression` is an algorithm values can be compressed with
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Compression {
+    Gzip,
+    Zstd,
+    Snappy,
+}
+
+impl Compression {
+    /// the "content_encoding" of values compressed with this algorithm
+    pub fn get_content_encoding(&self) -> &'static str {
+        match *self {
+            Compression::Gzip => "gzip",
+            Compression::Zstd => "zstd",
+            Compression::Snappy => "snappy",
+        }
+    }
+
+    /// the algorithm a "content_encoding" names, if it is one of these
+    ///
+    /// As in HTTP, encodings are case insensitive and "x-gzip" is the same as "gzip".
+    pub fn from_content_encoding(content_encoding: &[u8]) -> Option<Compression> {
+        let content_encoding = String::from_utf8_lossy(content_encoding).trim().to_lowercase();
+        match content_encoding.as_str() {
+            "gzip" | "x-gzip" => Some(Compression::Gzip),
+            "zstd" => Some(Compression::Zstd),
+            "snappy" => Some(Compression::Snappy),
+            _ => None,
+        }
+    }
+
+    /// compresses `value`
+    pub fn compress(&self, value: &[u8]) -> Result<Vec<u8>, RiakErr> {
+        let result = match *self {
+            Compression::Gzip => {
+                let mut encoder = GzEncoder::new(Vec::new(), GzipLevel::default());
+                match encoder.write_all(value) {
+                    Ok(()) => encoder.finish(),
+                    Err(error) => Err(error),
+                }
+            }
+            Compression::Zstd => zstd::encode_all(value, ZSTD_LEVEL),
+            Compression::Snappy => {
+                let mut encoder = snap::write::FrameEncoder::new(Vec::new());
+                match encoder.write_all(value) {
+                    Ok(()) => encoder.into_inner().map_err(|error| error.into_error()),
+                    Err(error) => Err(error),
+                }
+            }
+        };
+
+        match result {
+            Ok(compressed) => Ok(compressed),
+            Err(error) => Err(RiakErr::CompressionError(error.to_string())),
+        }
+    }
+
+    /// decompresses `value`
+    pub fn decompress(&self, value: &[u8]) -> Result<Vec<u8>, RiakErr> {
+        let mut decompressed: Vec<u8> = Vec::new();
+        let result = match *self {
+            Compression::Gzip => {
+                GzDecoder::new(value).read_to_end(&mut decompressed).map(|_| decompressed)
+            }
+            Compression::Zstd => zstd::decode_all(value),
+            Compression::Snappy => {
+                snap::read::FrameDecoder::new(value)
+                    .read_to_end(&mut decompressed)
+                    .map(|_| decompressed)
+            }
+        };
+
+        match result {
+            Ok(decompressed) => Ok(decompressed),
+            Err(error) => Err(RiakErr::CompressionError(error.to_string())),
+        }
+    }
+}
+
+/// `CompressionConfig` represents how a `Client` compresses the values it stores
+#[derive(Clone, Copy, Debug)]
+pub struct CompressionConfig {
+    compression: Compression,
+    threshold: usize,
+}
+
+impl CompressionConfig {
+    /// constructs a new `CompressionConfig` compressing values of at least `threshold` bytes
+    pub fn new(compression: Compression, threshold: usize) -> CompressionConfig {
+        CompressionConfig {
+            compression: compression,
+            threshold: threshold,
+        }
+    }
+
+    /// get the algorithm values are compressed with
+    pub fn get_compression(&self) -> Compression {
+        self.compression
+    }
+
+    /// set the algorithm values are compressed with
+    pub fn set_compression(&mut self, compression: Compression) {
+        self.compression = compression;
+    }
+
+    /// get the size (in bytes) from which values are compressed
+    pub fn get_threshold(&self) -> usize {
+        self.threshold
+    }
+
+    /// set the size (in bytes) from which values are compressed
+    pub fn set_threshold(&mut self, threshold: usize) {
+        self.threshold = threshold;
+    }
+
+    /// returns `content` with its value compressed, unless it is below the threshold or already
+    /// has a "content_encoding"
+    pub fn compress_content(&self, content: &ObjectContent) -> Result<ObjectContent, RiakErr> {
+        let value = content.get_value();
+        if value.len() < self.threshold || content.get_content_encoding().is_some() {
+            return Ok(content.clone());
+        }
+
+        let compressed = match self.compression.compress(&value) {
+            Ok(compressed) => compressed,
+            Err(error) => return Err(error),
+        };
+
+        let mut content = content.clone();
+        content.set_value(compressed);
+        content.set_content_encoding(self.compression.get_content_encoding());
+        Ok(content)
+    }
+}
+
+/// returns `content` with its value decompressed and its "content_encoding" removed, unless it
+/// has no "content_encoding" or one that is not recognised
+///
+/// # Errors
+///
+/// Returns `RiakErr::CompressionError` if the value is not valid for its "content_encoding".
+pub fn decompress_content(content: &ObjectContent) -> Result<ObjectContent, RiakErr> {
+    let compression = match content.get_content_encoding() {
+        Some(content_encoding) => {
+            match Compression::from_content_encoding(&content_encoding) {
+                Some(compression) => compression,
+                None => return Ok(content.clone()),
+            }
+        }
+        None => return Ok(content.clone()),
+    };
+
+    let decompressed = match compression.decompress(&content.get_value()) {
+        Ok(decompressed) => decompressed,
+        Err(error) => return Err(error),
+    };
+
+    let mut content = content.clone();
+    content.set_value(decompressed);
+    content.clear_content_encoding();
+    Ok(content)
+}
+
+/// returns every sibling in `contents` decompressed as `decompress_content` does
+///
+/// # Errors
+///
+/// Returns `RiakErr::CompressionError` if a value is not valid for its "content_encoding".
+pub fn decompress_contents(contents: &[ObjectContent]) -> Result<Vec<ObjectContent>, RiakErr> {
+    let mut decompressed: Vec<ObjectContent> = Vec::new();
+    for content in contents {
+        match decompress_content(content) {
+            Ok(content) => decompressed.push(content),
+            Err(error) => return Err(error),
+        };
+    }
+    Ok(decompressed)
+}
@@ -0,0 +1,222 @@
+/// Legacy counters, the counters of Riak 1.4 that predate Riak Data Types.
+///
+/// Legacy counters are stored as objects in buckets of the "default" bucket type, which must
+/// have the "allow_mult" property enabled. New code should use the counters in
+/// `riak::datatypes` instead, these remain for buckets that already hold legacy counters.
+///
+/// For more information: https://docs.basho.com/riak/kv/latest/developing/api/protocol-buffers/
+
+use errors::RiakErr;
+use protobuf::Message;
+use rpb::riak_kv::{RpbCounterGetReq, RpbCounterUpdateReq};
+use rpb::utils::RpbGenerator;
+
+/// `IncrementCounterReq` represents a request to increment (or decrement) a legacy counter
+#[derive(Clone, Debug)]
+pub struct IncrementCounterReq(RpbCounterUpdateReq);
+
+impl IncrementCounterReq {
+    /// constructs a new `IncrementCounterReq`, a negative `amount` decrements the counter
+    pub fn new<T: Into<Vec<u8>>>(bucket: T, key: T, amount: i64) -> IncrementCounterReq {
+        let mut req = IncrementCounterReq(RpbCounterUpdateReq::new());
+        req.0.set_bucket(bucket.into());
+        req.0.set_key(key.into());
+        req.0.set_amount(amount);
+        req
+    }
+
+    /// get the value of the "bucket" property
+    pub fn get_bucket(&self) -> Vec<u8> {
+        self.0.get_bucket().to_vec()
+    }
+
+    /// set the value of the "bucket" property
+    pub fn set_bucket<T: Into<Vec<u8>>>(&mut self, bucket: T) {
+        self.0.set_bucket(bucket.into());
+    }
+
+    /// get the value of the "key" property
+    pub fn get_key(&self) -> Vec<u8> {
+        self.0.get_key().to_vec()
+    }
+
+    /// set the value of the "key" property
+    pub fn set_key<T: Into<Vec<u8>>>(&mut self, key: T) {
+        self.0.set_key(key.into());
+    }
+
+    /// get the value of the "amount" property
+    pub fn get_amount(&self) -> i64 {
+        self.0.get_amount()
+    }
+
+    /// set the value of the "amount" property
+    pub fn set_amount(&mut self, amount: i64) {
+        self.0.set_amount(amount);
+    }
+
+    /// get the value of the "w" property
+    pub fn get_w(&self) -> Option<u32> {
+        if self.0.has_w() {
+            Some(self.0.get_w())
+        } else {
+            None
+        }
+    }
+
+    /// set the value of the "w" property
+    pub fn set_w(&mut self, w: u32) {
+        self.0.set_w(w);
+    }
+
+    /// get the value of the "dw" property
+    pub fn get_dw(&self) -> Option<u32> {
+        if self.0.has_dw() {
+            Some(self.0.get_dw())
+        } else {
+            None
+        }
+    }
+
+    /// set the value of the "dw" property
+    pub fn set_dw(&mut self, dw: u32) {
+        self.0.set_dw(dw);
+    }
+
+    /// get the value of the "pw" property
+    pub fn get_pw(&self) -> Option<u32> {
+        if self.0.has_pw() {
+            Some(self.0.get_pw())
+        } else {
+            None
+        }
+    }
+
+    /// set the value of the "pw" property
+    pub fn set_pw(&mut self, pw: u32) {
+        self.0.set_pw(pw);
+    }
+
+    /// get the value of the "returnvalue" property
+    pub fn get_returnvalue(&self) -> Option<bool> {
+        if self.0.has_returnvalue() {
+            Some(self.0.get_returnvalue())
+        } else {
+            None
+        }
+    }
+
+    /// set the value of the "returnvalue" property, which has Riak send back the value of the
+    /// counter after it is incremented
+    pub fn set_returnvalue(&mut self, returnvalue: bool) {
+        self.0.set_returnvalue(returnvalue);
+    }
+}
+
+impl RpbGenerator for IncrementCounterReq {
+    fn write_to_bytes(&self) -> Result<Vec<u8>, RiakErr> {
+        match self.0.write_to_bytes() {
+            Ok(bytes) => Ok(bytes),
+            Err(error) => Err(RiakErr::ProtobufError(error)),
+        }
+    }
+}
+
+/// `GetCounterReq` represents a request to get the value of a legacy counter
+#[derive(Clone, Debug)]
+pub struct GetCounterReq(RpbCounterGetReq);
+
+impl GetCounterReq {
+    /// constructs a new `GetCounterReq`
+    pub fn new<T: Into<Vec<u8>>>(bucket: T, key: T) -> GetCounterReq {
+        let mut req = GetCounterReq(RpbCounterGetReq::new());
+        req.0.set_bucket(bucket.into());
+        req.0.set_key(key.into());
+        req
+    }
+
+    /// get the value of the "bucket" property
+    pub fn get_bucket(&self) -> Vec<u8> {
+        self.0.get_bucket().to_vec()
+    }
+
+    /// set the value of the "bucket" property
+    pub fn set_bucket<T: Into<Vec<u8>>>(&mut self, bucket: T) {
+        self.0.set_bucket(bucket.into());
+    }
+
+    /// get the value of the "key" property
+    pub fn get_key(&self) -> Vec<u8> {
+        self.0.get_key().to_vec()
+    }
+
+    /// set the value of the "key" property
+    pub fn set_key<T: Into<Vec<u8>>>(&mut self, key: T) {
+        self.0.set_key(key.into());
+    }
+
+    /// get the value of the "r" property
+    pub fn get_r(&self) -> Option<u32> {
+        if self.0.has_r() {
+            Some(self.0.get_r())
+        } else {
+            None
+        }
+    }
+
+    /// set the value of the "r" property
+    pub fn set_r(&mut self, r: u32) {
+        self.0.set_r(r);
+    }
+
+    /// get the value of the "pr" property
+    pub fn get_pr(&self) -> Option<u32> {
+        if self.0.has_pr() {
+            Some(self.0.get_pr())
+        } else {
+            None
+        }
+    }
+
+    /// set the value of the "pr" property
+    pub fn set_pr(&mut self, pr: u32) {
+        self.0.set_pr(pr);
+    }
+
+    /// get the value of the "basic_quorum" property
+    pub fn get_basic_quorum(&self) -> Option<bool> {
+        if self.0.has_basic_quorum() {
+            Some(self.0.get_basic_quorum())
+        } else {
+            None
+        }
+    }
+
+    /// set the value of the "basic_quorum" property
+    pub fn set_basic_quorum(&mut self, basic_quorum: bool) {
+        self.0.set_basic_quorum(basic_quorum);
+    }
+
+    /// get the value of the "notfound_ok" property
+    pub fn get_notfound_ok(&self) -> Option<bool> {
+        if self.0.has_notfound_ok() {
+            Some(self.0.get_notfound_ok())
+        } else {
+            None
+        }
+    }
+
+    /// set the value of the "notfound_ok" property
+    pub fn set_notfound_ok(&mut self, notfound_ok: bool) {
+        self.0.set_notfound_ok(notfound_ok);
+    }
+}
+
+impl RpbGenerator for GetCounterReq {
+    fn write_to_bytes(&self) -> Result<Vec<u8>, RiakErr> {
+        match self.0.write_to_bytes() {
+            Ok(bytes) => Ok(bytes),
+            Err(error) => Err(RiakErr::ProtobufError(error)),
+        }
+    }
+}
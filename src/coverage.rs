@@ -0,0 +1,280 @@
+/// Coverage plans, for reading every key of a bucket in parallel.
+///
+/// A coverage plan splits the keyspace of a bucket into parts, each with the address of a node
+/// that can serve it and an opaque cover context. A secondary index query with a cover context
+/// set only reads that part of the keyspace, so the parts can be read at the same time from
+/// different nodes. `CoverageScanner` does this to list the keys of a bucket much faster than
+/// `Client::list_keys`, which walks the keys of every bucket in the cluster.
+///
+/// Coverage queries use the "$bucket" secondary index, so the backend of the bucket must support
+/// secondary indexes.
+///
+/// For more information: https://docs.basho.com/riak/kv/latest/developing/api/protocol-buffers/coverage-queries/
+
+use Client;
+use DEFAULT_TIMEOUT;
+use errors::RiakErr;
+use index::IndexQuery;
+#[cfg(feature = "tls")]
+use security::SecurityConfig;
+use std::io;
+use std::sync::mpsc::{channel, Sender};
+use std::thread;
+use transport::Transport;
+
+// The secondary index every object is in, under the name of its bucket.
+static BUCKET_INDEX: &str = "$bucket";
+
+// Defaults
+static DEFAULT_PAGE_SIZE: u32 = 1000;
+static DEFAULT_MAX_REPLACEMENTS: u32 = 3;
+
+// What a worker sends back when it finishes, the entry it read and the keys found in it.
+type WorkerResult = (CoverageEntry, Result<Vec<Vec<u8>>, RiakErr>);
+
+/// `CoverageEntry` represents part of the keyspace of a bucket in a coverage plan, the node to
+/// read it from, and the cover context that limits a query to it.
+#[derive(Clone, Debug)]
+pub struct CoverageEntry {
+    pub ip: String,
+    pub port: u16,
+    pub keyspace_desc: String,
+    pub cover_context: Vec<u8>,
+}
+
+impl CoverageEntry {
+    pub fn new(ip: &str, port: u16, keyspace_desc: &str, cover_context: Vec<u8>) -> CoverageEntry {
+        CoverageEntry {
+            ip: ip.to_string(),
+            port: port,
+            keyspace_desc: keyspace_desc.to_string(),
+            cover_context: cover_context,
+        }
+    }
+}
+
+/// `CoverageScanner` lists the keys of a bucket by reading each part of a coverage plan in
+/// parallel.
+///
+/// Every part is read on a thread of its own, connected directly to the node listed for it.
+/// When a part can not be read, Riak is asked to replace it with parts served by other nodes.
+///
+/// # Examples
+///
+/// ```
+/// use riak::Client;
+/// use riak::coverage::CoverageScanner;
+///
+/// let mut riak = Client::new("10.0.0.2:8087").unwrap();
+///
+/// let mut scanner = CoverageScanner::new("default", "testbucket");
+/// scanner.set_min_partitions(16);
+///
+/// let keys = scanner.list_keys(&mut riak).unwrap();
+/// println!("testbucket has {} keys", keys.len());
+/// ```
+#[derive(Clone, Debug)]
+pub struct CoverageScanner {
+    bucket_type: Vec<u8>,
+    bucket: Vec<u8>,
+    min_partitions: u32,
+    page_size: u32,
+    max_replacements: u32,
+    timeout: u32,
+    #[cfg(feature = "tls")]
+    security: Option<SecurityConfig>,
+}
+
+impl CoverageScanner {
+    /// constructs a new `CoverageScanner` for a bucket
+    pub fn new<T: Into<Vec<u8>>>(bucket_type: T, bucket: T) -> CoverageScanner {
+        CoverageScanner {
+            bucket_type: bucket_type.into(),
+            bucket: bucket.into(),
+            min_partitions: 0,
+            page_size: DEFAULT_PAGE_SIZE,
+            max_replacements: DEFAULT_MAX_REPLACEMENTS,
+            timeout: DEFAULT_TIMEOUT,
+            #[cfg(feature = "tls")]
+            security: None,
+        }
+    }
+
+    /// get the least number of parts the keyspace is split into
+    pub fn get_min_partitions(&self) -> u32 {
+        self.min_partitions
+    }
+
+    /// set the least number of parts the keyspace is split into, and so how many are read in
+    /// parallel, 0 (the default) uses Riak's usual coverage plan
+    pub fn set_min_partitions(&mut self, min_partitions: u32) {
+        self.min_partitions = min_partitions;
+    }
+
+    /// get how many keys are read from a part at a time
+    pub fn get_page_size(&self) -> u32 {
+        self.page_size
+    }
+
+    /// set how many keys are read from a part at a time
+    pub fn set_page_size(&mut self, page_size: u32) {
+        self.page_size = page_size;
+    }
+
+    /// get how many parts that could not be read are replaced before the scan fails
+    pub fn get_max_replacements(&self) -> u32 {
+        self.max_replacements
+    }
+
+    /// set how many parts that could not be read are replaced before the scan fails
+    pub fn set_max_replacements(&mut self, max_replacements: u32) {
+        self.max_replacements = max_replacements;
+    }
+
+    /// get the timeout (in seconds) of the connections to each node
+    pub fn get_timeout(&self) -> u32 {
+        self.timeout
+    }
+
+    /// set the timeout (in seconds) of the connections to each node
+    pub fn set_timeout(&mut self, timeout: u32) {
+        self.timeout = timeout;
+    }
+
+    /// set the security settings used to connect to each node, for clusters with security
+    /// enabled
+    ///
+    /// This requires the "tls" feature.
+    #[cfg(feature = "tls")]
+    pub fn set_security(&mut self, security: SecurityConfig) {
+        self.security = Some(security);
+    }
+
+    /// lists every key in the bucket, using `client` to fetch the coverage plan and any
+    /// replacements
+    ///
+    /// The keys are not in any particular order.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the coverage plan can not be fetched, or if a part of it can not be
+    /// read or replaced after "max_replacements" replacements.
+    pub fn list_keys<C: Transport>(&self,
+                                   client: &mut Client<C>)
+                                   -> Result<Vec<Vec<u8>>, RiakErr> {
+        let plan = match client.coverage_plan(self.bucket_type.clone(),
+                                              self.bucket.clone(),
+                                              self.min_partitions) {
+            Ok(plan) => plan,
+            Err(error) => return Err(error),
+        };
+
+        let (results_tx, results_rx) = channel();
+        let mut pending = 0;
+        for entry in plan {
+            self.spawn_worker(entry, results_tx.clone());
+            pending += 1;
+        }
+
+        let mut keys: Vec<Vec<u8>> = Vec::new();
+        let mut unavailable: Vec<Vec<u8>> = Vec::new();
+        while pending > 0 {
+            // every worker sends its result, so this waits for the next one to finish
+            let (entry, result) = match results_rx.recv() {
+                Ok(finished) => finished,
+                Err(_) => {
+                    return Err(RiakErr::IoError(io::Error::new(io::ErrorKind::Other,
+                                                               "coverage scan workers stopped")))
+                }
+            };
+            pending -= 1;
+
+            let error = match result {
+                Ok(found) => {
+                    keys.extend(found);
+                    continue;
+                }
+                Err(error) => error,
+            };
+
+            debug!("failed to read {} from {}:{}: {:?}",
+                   entry.keyspace_desc,
+                   entry.ip,
+                   entry.port,
+                   error);
+            if unavailable.len() as u32 >= self.max_replacements {
+                return Err(error);
+            }
+            unavailable.push(entry.cover_context.clone());
+
+            let replacements = match client.replace_cover(self.bucket_type.clone(),
+                                                          self.bucket.clone(),
+                                                          &entry.cover_context,
+                                                          &unavailable) {
+                Ok(replacements) => replacements,
+                Err(error) => return Err(error),
+            };
+            for replacement in replacements {
+                self.spawn_worker(replacement, results_tx.clone());
+                pending += 1;
+            }
+        }
+
+        Ok(keys)
+    }
+
+    // Reads the keys of one part of the plan on a new thread, sending them back with the entry.
+    fn spawn_worker(&self, entry: CoverageEntry, results: Sender<WorkerResult>) {
+        let mut query = IndexQuery::new_eq(self.bucket.clone(),
+                                           BUCKET_INDEX.as_bytes().to_vec(),
+                                           self.bucket.clone());
+        query.set_bucket_type(self.bucket_type.clone());
+        query.set_max_results(self.page_size);
+        query.set_cover_context(entry.cover_context.clone());
+
+        let scanner = self.clone();
+        thread::spawn(move || {
+            let result = match scanner.connect(&entry) {
+                Ok(mut client) => read_keys(&mut client, query),
+                Err(error) => Err(error),
+            };
+            match results.send((entry, result)) {
+                Ok(()) => (),
+                Err(_) => debug!("coverage scan ended before a worker finished"),
+            };
+        });
+    }
+
+    // Connects to the node listed for an entry.
+    #[cfg(not(feature = "tls"))]
+    fn connect(&self, entry: &CoverageEntry) -> Result<Client, RiakErr> {
+        Client::new_with_timeout((entry.ip.as_str(), entry.port), self.timeout)
+    }
+
+    // Connects to the node listed for an entry.
+    #[cfg(feature = "tls")]
+    fn connect(&self, entry: &CoverageEntry) -> Result<Client, RiakErr> {
+        let addr = (entry.ip.as_str(), entry.port);
+        match self.security {
+            Some(ref security) => Client::new_secure_with_timeout(addr, security, self.timeout),
+            None => Client::new_with_timeout(addr, self.timeout),
+        }
+    }
+}
+
+// Reads every page of keys matching a query.
+fn read_keys(client: &mut Client, mut query: IndexQuery) -> Result<Vec<Vec<u8>>, RiakErr> {
+    let mut keys: Vec<Vec<u8>> = Vec::new();
+    loop {
+        let results = match client.query_index(&query) {
+            Ok(results) => results,
+            Err(error) => return Err(error),
+        };
+        keys.extend(results.get_keys());
+
+        match results.get_continuation() {
+            Some(continuation) => query.set_continuation(continuation),
+            None => return Ok(keys),
+        };
+    }
+}
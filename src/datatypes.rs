@@ -0,0 +1,1039 @@
+/// Riak Data Types (CRDTs) and the requests used to fetch and update them.
+///
+/// Data Types live in buckets whose bucket type has the "datatype" property set, and every
+/// request is made against a bucket type, bucket and key. Changes made to a fetched value are
+/// recorded locally and sent to Riak as operations by `Client::update_datatype`. Removals from
+/// sets and maps require the causal context returned by a previous fetch.
+///
+/// For more information: https://docs.basho.com/riak/kv/latest/developing/data-types/
+
+use errors::RiakErr;
+use private_traits::{FetchDataTypeRespPrivate, UpdateDataTypeReqPrivate};
+use protobuf::{Message, RepeatedField};
+use rpb::riak_dt::{CounterOp, DtFetchReq, DtFetchResp, DtFetchResp_DataType, DtOp, DtUpdateReq,
+                   DtUpdateResp, HllOp, MapEntry, MapField, MapField_MapFieldType, MapOp,
+                   MapUpdate, MapUpdate_FlagOp, SetOp};
+use rpb::utils::RpbGenerator;
+use std::collections::HashMap;
+
+/// `Counter` represents a Riak counter, an integer that can be incremented or decremented.
+#[derive(Clone, Debug)]
+pub struct Counter {
+    value: i64,
+    increment: i64,
+}
+
+impl Counter {
+    /// constructs a new `Counter` with a value of zero
+    pub fn new() -> Counter {
+        Counter {
+            value: 0,
+            increment: 0,
+        }
+    }
+
+    /// get the value of the counter, including any local changes
+    pub fn get_value(&self) -> i64 {
+        self.value
+    }
+
+    /// increment the counter by `amount`
+    pub fn increment(&mut self, amount: i64) {
+        self.value += amount;
+        self.increment += amount;
+    }
+
+    /// decrement the counter by `amount`
+    pub fn decrement(&mut self, amount: i64) {
+        self.increment(-amount);
+    }
+
+    /// whether or not there are local changes to send to Riak
+    pub fn is_modified(&self) -> bool {
+        self.increment != 0
+    }
+
+    fn from_value(value: i64) -> Counter {
+        Counter {
+            value: value,
+            increment: 0,
+        }
+    }
+
+    fn to_counter_op(&self) -> CounterOp {
+        let mut op = CounterOp::new();
+        op.set_increment(self.increment);
+        op
+    }
+}
+
+/// `Set` represents a Riak set, a collection of unique binary values.
+#[derive(Clone, Debug)]
+pub struct Set {
+    value: Vec<Vec<u8>>,
+    adds: Vec<Vec<u8>>,
+    removes: Vec<Vec<u8>>,
+}
+
+impl Set {
+    /// constructs a new empty `Set`
+    pub fn new() -> Set {
+        Set {
+            value: Vec::new(),
+            adds: Vec::new(),
+            removes: Vec::new(),
+        }
+    }
+
+    /// get the members of the set, including any local changes
+    pub fn get_value(&self) -> Vec<Vec<u8>> {
+        self.value.clone()
+    }
+
+    /// whether or not the set contains `member`
+    pub fn contains<T: Into<Vec<u8>>>(&self, member: T) -> bool {
+        let member = member.into();
+        self.value.contains(&member)
+    }
+
+    /// add `member` to the set
+    pub fn add<T: Into<Vec<u8>>>(&mut self, member: T) {
+        let member = member.into();
+        self.removes.retain(|m| *m != member);
+        if !self.value.contains(&member) {
+            self.value.push(member.clone());
+        }
+        if !self.adds.contains(&member) {
+            self.adds.push(member);
+        }
+    }
+
+    /// remove `member` from the set, this requires the context of a previous fetch
+    pub fn remove<T: Into<Vec<u8>>>(&mut self, member: T) {
+        let member = member.into();
+        self.adds.retain(|m| *m != member);
+        self.value.retain(|m| *m != member);
+        if !self.removes.contains(&member) {
+            self.removes.push(member);
+        }
+    }
+
+    /// whether or not there are local changes to send to Riak
+    pub fn is_modified(&self) -> bool {
+        !self.adds.is_empty() || !self.removes.is_empty()
+    }
+
+    fn from_value(value: Vec<Vec<u8>>) -> Set {
+        Set {
+            value: value,
+            adds: Vec::new(),
+            removes: Vec::new(),
+        }
+    }
+
+    fn to_set_op(&self) -> SetOp {
+        let mut op = SetOp::new();
+        op.set_adds(RepeatedField::from_vec(self.adds.clone()));
+        op.set_removes(RepeatedField::from_vec(self.removes.clone()));
+        op
+    }
+}
+
+/// `Hll` represents a Riak HyperLogLog, which estimates the number of unique elements added.
+#[derive(Clone, Debug)]
+pub struct Hll {
+    value: u64,
+    adds: Vec<Vec<u8>>,
+}
+
+impl Hll {
+    /// constructs a new empty `Hll`
+    pub fn new() -> Hll {
+        Hll {
+            value: 0,
+            adds: Vec::new(),
+        }
+    }
+
+    /// get the estimated cardinality as of the last fetch, local additions are not included
+    pub fn get_value(&self) -> u64 {
+        self.value
+    }
+
+    /// add `element` to the HyperLogLog
+    pub fn add<T: Into<Vec<u8>>>(&mut self, element: T) {
+        self.adds.push(element.into());
+    }
+
+    /// whether or not there are local changes to send to Riak
+    pub fn is_modified(&self) -> bool {
+        !self.adds.is_empty()
+    }
+
+    fn from_value(value: u64) -> Hll {
+        Hll {
+            value: value,
+            adds: Vec::new(),
+        }
+    }
+
+    fn to_hll_op(&self) -> HllOp {
+        let mut op = HllOp::new();
+        op.set_adds(RepeatedField::from_vec(self.adds.clone()));
+        op
+    }
+}
+
+/// `Register` represents a Riak register, a binary value that can only be embedded in a `Map`.
+#[derive(Clone, Debug)]
+pub struct Register {
+    value: Vec<u8>,
+    update: Option<Vec<u8>>,
+}
+
+impl Register {
+    /// constructs a new empty `Register`
+    pub fn new() -> Register {
+        Register {
+            value: Vec::new(),
+            update: None,
+        }
+    }
+
+    /// get the value of the register, including any local changes
+    pub fn get_value(&self) -> Vec<u8> {
+        self.value.clone()
+    }
+
+    /// set the value of the register
+    pub fn set_value<T: Into<Vec<u8>>>(&mut self, value: T) {
+        let value = value.into();
+        self.value = value.clone();
+        self.update = Some(value);
+    }
+
+    /// whether or not there are local changes to send to Riak
+    pub fn is_modified(&self) -> bool {
+        self.update.is_some()
+    }
+
+    fn from_value(value: Vec<u8>) -> Register {
+        Register {
+            value: value,
+            update: None,
+        }
+    }
+}
+
+/// `Flag` represents a Riak flag, a boolean that can only be embedded in a `Map`.
+#[derive(Clone, Debug)]
+pub struct Flag {
+    value: bool,
+    update: Option<bool>,
+}
+
+impl Flag {
+    /// constructs a new disabled `Flag`
+    pub fn new() -> Flag {
+        Flag {
+            value: false,
+            update: None,
+        }
+    }
+
+    /// get the value of the flag, including any local changes
+    pub fn get_value(&self) -> bool {
+        self.value
+    }
+
+    /// enable the flag
+    pub fn enable(&mut self) {
+        self.value = true;
+        self.update = Some(true);
+    }
+
+    /// disable the flag, this requires the context of a previous fetch
+    pub fn disable(&mut self) {
+        self.value = false;
+        self.update = Some(false);
+    }
+
+    /// whether or not there are local changes to send to Riak
+    pub fn is_modified(&self) -> bool {
+        self.update.is_some()
+    }
+
+    fn from_value(value: bool) -> Flag {
+        Flag {
+            value: value,
+            update: None,
+        }
+    }
+}
+
+/// `MapFieldType` is the type of a field embedded in a `Map`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum MapFieldType {
+    Counter,
+    Set,
+    Register,
+    Flag,
+    Map,
+}
+
+impl MapFieldType {
+    fn to_rpb(self) -> MapField_MapFieldType {
+        match self {
+            MapFieldType::Counter => MapField_MapFieldType::COUNTER,
+            MapFieldType::Set => MapField_MapFieldType::SET,
+            MapFieldType::Register => MapField_MapFieldType::REGISTER,
+            MapFieldType::Flag => MapField_MapFieldType::FLAG,
+            MapFieldType::Map => MapField_MapFieldType::MAP,
+        }
+    }
+}
+
+/// `Map` represents a Riak map, a collection of named counters, sets, registers, flags and maps.
+///
+/// Fields are addressed by their name and type, so a counter and a set may share a name.
+#[derive(Clone, Debug)]
+pub struct Map {
+    counters: HashMap<Vec<u8>, Counter>,
+    sets: HashMap<Vec<u8>, Set>,
+    registers: HashMap<Vec<u8>, Register>,
+    flags: HashMap<Vec<u8>, Flag>,
+    maps: HashMap<Vec<u8>, Map>,
+    removes: Vec<(Vec<u8>, MapFieldType)>,
+}
+
+impl Map {
+    /// constructs a new empty `Map`
+    pub fn new() -> Map {
+        Map {
+            counters: HashMap::new(),
+            sets: HashMap::new(),
+            registers: HashMap::new(),
+            flags: HashMap::new(),
+            maps: HashMap::new(),
+            removes: Vec::new(),
+        }
+    }
+
+    /// get all of the counters in the map
+    pub fn get_counters(&self) -> &HashMap<Vec<u8>, Counter> {
+        &self.counters
+    }
+
+    /// get the counter named `name`
+    pub fn get_counter<T: Into<Vec<u8>>>(&self, name: T) -> Option<&Counter> {
+        self.counters.get(&name.into())
+    }
+
+    /// get the counter named `name` for modification, creating it if it doesn't exist
+    pub fn counter_mut<T: Into<Vec<u8>>>(&mut self, name: T) -> &mut Counter {
+        self.counters.entry(name.into()).or_insert_with(Counter::new)
+    }
+
+    /// get all of the sets in the map
+    pub fn get_sets(&self) -> &HashMap<Vec<u8>, Set> {
+        &self.sets
+    }
+
+    /// get the set named `name`
+    pub fn get_set<T: Into<Vec<u8>>>(&self, name: T) -> Option<&Set> {
+        self.sets.get(&name.into())
+    }
+
+    /// get the set named `name` for modification, creating it if it doesn't exist
+    pub fn set_mut<T: Into<Vec<u8>>>(&mut self, name: T) -> &mut Set {
+        self.sets.entry(name.into()).or_insert_with(Set::new)
+    }
+
+    /// get all of the registers in the map
+    pub fn get_registers(&self) -> &HashMap<Vec<u8>, Register> {
+        &self.registers
+    }
+
+    /// get the register named `name`
+    pub fn get_register<T: Into<Vec<u8>>>(&self, name: T) -> Option<&Register> {
+        self.registers.get(&name.into())
+    }
+
+    /// get the register named `name` for modification, creating it if it doesn't exist
+    pub fn register_mut<T: Into<Vec<u8>>>(&mut self, name: T) -> &mut Register {
+        self.registers.entry(name.into()).or_insert_with(Register::new)
+    }
+
+    /// get all of the flags in the map
+    pub fn get_flags(&self) -> &HashMap<Vec<u8>, Flag> {
+        &self.flags
+    }
+
+    /// get the flag named `name`
+    pub fn get_flag<T: Into<Vec<u8>>>(&self, name: T) -> Option<&Flag> {
+        self.flags.get(&name.into())
+    }
+
+    /// get the flag named `name` for modification, creating it if it doesn't exist
+    pub fn flag_mut<T: Into<Vec<u8>>>(&mut self, name: T) -> &mut Flag {
+        self.flags.entry(name.into()).or_insert_with(Flag::new)
+    }
+
+    /// get all of the maps embedded in the map
+    pub fn get_maps(&self) -> &HashMap<Vec<u8>, Map> {
+        &self.maps
+    }
+
+    /// get the map named `name`
+    pub fn get_map<T: Into<Vec<u8>>>(&self, name: T) -> Option<&Map> {
+        self.maps.get(&name.into())
+    }
+
+    /// get the map named `name` for modification, creating it if it doesn't exist
+    pub fn map_mut<T: Into<Vec<u8>>>(&mut self, name: T) -> &mut Map {
+        self.maps.entry(name.into()).or_insert_with(Map::new)
+    }
+
+    /// remove the field named `name` of type `field_type`, this requires the context of a
+    /// previous fetch
+    pub fn remove<T: Into<Vec<u8>>>(&mut self, name: T, field_type: MapFieldType) {
+        let name = name.into();
+        match field_type {
+            MapFieldType::Counter => {
+                self.counters.remove(&name);
+            }
+            MapFieldType::Set => {
+                self.sets.remove(&name);
+            }
+            MapFieldType::Register => {
+                self.registers.remove(&name);
+            }
+            MapFieldType::Flag => {
+                self.flags.remove(&name);
+            }
+            MapFieldType::Map => {
+                self.maps.remove(&name);
+            }
+        };
+        self.removes.push((name, field_type));
+    }
+
+    /// whether or not there are local changes to send to Riak
+    pub fn is_modified(&self) -> bool {
+        !self.removes.is_empty() || self.counters.values().any(|c| c.is_modified()) ||
+        self.sets.values().any(|s| s.is_modified()) ||
+        self.registers.values().any(|r| r.is_modified()) ||
+        self.flags.values().any(|f| f.is_modified()) ||
+        self.maps.values().any(|m| m.is_modified())
+    }
+
+    fn from_entries(entries: Vec<MapEntry>) -> Map {
+        let mut map = Map::new();
+
+        for mut entry in entries.into_iter() {
+            let mut field = entry.take_field();
+            let name = field.take_name();
+            match field.get_field_type() {
+                MapField_MapFieldType::COUNTER => {
+                    map.counters.insert(name, Counter::from_value(entry.get_counter_value()));
+                }
+                MapField_MapFieldType::SET => {
+                    map.sets.insert(name, Set::from_value(entry.take_set_value().into_vec()));
+                }
+                MapField_MapFieldType::REGISTER => {
+                    map.registers.insert(name, Register::from_value(entry.take_register_value()));
+                }
+                MapField_MapFieldType::FLAG => {
+                    map.flags.insert(name, Flag::from_value(entry.get_flag_value()));
+                }
+                MapField_MapFieldType::MAP => {
+                    map.maps.insert(name, Map::from_entries(entry.take_map_value().into_vec()));
+                }
+            };
+        }
+
+        map
+    }
+
+    fn to_map_op(&self) -> MapOp {
+        let mut op = MapOp::new();
+
+        for (name, field_type) in self.removes.iter() {
+            op.mut_removes().push(new_map_field(name, field_type));
+        }
+
+        for (name, counter) in self.counters.iter().filter(|&(_, c)| c.is_modified()) {
+            let mut update = MapUpdate::new();
+            update.set_field(new_map_field(name, &MapFieldType::Counter));
+            update.set_counter_op(counter.to_counter_op());
+            op.mut_updates().push(update);
+        }
+
+        for (name, set) in self.sets.iter().filter(|&(_, s)| s.is_modified()) {
+            let mut update = MapUpdate::new();
+            update.set_field(new_map_field(name, &MapFieldType::Set));
+            update.set_set_op(set.to_set_op());
+            op.mut_updates().push(update);
+        }
+
+        for (name, register) in self.registers.iter() {
+            if let Some(ref value) = register.update {
+                let mut update = MapUpdate::new();
+                update.set_field(new_map_field(name, &MapFieldType::Register));
+                update.set_register_op(value.clone());
+                op.mut_updates().push(update);
+            }
+        }
+
+        for (name, flag) in self.flags.iter() {
+            if let Some(enabled) = flag.update {
+                let mut update = MapUpdate::new();
+                update.set_field(new_map_field(name, &MapFieldType::Flag));
+                if enabled {
+                    update.set_flag_op(MapUpdate_FlagOp::ENABLE);
+                } else {
+                    update.set_flag_op(MapUpdate_FlagOp::DISABLE);
+                }
+                op.mut_updates().push(update);
+            }
+        }
+
+        for (name, map) in self.maps.iter().filter(|&(_, m)| m.is_modified()) {
+            let mut update = MapUpdate::new();
+            update.set_field(new_map_field(name, &MapFieldType::Map));
+            update.set_map_op(map.to_map_op());
+            op.mut_updates().push(update);
+        }
+
+        op
+    }
+}
+
+// Builds a `MapField` for a field name and type.
+fn new_map_field(name: &[u8], field_type: &MapFieldType) -> MapField {
+    let mut field = MapField::new();
+    field.set_name(name.to_vec());
+    field.set_field_type(field_type.to_rpb());
+    field
+}
+
+// Whether or not an operation removes anything, including from maps embedded in maps.
+fn dt_op_has_removes(op: &DtOp) -> bool {
+    (op.has_set_op() && !op.get_set_op().get_removes().is_empty()) ||
+    (op.has_map_op() && map_op_has_removes(op.get_map_op()))
+}
+
+fn map_op_has_removes(op: &MapOp) -> bool {
+    !op.get_removes().is_empty() ||
+    op.get_updates().iter().any(|update| {
+        (update.has_set_op() && !update.get_set_op().get_removes().is_empty()) ||
+        (update.has_flag_op() && update.get_flag_op() == MapUpdate_FlagOp::DISABLE) ||
+        (update.has_map_op() && map_op_has_removes(update.get_map_op()))
+    })
+}
+
+/// `DataType` is any of the Data Types that can be stored at the top level of a bucket.
+#[derive(Clone, Debug)]
+pub enum DataType {
+    Counter(Counter),
+    Set(Set),
+    Map(Map),
+    Hll(Hll),
+}
+
+impl DataType {
+    fn to_dt_op(&self) -> DtOp {
+        let mut op = DtOp::new();
+        match *self {
+            DataType::Counter(ref counter) => op.set_counter_op(counter.to_counter_op()),
+            DataType::Set(ref set) => op.set_set_op(set.to_set_op()),
+            DataType::Map(ref map) => op.set_map_op(map.to_map_op()),
+            DataType::Hll(ref hll) => op.set_hll_op(hll.to_hll_op()),
+        };
+        op
+    }
+}
+
+/// `FetchDataTypeReq` represents a request to fetch a Data Type from Riak.
+#[derive(Clone, Debug)]
+pub struct FetchDataTypeReq(DtFetchReq);
+
+impl FetchDataTypeReq {
+    /// constructs a new `FetchDataTypeReq`
+    pub fn new<T: Into<Vec<u8>>>(bucket_type: T, bucket: T, key: T) -> FetchDataTypeReq {
+        let mut req = DtFetchReq::new();
+        req.set_field_type(bucket_type.into());
+        req.set_bucket(bucket.into());
+        req.set_key(key.into());
+        FetchDataTypeReq(req)
+    }
+
+    /// get the value of the "bucket_type" property
+    pub fn get_bucket_type(&self) -> Vec<u8> {
+        self.0.get_field_type().to_vec()
+    }
+
+    /// set the value of the "bucket_type" property
+    pub fn set_bucket_type<T: Into<Vec<u8>>>(&mut self, bucket_type: T) {
+        self.0.set_field_type(bucket_type.into());
+    }
+
+    /// get the value of the "bucket" property
+    pub fn get_bucket(&self) -> Vec<u8> {
+        self.0.get_bucket().to_vec()
+    }
+
+    /// set the value of the "bucket" property
+    pub fn set_bucket<T: Into<Vec<u8>>>(&mut self, bucket: T) {
+        self.0.set_bucket(bucket.into());
+    }
+
+    /// get the value of the "key" property
+    pub fn get_key(&self) -> Vec<u8> {
+        self.0.get_key().to_vec()
+    }
+
+    /// set the value of the "key" property
+    pub fn set_key<T: Into<Vec<u8>>>(&mut self, key: T) {
+        self.0.set_key(key.into());
+    }
+
+    /// get the value of the "r" property
+    pub fn get_r(&self) -> Option<u32> {
+        if self.0.has_r() {
+            Some(self.0.get_r())
+        } else {
+            None
+        }
+    }
+
+    /// set the value of the "r" property
+    pub fn set_r(&mut self, r: u32) {
+        self.0.set_r(r);
+    }
+
+    /// get the value of the "pr" property
+    pub fn get_pr(&self) -> Option<u32> {
+        if self.0.has_pr() {
+            Some(self.0.get_pr())
+        } else {
+            None
+        }
+    }
+
+    /// set the value of the "pr" property
+    pub fn set_pr(&mut self, pr: u32) {
+        self.0.set_pr(pr);
+    }
+
+    /// get the value of the "basic_quorum" property
+    pub fn get_basic_quorum(&self) -> Option<bool> {
+        if self.0.has_basic_quorum() {
+            Some(self.0.get_basic_quorum())
+        } else {
+            None
+        }
+    }
+
+    /// set the value of the "basic_quorum" property
+    pub fn set_basic_quorum(&mut self, basic_quorum: bool) {
+        self.0.set_basic_quorum(basic_quorum);
+    }
+
+    /// get the value of the "notfound_ok" property
+    pub fn get_notfound_ok(&self) -> Option<bool> {
+        if self.0.has_notfound_ok() {
+            Some(self.0.get_notfound_ok())
+        } else {
+            None
+        }
+    }
+
+    /// set the value of the "notfound_ok" property
+    pub fn set_notfound_ok(&mut self, notfound_ok: bool) {
+        self.0.set_notfound_ok(notfound_ok);
+    }
+
+    /// get the value of the "timeout" property
+    pub fn get_timeout(&self) -> Option<u32> {
+        if self.0.has_timeout() {
+            Some(self.0.get_timeout())
+        } else {
+            None
+        }
+    }
+
+    /// set the value of the "timeout" property
+    pub fn set_timeout(&mut self, timeout: u32) {
+        self.0.set_timeout(timeout);
+    }
+
+    /// get the value of the "sloppy_quorum" property
+    pub fn get_sloppy_quorum(&self) -> Option<bool> {
+        if self.0.has_sloppy_quorum() {
+            Some(self.0.get_sloppy_quorum())
+        } else {
+            None
+        }
+    }
+
+    /// set the value of the "sloppy_quorum" property
+    pub fn set_sloppy_quorum(&mut self, sloppy_quorum: bool) {
+        self.0.set_sloppy_quorum(sloppy_quorum);
+    }
+
+    /// get the value of the "n_val" property
+    pub fn get_n_val(&self) -> Option<u32> {
+        if self.0.has_n_val() {
+            Some(self.0.get_n_val())
+        } else {
+            None
+        }
+    }
+
+    /// set the value of the "n_val" property
+    pub fn set_n_val(&mut self, n_val: u32) {
+        self.0.set_n_val(n_val);
+    }
+
+    /// get the value of the "include_context" property
+    pub fn get_include_context(&self) -> Option<bool> {
+        if self.0.has_include_context() {
+            Some(self.0.get_include_context())
+        } else {
+            None
+        }
+    }
+
+    /// set the value of the "include_context" property
+    pub fn set_include_context(&mut self, include_context: bool) {
+        self.0.set_include_context(include_context);
+    }
+}
+
+impl RpbGenerator for FetchDataTypeReq {
+    fn write_to_bytes(&self) -> Result<Vec<u8>, RiakErr> {
+        match self.0.write_to_bytes() {
+            Ok(bytes) => Ok(bytes),
+            Err(error) => Err(RiakErr::ProtobufError(error)),
+        }
+    }
+}
+
+/// `FetchDataTypeResp` represents a Data Type fetched from Riak along with its causal context.
+///
+/// The value will be `None` if nothing is stored at the requested key.
+#[derive(Clone, Debug)]
+pub struct FetchDataTypeResp {
+    context: Option<Vec<u8>>,
+    value: Option<DataType>,
+}
+
+impl FetchDataTypeResp {
+    /// get the opaque causal context, to be passed along to `UpdateDataTypeReq::set_context`
+    pub fn get_context(&self) -> Option<Vec<u8>> {
+        self.context.clone()
+    }
+
+    /// get the fetched Data Type
+    pub fn get_value(&self) -> Option<DataType> {
+        self.value.clone()
+    }
+}
+
+impl FetchDataTypeRespPrivate for FetchDataTypeResp {
+    fn new_from_dt_fetch_resp(mut dt_fetch_resp: DtFetchResp) -> FetchDataTypeResp {
+        let context = if dt_fetch_resp.has_context() {
+            Some(dt_fetch_resp.take_context())
+        } else {
+            None
+        };
+
+        let value = if dt_fetch_resp.has_value() {
+            let mut dt_value = dt_fetch_resp.take_value();
+            let data_type = match dt_fetch_resp.get_field_type() {
+                DtFetchResp_DataType::COUNTER => {
+                    DataType::Counter(Counter::from_value(dt_value.get_counter_value()))
+                }
+                DtFetchResp_DataType::SET => {
+                    DataType::Set(Set::from_value(dt_value.take_set_value().into_vec()))
+                }
+                DtFetchResp_DataType::MAP => {
+                    DataType::Map(Map::from_entries(dt_value.take_map_value().into_vec()))
+                }
+                DtFetchResp_DataType::HLL => {
+                    DataType::Hll(Hll::from_value(dt_value.get_hll_value()))
+                }
+            };
+            Some(data_type)
+        } else {
+            None
+        };
+
+        FetchDataTypeResp {
+            context: context,
+            value: value,
+        }
+    }
+}
+
+/// `UpdateDataTypeReq` represents a request to send the local changes of a Data Type to Riak.
+///
+/// Changes that remove anything, such as removing a member from a set, a field from a map or
+/// disabling a flag, need the context of a previous fetch set with `set_context`.
+#[derive(Clone, Debug)]
+pub struct UpdateDataTypeReq(DtUpdateReq);
+
+impl UpdateDataTypeReq {
+    /// constructs a new `UpdateDataTypeReq` with the changes made to `value`
+    pub fn new<T: Into<Vec<u8>>>(bucket_type: T, bucket: T, value: &DataType) -> UpdateDataTypeReq {
+        let mut req = DtUpdateReq::new();
+        req.set_field_type(bucket_type.into());
+        req.set_bucket(bucket.into());
+        req.set_op(value.to_dt_op());
+        UpdateDataTypeReq(req)
+    }
+
+    /// get the value of the "bucket_type" property
+    pub fn get_bucket_type(&self) -> Vec<u8> {
+        self.0.get_field_type().to_vec()
+    }
+
+    /// set the value of the "bucket_type" property
+    pub fn set_bucket_type<T: Into<Vec<u8>>>(&mut self, bucket_type: T) {
+        self.0.set_field_type(bucket_type.into());
+    }
+
+    /// get the value of the "bucket" property
+    pub fn get_bucket(&self) -> Vec<u8> {
+        self.0.get_bucket().to_vec()
+    }
+
+    /// set the value of the "bucket" property
+    pub fn set_bucket<T: Into<Vec<u8>>>(&mut self, bucket: T) {
+        self.0.set_bucket(bucket.into());
+    }
+
+    /// get the value of the "key" property
+    pub fn get_key(&self) -> Option<Vec<u8>> {
+        if self.0.has_key() {
+            Some(self.0.get_key().to_vec())
+        } else {
+            None
+        }
+    }
+
+    /// set the value of the "key" property, Riak will generate a key if none is set
+    pub fn set_key<T: Into<Vec<u8>>>(&mut self, key: T) {
+        self.0.set_key(key.into());
+    }
+
+    /// get the value of the "context" property
+    pub fn get_context(&self) -> Option<Vec<u8>> {
+        if self.0.has_context() {
+            Some(self.0.get_context().to_vec())
+        } else {
+            None
+        }
+    }
+
+    /// set the value of the "context" property, as returned by a previous fetch or update
+    pub fn set_context<T: Into<Vec<u8>>>(&mut self, context: T) {
+        self.0.set_context(context.into());
+    }
+
+    /// get the value of the "w" property
+    pub fn get_w(&self) -> Option<u32> {
+        if self.0.has_w() {
+            Some(self.0.get_w())
+        } else {
+            None
+        }
+    }
+
+    /// set the value of the "w" property
+    pub fn set_w(&mut self, w: u32) {
+        self.0.set_w(w);
+    }
+
+    /// get the value of the "dw" property
+    pub fn get_dw(&self) -> Option<u32> {
+        if self.0.has_dw() {
+            Some(self.0.get_dw())
+        } else {
+            None
+        }
+    }
+
+    /// set the value of the "dw" property
+    pub fn set_dw(&mut self, dw: u32) {
+        self.0.set_dw(dw);
+    }
+
+    /// get the value of the "pw" property
+    pub fn get_pw(&self) -> Option<u32> {
+        if self.0.has_pw() {
+            Some(self.0.get_pw())
+        } else {
+            None
+        }
+    }
+
+    /// set the value of the "pw" property
+    pub fn set_pw(&mut self, pw: u32) {
+        self.0.set_pw(pw);
+    }
+
+    /// get the value of the "return_body" property
+    pub fn get_return_body(&self) -> Option<bool> {
+        if self.0.has_return_body() {
+            Some(self.0.get_return_body())
+        } else {
+            None
+        }
+    }
+
+    /// set the value of the "return_body" property
+    pub fn set_return_body(&mut self, return_body: bool) {
+        self.0.set_return_body(return_body);
+    }
+
+    /// get the value of the "timeout" property
+    pub fn get_timeout(&self) -> Option<u32> {
+        if self.0.has_timeout() {
+            Some(self.0.get_timeout())
+        } else {
+            None
+        }
+    }
+
+    /// set the value of the "timeout" property
+    pub fn set_timeout(&mut self, timeout: u32) {
+        self.0.set_timeout(timeout);
+    }
+
+    /// get the value of the "sloppy_quorum" property
+    pub fn get_sloppy_quorum(&self) -> Option<bool> {
+        if self.0.has_sloppy_quorum() {
+            Some(self.0.get_sloppy_quorum())
+        } else {
+            None
+        }
+    }
+
+    /// set the value of the "sloppy_quorum" property
+    pub fn set_sloppy_quorum(&mut self, sloppy_quorum: bool) {
+        self.0.set_sloppy_quorum(sloppy_quorum);
+    }
+
+    /// get the value of the "n_val" property
+    pub fn get_n_val(&self) -> Option<u32> {
+        if self.0.has_n_val() {
+            Some(self.0.get_n_val())
+        } else {
+            None
+        }
+    }
+
+    /// set the value of the "n_val" property
+    pub fn set_n_val(&mut self, n_val: u32) {
+        self.0.set_n_val(n_val);
+    }
+
+    /// get the value of the "include_context" property
+    pub fn get_include_context(&self) -> Option<bool> {
+        if self.0.has_include_context() {
+            Some(self.0.get_include_context())
+        } else {
+            None
+        }
+    }
+
+    /// set the value of the "include_context" property
+    pub fn set_include_context(&mut self, include_context: bool) {
+        self.0.set_include_context(include_context);
+    }
+}
+
+impl RpbGenerator for UpdateDataTypeReq {
+    fn write_to_bytes(&self) -> Result<Vec<u8>, RiakErr> {
+        // Riak can't tell what a removal applies to without the context
+        if !self.0.has_context() && dt_op_has_removes(self.0.get_op()) {
+            let message = "removals require the context of a previous fetch".to_string();
+            return Err(RiakErr::InvalidDataTypeUpdate(message));
+        }
+
+        match self.0.write_to_bytes() {
+            Ok(bytes) => Ok(bytes),
+            Err(error) => Err(RiakErr::ProtobufError(error)),
+        }
+    }
+}
+
+impl UpdateDataTypeReqPrivate for UpdateDataTypeReq {
+    fn new_response(&self, mut dt_update_resp: DtUpdateResp) -> UpdateDataTypeResp {
+        let key = if dt_update_resp.has_key() {
+            Some(dt_update_resp.take_key())
+        } else {
+            None
+        };
+
+        let context = if dt_update_resp.has_context() {
+            Some(dt_update_resp.take_context())
+        } else {
+            None
+        };
+
+        // the value is only returned when requested, and its type is that of the operation sent
+        let value = if self.0.get_return_body() {
+            let op = self.0.get_op();
+            if op.has_counter_op() {
+                Some(DataType::Counter(Counter::from_value(dt_update_resp.get_counter_value())))
+            } else if op.has_set_op() {
+                Some(DataType::Set(Set::from_value(dt_update_resp.take_set_value().into_vec())))
+            } else if op.has_map_op() {
+                Some(DataType::Map(Map::from_entries(dt_update_resp.take_map_value().into_vec())))
+            } else if op.has_hll_op() {
+                Some(DataType::Hll(Hll::from_value(dt_update_resp.get_hll_value())))
+            } else {
+                None
+            }
+        } else {
+            None
+        };
+
+        UpdateDataTypeResp {
+            key: key,
+            context: context,
+            value: value,
+        }
+    }
+}
+
+/// `UpdateDataTypeResp` represents the response to an update of a Data Type.
+///
+/// The key is only present when it was generated by Riak, and the value is only present when
+/// "return_body" was set on the request.
+#[derive(Clone, Debug)]
+pub struct UpdateDataTypeResp {
+    key: Option<Vec<u8>>,
+    context: Option<Vec<u8>>,
+    value: Option<DataType>,
+}
+
+impl UpdateDataTypeResp {
+    /// get the key generated by Riak, if no key was provided in the request
+    pub fn get_key(&self) -> Option<Vec<u8>> {
+        self.key.clone()
+    }
+
+    /// get the opaque causal context, to be passed along to `UpdateDataTypeReq::set_context`
+    pub fn get_context(&self) -> Option<Vec<u8>> {
+        self.context.clone()
+    }
+
+    /// get the updated Data Type
+    pub fn get_value(&self) -> Option<DataType> {
+        self.value.clone()
+    }
+}
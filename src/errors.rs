@@ -2,21 +2,105 @@ use std::io;
 use std::error;
 use std::fmt;
 use protobuf;
+use rpb::codes;
+use rpb::riak::RpbErrorResp;
 
 /// Represents an error that has occurred on the server side.
+///
+/// When Riak responds with an `RpbErrorResp` the message and error code are decoded from it,
+/// otherwise the raw data of the unexpected response is kept as the message.
 #[derive(Debug)]
 pub struct ServerError {
     code: u8,
     data: Vec<u8>,
+    message: Vec<u8>,
+    errcode: Option<u32>,
 }
 
 impl ServerError {
     pub fn new<T: Into<Vec<u8>>>(error_code: u8, error_data: T) -> ServerError {
+        let data = error_data.into();
+
+        // decode the message and error code when the server sent an RpbErrorResp
+        let decoded = if error_code == codes::RpbErrorResp {
+            match protobuf::parse_from_bytes::<RpbErrorResp>(&data) {
+                Ok(resp) => Some((resp.get_errmsg().to_vec(), resp.get_errcode())),
+                Err(_) => None,
+            }
+        } else {
+            None
+        };
+
+        let (message, errcode) = match decoded {
+            Some((message, errcode)) => (message, Some(errcode)),
+            None => (data.clone(), None),
+        };
+
         ServerError {
             code: error_code,
-            data: error_data.into(),
+            data: data,
+            message: message,
+            errcode: errcode,
         }
     }
+
+    /// get the message code of the response the server sent
+    pub fn get_code(&self) -> u8 {
+        self.code
+    }
+
+    /// get the raw data of the response the server sent
+    pub fn get_data(&self) -> Vec<u8> {
+        self.data.clone()
+    }
+
+    /// get the error message, decoded from `RpbErrorResp` when possible
+    pub fn get_message(&self) -> String {
+        String::from_utf8_lossy(self.message.as_slice()).into_owned()
+    }
+
+    /// get the error code from `RpbErrorResp`, if the server sent one
+    pub fn get_errcode(&self) -> Option<u32> {
+        self.errcode
+    }
+
+    /// whether or not the requested object, bucket type or index was not found
+    pub fn is_not_found(&self) -> bool {
+        let message = self.message_lowercase();
+        message.contains("notfound") || message.contains("not_found") ||
+        message.contains("not found")
+    }
+
+    /// whether or not the request timed out on the server
+    pub fn is_timeout(&self) -> bool {
+        self.message_lowercase().contains("timeout")
+    }
+
+    /// whether or not the server is overloaded and shedding requests
+    pub fn is_overload(&self) -> bool {
+        self.message_lowercase().contains("overload")
+    }
+
+    /// whether or not a conditional store failed, either because "if_none_match" was set and the
+    /// object exists ("match_found"), or "if_not_modified" was set and the object changed
+    /// ("modified")
+    pub fn is_precondition_failed(&self) -> bool {
+        let message = self.message_lowercase();
+        let message = message.trim();
+        message == "match_found" || message == "modified"
+    }
+
+    /// whether or not the request may succeed if it is sent again, such as after a timeout,
+    /// overload or when too few vnodes or nodes were available
+    pub fn is_retryable(&self) -> bool {
+        let message = self.message_lowercase();
+        self.is_timeout() || self.is_overload() || message.contains("insufficient_vnodes") ||
+        message.contains("all_nodes_down") || message.contains("unavailable")
+    }
+
+    fn message_lowercase(&self) -> String {
+        self.get_message().to_lowercase()
+    }
 }
 
 impl error::Error for ServerError {
@@ -27,10 +111,15 @@ impl error::Error for ServerError {
 
 impl fmt::Display for ServerError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f,
-               "received code {}, error was: {}",
-               self.code,
-               String::from_utf8_lossy(self.data.as_slice()))
+        match self.errcode {
+            Some(errcode) => {
+                write!(f,
+                       "received error code {}, error was: {}",
+                       errcode,
+                       self.get_message())
+            }
+            None => write!(f, "received code {}, error was: {}", self.code, self.get_message()),
+        }
     }
 }
 
@@ -40,6 +129,14 @@ pub enum RiakErr {
     IoError(io::Error),
     ProtobufError(protobuf::ProtobufError),
     ServerError(ServerError),
+    InvalidIndex(String),
+    InvalidMapReduce(String),
+    InvalidDataTypeUpdate(String),
+    PoolTimeout,
+    NoNodesAvailable,
+    TlsError(String),
+    CodecError(String),
+    CompressionError(String),
 }
 
 impl fmt::Display for RiakErr {
@@ -48,6 +145,16 @@ impl fmt::Display for RiakErr {
             RiakErr::IoError(ref err) => write!(f, "error pinging riak: {}", err),
             RiakErr::ProtobufError(ref err) => write!(f, "connection to riak terminated: {}", err),
             RiakErr::ServerError(ref err) => write!(f, "error from server: {}", err),
+            RiakErr::InvalidIndex(ref msg) => write!(f, "invalid secondary index: {}", msg),
+            RiakErr::InvalidMapReduce(ref msg) => write!(f, "invalid mapreduce job: {}", msg),
+            RiakErr::InvalidDataTypeUpdate(ref msg) => {
+                write!(f, "invalid data type update: {}", msg)
+            }
+            RiakErr::PoolTimeout => write!(f, "timed out waiting for a pooled connection"),
+            RiakErr::NoNodesAvailable => write!(f, "no riak nodes are available"),
+            RiakErr::TlsError(ref msg) => write!(f, "tls error: {}", msg),
+            RiakErr::CodecError(ref msg) => write!(f, "codec error: {}", msg),
+            RiakErr::CompressionError(ref msg) => write!(f, "compression error: {}", msg),
         }
     }
 }
@@ -58,6 +165,14 @@ impl error::Error for RiakErr {
             RiakErr::IoError(ref err) => err.description(),
             RiakErr::ProtobufError(ref err) => err.description(),
             RiakErr::ServerError(ref err) => err.description(),
+            RiakErr::InvalidIndex(_) => "invalid secondary index",
+            RiakErr::InvalidMapReduce(_) => "invalid mapreduce job",
+            RiakErr::InvalidDataTypeUpdate(_) => "invalid data type update",
+            RiakErr::PoolTimeout => "timed out waiting for a pooled connection",
+            RiakErr::NoNodesAvailable => "no riak nodes are available",
+            RiakErr::TlsError(_) => "tls error",
+            RiakErr::CodecError(_) => "codec error",
+            RiakErr::CompressionError(_) => "compression error",
         }
     }
 
@@ -66,6 +181,14 @@ impl error::Error for RiakErr {
             RiakErr::IoError(ref err) => Some(err),
             RiakErr::ProtobufError(ref err) => Some(err),
             RiakErr::ServerError(ref err) => Some(err),
+            RiakErr::InvalidIndex(_) => None,
+            RiakErr::InvalidMapReduce(_) => None,
+            RiakErr::InvalidDataTypeUpdate(_) => None,
+            RiakErr::PoolTimeout => None,
+            RiakErr::NoNodesAvailable => None,
+            RiakErr::TlsError(_) => None,
+            RiakErr::CodecError(_) => None,
+            RiakErr::CompressionError(_) => None,
         }
     }
 }
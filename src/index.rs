@@ -0,0 +1,304 @@
+/// Secondary index (2i) related structs for querying objects by index.
+///
+/// For more information: https://docs.basho.com/riak/kv/latest/developing/usage/secondary-indexes/
+
+use errors::RiakErr;
+use object::FetchObjectResp;
+use private_traits::IndexQueryPrivate;
+use protobuf::Message;
+use rpb::riak_kv::{RpbIndexReq, RpbIndexReq_IndexQueryType};
+
+/// `IndexQuery` represents a query against a secondary index, either for an exact match or a
+/// range of values.
+///
+/// # Examples
+///
+/// ```
+/// use riak::index::IndexQuery;
+///
+/// let mut query = IndexQuery::new_range("testbucket", "age_int", "18", "35");
+/// query.set_return_terms(true);
+/// query.set_max_results(100);
+/// ```
+#[derive(Clone, Debug)]
+pub struct IndexQuery(RpbIndexReq);
+
+impl IndexQuery {
+    /// constructs a new `IndexQuery` for objects where the index exactly matches `key`
+    pub fn new_eq<T: Into<Vec<u8>>>(bucket: T, index: T, key: T) -> IndexQuery {
+        let mut req = RpbIndexReq::new();
+        req.set_bucket(bucket.into());
+        req.set_index(index.into());
+        req.set_qtype(RpbIndexReq_IndexQueryType::eq);
+        req.set_key(key.into());
+        IndexQuery(req)
+    }
+
+    /// constructs a new `IndexQuery` for objects where the index falls between `range_min` and
+    /// `range_max` inclusive
+    pub fn new_range<T: Into<Vec<u8>>>(bucket: T,
+                                       index: T,
+                                       range_min: T,
+                                       range_max: T)
+                                       -> IndexQuery {
+        let mut req = RpbIndexReq::new();
+        req.set_bucket(bucket.into());
+        req.set_index(index.into());
+        req.set_qtype(RpbIndexReq_IndexQueryType::range);
+        req.set_range_min(range_min.into());
+        req.set_range_max(range_max.into());
+        IndexQuery(req)
+    }
+
+    /// get the value of the "bucket_type" property
+    pub fn get_bucket_type(&self) -> Option<Vec<u8>> {
+        if self.0.has_field_type() {
+            Some(self.0.get_field_type().to_vec())
+        } else {
+            None
+        }
+    }
+
+    /// set the value of the "bucket_type" property
+    pub fn set_bucket_type<T: Into<Vec<u8>>>(&mut self, bucket_type: T) {
+        self.0.set_field_type(bucket_type.into());
+    }
+
+    /// get the value of the "bucket" property
+    pub fn get_bucket(&self) -> Vec<u8> {
+        self.0.get_bucket().to_vec()
+    }
+
+    /// set the value of the "bucket" property
+    pub fn set_bucket<T: Into<Vec<u8>>>(&mut self, bucket: T) {
+        self.0.set_bucket(bucket.into());
+    }
+
+    /// get the value of the "index" property
+    pub fn get_index(&self) -> Vec<u8> {
+        self.0.get_index().to_vec()
+    }
+
+    /// set the value of the "index" property
+    pub fn set_index<T: Into<Vec<u8>>>(&mut self, index: T) {
+        self.0.set_index(index.into());
+    }
+
+    /// get the value of the "key" property, used for exact match queries
+    pub fn get_key(&self) -> Option<Vec<u8>> {
+        if self.0.has_key() {
+            Some(self.0.get_key().to_vec())
+        } else {
+            None
+        }
+    }
+
+    /// get the value of the "range_min" property, used for range queries
+    pub fn get_range_min(&self) -> Option<Vec<u8>> {
+        if self.0.has_range_min() {
+            Some(self.0.get_range_min().to_vec())
+        } else {
+            None
+        }
+    }
+
+    /// get the value of the "range_max" property, used for range queries
+    pub fn get_range_max(&self) -> Option<Vec<u8>> {
+        if self.0.has_range_max() {
+            Some(self.0.get_range_max().to_vec())
+        } else {
+            None
+        }
+    }
+
+    /// get the value of the "return_terms" property
+    pub fn get_return_terms(&self) -> Option<bool> {
+        if self.0.has_return_terms() {
+            Some(self.0.get_return_terms())
+        } else {
+            None
+        }
+    }
+
+    /// set the value of the "return_terms" property, only honored for range queries
+    pub fn set_return_terms(&mut self, return_terms: bool) {
+        self.0.set_return_terms(return_terms);
+    }
+
+    /// get the value of the "max_results" property
+    pub fn get_max_results(&self) -> Option<u32> {
+        if self.0.has_max_results() {
+            Some(self.0.get_max_results())
+        } else {
+            None
+        }
+    }
+
+    /// set the value of the "max_results" property, enabling pagination
+    pub fn set_max_results(&mut self, max_results: u32) {
+        self.0.set_max_results(max_results);
+    }
+
+    /// get the value of the "continuation" property
+    pub fn get_continuation(&self) -> Option<Vec<u8>> {
+        if self.0.has_continuation() {
+            Some(self.0.get_continuation().to_vec())
+        } else {
+            None
+        }
+    }
+
+    /// set the value of the "continuation" property, as returned by a previous page of results
+    pub fn set_continuation<T: Into<Vec<u8>>>(&mut self, continuation: T) {
+        self.0.set_continuation(continuation.into());
+    }
+
+    /// get the value of the "timeout" property
+    pub fn get_timeout(&self) -> Option<u32> {
+        if self.0.has_timeout() {
+            Some(self.0.get_timeout())
+        } else {
+            None
+        }
+    }
+
+    /// set the value of the "timeout" property
+    pub fn set_timeout(&mut self, timeout: u32) {
+        self.0.set_timeout(timeout);
+    }
+
+    /// get the value of the "term_regex" property
+    pub fn get_term_regex(&self) -> Option<Vec<u8>> {
+        if self.0.has_term_regex() {
+            Some(self.0.get_term_regex().to_vec())
+        } else {
+            None
+        }
+    }
+
+    /// set the value of the "term_regex" property, only honored for range queries on `_bin`
+    /// indexes
+    pub fn set_term_regex<T: Into<Vec<u8>>>(&mut self, term_regex: T) {
+        self.0.set_term_regex(term_regex.into());
+    }
+
+    /// get the value of the "pagination_sort" property
+    pub fn get_pagination_sort(&self) -> Option<bool> {
+        if self.0.has_pagination_sort() {
+            Some(self.0.get_pagination_sort())
+        } else {
+            None
+        }
+    }
+
+    /// set the value of the "pagination_sort" property
+    pub fn set_pagination_sort(&mut self, pagination_sort: bool) {
+        self.0.set_pagination_sort(pagination_sort);
+    }
+
+    /// get the value of the "return_body" property
+    pub fn get_return_body(&self) -> Option<bool> {
+        if self.0.has_return_body() {
+            Some(self.0.get_return_body())
+        } else {
+            None
+        }
+    }
+
+    /// set the value of the "return_body" property, returning the matching objects rather than
+    /// their keys
+    pub fn set_return_body(&mut self, return_body: bool) {
+        self.0.set_return_body(return_body);
+    }
+
+    /// get the value of the "cover_context" property
+    pub fn get_cover_context(&self) -> Option<Vec<u8>> {
+        if self.0.has_cover_context() {
+            Some(self.0.get_cover_context().to_vec())
+        } else {
+            None
+        }
+    }
+
+    /// set the value of the "cover_context" property, limiting the query to the part of the
+    /// keyspace described by a `CoverageEntry`
+    pub fn set_cover_context<T: Into<Vec<u8>>>(&mut self, cover_context: T) {
+        self.0.set_cover_context(cover_context.into());
+    }
+}
+
+impl IndexQueryPrivate for IndexQuery {
+    fn write_to_bytes(&self, stream: bool) -> Result<Vec<u8>, RiakErr> {
+        let mut req = self.0.clone();
+        req.set_stream(stream);
+        match req.write_to_bytes() {
+            Ok(bytes) => Ok(bytes),
+            Err(error) => Err(RiakErr::ProtobufError(error)),
+        }
+    }
+}
+
+/// `IndexResults` represents the results of an `IndexQuery`.
+///
+/// Depending on the query, matches are returned as keys, as `(term, key)` pairs when
+/// "return_terms" was set, or as `(key, object)` pairs when "return_body" was set. A
+/// continuation is returned when more results are available than "max_results" allowed.
+#[derive(Clone, Debug)]
+pub struct IndexResults {
+    keys: Vec<Vec<u8>>,
+    terms: Vec<(Vec<u8>, Vec<u8>)>,
+    objects: Vec<(Vec<u8>, FetchObjectResp)>,
+    continuation: Option<Vec<u8>>,
+}
+
+impl IndexResults {
+    /// constructs a new empty `IndexResults`
+    pub fn new() -> IndexResults {
+        IndexResults {
+            keys: Vec::new(),
+            terms: Vec::new(),
+            objects: Vec::new(),
+            continuation: None,
+        }
+    }
+
+    /// get the keys that matched the query
+    pub fn get_keys(&self) -> Vec<Vec<u8>> {
+        self.keys.clone()
+    }
+
+    /// set the keys that matched the query
+    pub fn set_keys(&mut self, keys: Vec<Vec<u8>>) {
+        self.keys = keys;
+    }
+
+    /// get the `(term, key)` pairs that matched the query
+    pub fn get_terms(&self) -> Vec<(Vec<u8>, Vec<u8>)> {
+        self.terms.clone()
+    }
+
+    /// set the `(term, key)` pairs that matched the query
+    pub fn set_terms(&mut self, terms: Vec<(Vec<u8>, Vec<u8>)>) {
+        self.terms = terms;
+    }
+
+    /// get the `(key, object)` pairs that matched the query
+    pub fn get_objects(&self) -> Vec<(Vec<u8>, FetchObjectResp)> {
+        self.objects.clone()
+    }
+
+    /// set the `(key, object)` pairs that matched the query
+    pub fn set_objects(&mut self, objects: Vec<(Vec<u8>, FetchObjectResp)>) {
+        self.objects = objects;
+    }
+
+    /// get the continuation to set on the query to fetch the next page of results
+    pub fn get_continuation(&self) -> Option<Vec<u8>> {
+        self.continuation.clone()
+    }
+
+    /// set the continuation
+    pub fn set_continuation(&mut self, continuation: Vec<u8>) {
+        self.continuation = Some(continuation);
+    }
+}
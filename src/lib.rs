@@ -32,15 +32,59 @@
 //! riak.store_object(&req).unwrap();
 //! ```
 
+#[cfg(feature = "async")]
+extern crate bytes;
+#[cfg(feature = "serde")]
+extern crate ciborium;
+#[cfg(feature = "compression")]
+extern crate flate2;
+#[cfg(feature = "async")]
+extern crate futures;
 #[macro_use]
 extern crate log;
+#[cfg(feature = "tls")]
+extern crate native_tls;
 extern crate protobuf;
-
+#[cfg(feature = "serde")]
+extern crate rmp_serde;
+#[cfg(feature = "serde")]
+extern crate serde;
+#[cfg(feature = "serde")]
+extern crate serde_json;
+#[cfg(feature = "compression")]
+extern crate snap;
+#[cfg(feature = "async")]
+extern crate tokio;
+#[cfg(feature = "async")]
+extern crate tokio_util;
+#[cfg(feature = "compression")]
+extern crate zstd;
+
+#[cfg(feature = "async")]
+pub mod async_client;
 pub mod bucket;
+pub mod cluster;
+#[cfg(feature = "serde")]
+pub mod codec;
+#[cfg(feature = "compression")]
+pub mod compression;
+pub mod counter;
+pub mod coverage;
+pub mod datatypes;
 pub mod errors;
+pub mod index;
+pub mod mapreduce;
 pub mod object;
+pub mod pool;
 pub mod preflist;
+pub mod resolver;
+#[cfg(feature = "tls")]
+pub mod security;
 pub mod stream;
+#[cfg(feature = "testing")]
+pub mod testing;
+pub mod timeseries;
+pub mod transport;
 pub mod yokozuna;
 
 mod connection;
@@ -48,32 +92,69 @@ mod private_traits;
 mod rpb;
 
 use bucket::BucketProps;
+#[cfg(feature = "serde")]
+use codec::{Codec, decode_content};
+#[cfg(feature = "compression")]
+use compression::{CompressionConfig, decompress_contents};
 use connection::RiakConn;
+use counter::{GetCounterReq, IncrementCounterReq};
+use coverage::CoverageEntry;
+use datatypes::{FetchDataTypeReq, FetchDataTypeResp, UpdateDataTypeReq, UpdateDataTypeResp};
 use errors::RiakErr;
-use object::{DeleteObjectReq, FetchObjectReq, StoreObjectReq, FetchObjectResp};
+use index::{IndexQuery, IndexResults};
+use mapreduce::MapReduceJob;
+use object::{DeleteObjectReq, FetchObjectReq, ObjectContent, StoreObjectReq, StoreObjectResp,
+             FetchObjectResp};
 use preflist::PreflistItem;
-use private_traits::{BucketPropsPrivate, DeleteObjectReqPrivate, YokozunaIndexPrivate};
-use protobuf::{Message, parse_from_bytes};
+use resolver::{ConflictResolver, LastModifiedWins};
+use private_traits::{BucketPropsPrivate, DeleteObjectReqPrivate, FetchDataTypeRespPrivate,
+                     IndexQueryPrivate, UpdateDataTypeReqPrivate, YokozunaIndexPrivate};
+use protobuf::{Message, RepeatedField, parse_from_bytes};
 use rpb::codes;
 use rpb::riak::{RpbGetBucketReq, RpbGetBucketResp, RpbGetBucketTypeReq, RpbGetServerInfoResp,
                 RpbResetBucketReq};
-use rpb::riak_kv::{RpbGetResp, RpbGetBucketKeyPreflistReq, RpbGetBucketKeyPreflistResp};
+use rpb::riak_dt::{DtFetchResp, DtUpdateResp};
+use rpb::riak_kv::{RpbCounterGetResp, RpbCounterUpdateResp, RpbCoverageReq, RpbGetResp,
+                   RpbPutResp, RpbGetBucketKeyPreflistReq, RpbGetBucketKeyPreflistResp};
 use rpb::riak_yokozuna::{RpbYokozunaSchema, RpbYokozunaSchemaPutReq, RpbYokozunaSchemaGetReq,
                          RpbYokozunaSchemaGetResp, RpbYokozunaIndexGetReq,
                          RpbYokozunaIndexGetResp, RpbYokozunaIndexDeleteReq};
-use rpb::utils::{rpb_get_resp_to_fetch_object_resp, RpbGenerator};
+use rpb::riak_search::RpbSearchQueryResp;
+use rpb::riak_ts::{TsDelReq, TsGetReq, TsGetResp, TsInterpolation, TsListKeysReq, TsListKeysResp,
+                   TsPutReq, TsQueryReq, TsQueryResp};
+#[cfg(feature = "tls")]
+use security::SecurityConfig;
+#[cfg(feature = "serde")]
+use serde::Serialize;
+#[cfg(feature = "serde")]
+use serde::de::DeserializeOwned;
+use rpb::utils::{coverage_response_to_coverage_entries, index_response_code,
+                 index_response_to_index_results, row_to_rpb_ts_row, rpb_get_resp_to_fetch_object_resp,
+                 rpb_put_resp_to_store_object_resp,
+                 rpb_search_query_resp_to_search_results,
+                 rpb_ts_columns_and_rows_to_query_results, rpb_ts_row_to_row,
+                 ts_cells_to_rpb_ts_cells, RpbGenerator};
 use std::net::ToSocketAddrs;
-use stream::{BucketStream, KeyStream};
-use yokozuna::{SearchQuery, YokozunaIndex};
+use stream::{BucketStream, IndexStream, KeyStream, MapReduceStream, ObjectFold};
+use timeseries::{QueryResults, Row, TsCell};
+use transport::{TcpTransport, Transport};
+use yokozuna::{SearchQuery, SearchResults, YokozunaIndex};
 
 // Defaults
 static DEFAULT_TIMEOUT: u32 = 3600;
+static DEFAULT_UPDATE_RETRIES: u32 = 3;
 
 /// `Client` Represents a connection to a Riak server's Protocol Buffers API.
+///
+/// Messages are carried by a `Transport`, which is a `TcpTransport` unless the `Client` is
+/// constructed with `Client::from_transport`.
 #[derive(Debug)]
-pub struct Client {
-    connection: RiakConn,
+pub struct Client<C: Transport = TcpTransport> {
+    connection: RiakConn<C>,
     timeout: u32,
+    update_retries: u32,
+    #[cfg(feature = "compression")]
+    compression: Option<CompressionConfig>,
 }
 
 impl Client {
@@ -110,17 +191,109 @@ impl Client {
     ///
     /// TODO
     pub fn new_with_timeout<A: ToSocketAddrs>(addr: A, timeout: u32) -> Result<Client, RiakErr> {
-        let connection = match RiakConn::new(addr, timeout) {
-            Ok(connection) => connection,
+        let connection = match TcpTransport::new(addr, timeout) {
+            Ok(transport) => RiakConn::new(transport),
+            Err(error) => return Err(error),
+        };
+
+        Ok(Client {
+            connection: connection,
+            timeout: timeout,
+            update_retries: DEFAULT_UPDATE_RETRIES,
+            #[cfg(feature = "compression")]
+            compression: None,
+        })
+    }
+
+    /// Constructs a new `Client` for a Riak node with security enabled, using the default
+    /// timeout.
+    ///
+    /// The connection is upgraded to TLS and then authenticated with the credentials provided.
+    /// This requires the "tls" feature.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use riak::Client;
+    /// use riak::security::SecurityConfig;
+    ///
+    /// let security = SecurityConfig::new("riakuser", "riakpass");
+    /// let mut riak = Client::new_secure("10.0.0.2:8087", &security).unwrap();
+    /// riak.ping().unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns `RiakErr::TlsError` if the TLS settings are invalid or the handshake fails, and
+    /// `RiakErr::ServerError` if Riak rejects the upgrade or the credentials.
+    #[cfg(feature = "tls")]
+    pub fn new_secure<A: ToSocketAddrs>(addr: A,
+                                        security: &SecurityConfig)
+                                        -> Result<Client, RiakErr> {
+        Client::new_secure_with_timeout(addr, security, DEFAULT_TIMEOUT)
+    }
+
+    /// Constructs a new `Client` for a Riak node with security enabled, with a timeout (in
+    /// seconds) provided.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use riak::Client;
+    /// use riak::security::SecurityConfig;
+    ///
+    /// let security = SecurityConfig::new("riakuser", "riakpass");
+    /// let mut riak = Client::new_secure_with_timeout("10.0.0.2:8087", &security, 3600).unwrap();
+    /// riak.ping().unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// See `Client::new_secure`.
+    #[cfg(feature = "tls")]
+    pub fn new_secure_with_timeout<A: ToSocketAddrs>(addr: A,
+                                                     security: &SecurityConfig,
+                                                     timeout: u32)
+                                                     -> Result<Client, RiakErr> {
+        let connection = match TcpTransport::new_secure(addr, timeout, security) {
+            Ok(transport) => RiakConn::new(transport),
             Err(error) => return Err(error),
         };
 
         Ok(Client {
             connection: connection,
             timeout: timeout,
+            update_retries: DEFAULT_UPDATE_RETRIES,
+            #[cfg(feature = "compression")]
+            compression: None,
         })
     }
 
+}
+
+impl<C: Transport> Client<C> {
+    /// Constructs a new `Client` that sends requests over the `Transport` provided.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use riak::Client;
+    /// use riak::transport::TcpTransport;
+    ///
+    /// let transport = TcpTransport::new("10.0.0.2:8087", 3600).unwrap();
+    /// let mut riak = Client::from_transport(transport);
+    /// riak.ping().unwrap();
+    /// ```
+    pub fn from_transport(transport: C) -> Client<C> {
+        Client {
+            timeout: transport.get_timeout(),
+            connection: RiakConn::new(transport),
+            update_retries: DEFAULT_UPDATE_RETRIES,
+            #[cfg(feature = "compression")]
+            compression: None,
+        }
+    }
+
     /// Set the timeout (in seconds) allowed for future requests.
     ///
     /// # Examples
@@ -135,6 +308,57 @@ impl Client {
         self.timeout = timeout;
     }
 
+    /// Set how many times `update_object` retries after another client modified the object
+    /// between the fetch and the store.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use riak::Client;
+    ///
+    /// let mut riak = Client::new("10.0.0.2:8087").unwrap();
+    /// riak.set_update_retries(10);
+    /// ```
+    pub fn set_update_retries(&mut self, update_retries: u32) {
+        self.update_retries = update_retries;
+    }
+
+    /// Get how values stored by `store_object` are compressed, if they are.
+    ///
+    /// This requires the "compression" feature.
+    #[cfg(feature = "compression")]
+    pub fn get_compression(&self) -> Option<CompressionConfig> {
+        self.compression
+    }
+
+    /// Compress values stored by `store_object` from a size threshold, setting their
+    /// "content_encoding". See `riak::compression` for how compressed values are fetched.
+    ///
+    /// This requires the "compression" feature.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use riak::Client;
+    /// use riak::compression::{Compression, CompressionConfig};
+    ///
+    /// let mut riak = Client::new("10.0.0.2:8087").unwrap();
+    /// riak.set_compression(CompressionConfig::new(Compression::Gzip, 4096));
+    /// ```
+    #[cfg(feature = "compression")]
+    pub fn set_compression(&mut self, compression: CompressionConfig) {
+        self.compression = Some(compression);
+    }
+
+    /// Stop compressing values stored by `store_object`, compressed values are still
+    /// decompressed by `fetch_object`.
+    ///
+    /// This requires the "compression" feature.
+    #[cfg(feature = "compression")]
+    pub fn disable_compression(&mut self) {
+        self.compression = None;
+    }
+
     /// Reconnect to the Riak server originally connected to when this client was initiated.
     ///
     /// # Examples
@@ -218,19 +442,17 @@ impl Client {
     ///
     /// let mut riak = Client::new("10.0.0.2:8087").unwrap();
     /// let mut bucketstream = riak.stream_buckets().unwrap();
+    /// bucketstream.set_bucket_type("default");
     ///
-    /// loop {
-    ///     match bucketstream.next() {
-    ///         Some(buckets) => println!("found buckets {:?}", buckets.unwrap()),
-    ///         None => break,
-    ///     };
+    /// for buckets in bucketstream {
+    ///     println!("found buckets {:?}", buckets.unwrap());
     /// }
     /// ```
     ///
     /// # Errors
     ///
     /// TODO
-    pub fn stream_buckets(&mut self) -> Result<BucketStream, RiakErr> {
+    pub fn stream_buckets(&mut self) -> Result<BucketStream<C>, RiakErr> {
         BucketStream::new(self)
     }
 
@@ -486,19 +708,17 @@ impl Client {
     /// let mut riak = Client::new("10.0.0.2:8087").unwrap();
     ///
     /// let mut keystream = riak.stream_keys("testbucket").unwrap();
+    /// keystream.set_timeout(60000);
     ///
-    /// loop {
-    ///     match keystream.next() {
-    ///         Some(keys_result) => println!("found keys {:?}", keys_result.unwrap()),
-    ///         None => break,
-    ///     };
+    /// for key in keystream.items() {
+    ///     println!("found key {:?}", key.unwrap());
     /// }
     /// ```
     ///
     /// # Errors
     ///
     /// TODO
-    pub fn stream_keys<T: Into<Vec<u8>>>(&mut self, bucket: T) -> Result<KeyStream, RiakErr> {
+    pub fn stream_keys<T: Into<Vec<u8>>>(&mut self, bucket: T) -> Result<KeyStream<C>, RiakErr> {
         KeyStream::new(self, bucket.into())
     }
 
@@ -532,6 +752,9 @@ impl Client {
 
     /// Stores an object on the Riak server.
     ///
+    /// The response contains the key when it was generated by Riak, and the vector clock and
+    /// contents of the stored object when "return_body" or "return_head" was set.
+    ///
     /// # Examples
     ///
     /// ```
@@ -542,26 +765,71 @@ impl Client {
     ///
     /// let contents = ObjectContent::new("This is a test!".as_bytes());
     /// let mut req = StoreObjectReq::new("testbucket", contents);
-    /// req.set_key("testkey");
+    /// req.set_return_head(true);
     ///
-    /// riak.store_object(&req).unwrap();
+    /// let resp = riak.store_object(&req).unwrap();
+    /// println!("stored object with generated key {:?}", resp.get_key().unwrap());
     /// ```
     ///
     /// # Errors
     ///
     /// TODO
-    pub fn store_object(&mut self, req: &StoreObjectReq) -> Result<(), RiakErr> {
+    pub fn store_object(&mut self, req: &StoreObjectReq) -> Result<StoreObjectResp, RiakErr> {
+        // compress the value if it is large enough
+        #[cfg(feature = "compression")]
+        let compressed_req = match self.compression {
+            Some(compression) => {
+                match compression.compress_content(&req.get_content()) {
+                    Ok(content) => {
+                        let mut compressed_req = req.clone();
+                        compressed_req.set_content(content);
+                        Some(compressed_req)
+                    }
+                    Err(error) => return Err(error),
+                }
+            }
+            None => None,
+        };
+        #[cfg(feature = "compression")]
+        let req = compressed_req.as_ref().unwrap_or(req);
+
         // convert the request to protobuf bytes
         let bytes = match req.write_to_bytes() {
             Ok(b) => b,
             Err(err) => return Err(err),
         };
 
-        // make the exchange and check for errors
-        match self.connection.exchange(codes::RpbPutReq, codes::RpbPutResp, &bytes) {
-            Ok(_) => Ok(()),
-            Err(err) => Err(err),
+        // make the exchange and get the response bytes
+        let response = match self.connection.exchange(codes::RpbPutReq, codes::RpbPutResp, &bytes) {
+            Ok(response) => response,
+            Err(err) => return Err(err),
+        };
+
+        // parse the response into an `RpbPutResp` struct
+        let mut rpb_put_resp = match parse_from_bytes::<RpbPutResp>(&response) {
+            Ok(rpb_put_resp) => rpb_put_resp,
+            Err(err) => return Err(RiakErr::ProtobufError(err)),
+        };
+
+        // if no errors occur build a `StoreObjectResp` to return
+        #[cfg_attr(not(feature = "compression"), allow(unused_mut))]
+        let mut store_object_resp = rpb_put_resp_to_store_object_resp(&mut rpb_put_resp);
+
+        // decompress any values returned with "return_body", "return_head" alone returns empty
+        // values that keep their "content_encoding"
+        #[cfg(feature = "compression")]
+        {
+            let head_only = req.get_return_head() == Some(true) &&
+                            req.get_return_body() != Some(true);
+            if !head_only {
+                match decompress_contents(&store_object_resp.get_content()) {
+                    Ok(content) => store_object_resp.set_content(content),
+                    Err(error) => return Err(error),
+                };
+            }
         }
+
+        Ok(store_object_resp)
     }
 
     /// Fetches an object from the Riak server.
@@ -603,223 +871,776 @@ impl Client {
         };
 
         // if no errors occur build a `FetchObjectResp` to return
-        let fetch_object_resp = rpb_get_resp_to_fetch_object_resp(&mut rpb_get_resp);
+        #[cfg_attr(not(feature = "compression"), allow(unused_mut))]
+        let mut fetch_object_resp = rpb_get_resp_to_fetch_object_resp(&mut rpb_get_resp);
+
+        // decompress values with a recognised "content_encoding", unless only the metadata was
+        // returned, in which case the values are empty and keep their "content_encoding"
+        #[cfg(feature = "compression")]
+        {
+            let head_only = req.get_head() == Some(true) ||
+                            fetch_object_resp.get_unchanged() == Some(true);
+            if !head_only {
+                match decompress_contents(&fetch_object_resp.get_content()) {
+                    Ok(content) => fetch_object_resp.set_content(content),
+                    Err(error) => return Err(error),
+                };
+            }
+        }
+
         Ok(fetch_object_resp)
     }
 
-    /// Deletes an object from Riak
+    /// Fetches an object and resolves any siblings into a single value with `resolver`,
+    /// optionally storing the resolved value back to Riak so the siblings are replaced.
+    ///
+    /// The returned `FetchObjectResp` contains at most one value. When the resolved value is
+    /// stored back the returned vclock is the one produced by that store, so the response can
+    /// be used for further updates. The values are needed to store them back, so "head" and
+    /// "if_modified" are ignored when `store` is set.
     ///
     /// # Examples
     ///
     /// ```
     /// use riak::Client;
-    /// use riak::object::DeleteObjectReq;
+    /// use riak::object::FetchObjectReq;
+    /// use riak::resolver::LastModifiedWins;
     ///
     /// let mut riak = Client::new("10.0.0.2:8087").unwrap();
     ///
-    /// let mut request = DeleteObjectReq::new("testbucket", "testkey");
-    /// request.set_dw(3);
-    ///
-    /// riak.delete_object(&request).unwrap();
+    /// let req = FetchObjectReq::new("testbucket", "testkey");
+    /// let object = riak.fetch_resolved(&req, &LastModifiedWins, true).unwrap();
+    /// println!("testkey resolved to: {:?}", object.get_content());
     /// ```
     ///
     /// # Errors
     ///
-    /// TODO
-    pub fn delete_object(&mut self, request: &DeleteObjectReq) -> Result<(), RiakErr> {
-        // parse the request into bytes to send out
-        let bytes = match request.write_to_bytes() {
-            Ok(bytes) => bytes,
+    /// Returns any error from fetching the object, or from storing it back when `store` is set.
+    pub fn fetch_resolved<R: ConflictResolver + ?Sized>(&mut self,
+                                                        req: &FetchObjectReq,
+                                                        resolver: &R,
+                                                        store: bool)
+                                                        -> Result<FetchObjectResp, RiakErr> {
+        // resolving from metadata alone would store an empty value over the siblings
+        let mut fetch_req = req.clone();
+        if store {
+            fetch_req.set_head(false);
+            fetch_req.clear_if_modified();
+        }
+
+        let mut fetched = match self.fetch_object(&fetch_req) {
+            Ok(fetched) => fetched,
             Err(error) => return Err(error),
         };
 
-        // make the exchange, nothing to return on success
-        match self.connection.exchange(codes::RpbDelReq, codes::RpbDelResp, &bytes) {
-            Ok(_) => Ok(()),
-            Err(error) => Err(error),
+        // nothing to resolve unless there are siblings
+        let siblings = fetched.get_content();
+        if siblings.len() < 2 {
+            return Ok(fetched);
+        }
+
+        let resolved = resolver.resolve(&siblings);
+        fetched.set_content(vec![resolved.clone()]);
+
+        if store {
+            let mut store_req = StoreObjectReq::new(req.get_bucket(), resolved);
+            store_req.set_key(req.get_key());
+            store_req.set_vclock(fetched.get_vclock());
+            store_req.set_return_head(true);
+            if let Some(bucket_type) = req.get_bucket_type() {
+                store_req.set_bucket_type(bucket_type);
+            }
+
+            let stored = match self.store_object(&store_req) {
+                Ok(stored) => stored,
+                Err(error) => return Err(error),
+            };
+
+            if let Some(vclock) = stored.get_vclock() {
+                fetched.set_vclock(vclock);
+            }
         }
+
+        Ok(fetched)
     }
 
-    /// Fetch the preflist for a bucket/key combination.
+    /// Updates an object with a read-modify-write cycle.
+    ///
+    /// The object is fetched, any siblings are resolved with `LastModifiedWins`, and `update` is
+    /// called with the current value (or `None` if the object doesn't exist) to produce the new
+    /// value. The new value is stored with the fetched vclock and "if_not_modified" set, or with
+    /// "if_none_match" set when the object didn't exist, so concurrent changes are never
+    /// overwritten. If the store fails because the object changed, the whole cycle is retried up
+    /// to the limit set with `set_update_retries`.
     ///
     /// # Examples
     ///
     /// ```
     /// use riak::Client;
+    /// use riak::object::ObjectContent;
     ///
     /// let mut riak = Client::new("10.0.0.2:8087").unwrap();
-    /// let preflist = riak.fetch_preflist("testbucket", "testkey").unwrap();
     ///
-    /// for preflist_item in preflist {
-    ///     if preflist_item.is_primary {
-    ///         println!("found primary partition {:?} for key {:?} on node {:?}", preflist_item.partition, "testkey", preflist_item.node);
-    ///     }
-    /// }
+    /// // append to a log, creating it if needed
+    /// riak.update_object("testbucket", "testlog", |current| {
+    ///     let mut value = match current {
+    ///         Some(content) => content.get_value(),
+    ///         None => Vec::new(),
+    ///     };
+    ///     value.extend_from_slice(b"another entry\n");
+    ///     ObjectContent::new(value)
+    /// }).unwrap();
     /// ```
     ///
     /// # Errors
     ///
-    /// TODO
-    pub fn fetch_preflist<T: Into<Vec<u8>>>(&mut self,
-                                            bucket: T,
-                                            key: T)
-                                            -> Result<Vec<PreflistItem>, RiakErr> {
-        // build the request
-        let mut req = RpbGetBucketKeyPreflistReq::new();
-        req.set_bucket(bucket.into());
-        req.set_key(key.into());
-
-        // parse the request into bytes
-        let bytes = match req.write_to_bytes() {
-            Ok(bytes) => bytes,
-            Err(error) => return Err(RiakErr::ProtobufError(error)),
-        };
-
-        // send the request and check the response
-        let response = match self.connection
-            .exchange(codes::RpbGetBucketKeyPreflistReq,
-                      codes::RpbGetBucketKeyPreflistResp,
-                      &bytes) {
-            Ok(response) => response,
-            Err(error) => return Err(error),
-        };
-
-        // parse the response
-        let rpb_preflist_resp = match parse_from_bytes::<RpbGetBucketKeyPreflistResp>(&response) {
-            Ok(parsed) => parsed,
-            Err(err) => return Err(RiakErr::ProtobufError(err)),
-        };
-
-        // get each item and build a `PreflistItem` for it
-        let mut preflist: Vec<PreflistItem> = Vec::new();
-        for preflist_item in rpb_preflist_resp.get_preflist() {
-            let node = String::from_utf8_lossy(preflist_item.get_node()).into_owned();
-            let converted = PreflistItem::new(preflist_item.get_partition(),
-                                              &node,
-                                              preflist_item.get_primary());
-            preflist.push(converted);
-        }
-
-        Ok(preflist)
+    /// Returns any error from fetching or storing the object, including the precondition failure
+    /// once all retries are used up.
+    pub fn update_object<T, F>(&mut self,
+                               bucket: T,
+                               key: T,
+                               update: F)
+                               -> Result<StoreObjectResp, RiakErr>
+        where T: Into<Vec<u8>>,
+              F: FnMut(Option<ObjectContent>) -> ObjectContent
+    {
+        self.update_object_with_resolver(bucket, key, &LastModifiedWins, update)
     }
 
-    /// Create a search schema
+    /// Updates an object with a read-modify-write cycle like `update_object`, resolving any
+    /// siblings with `resolver`.
     ///
     /// # Examples
     ///
     /// ```
     /// use riak::Client;
-    /// use std::fs::File;
-    /// use std::io::Read;
+    /// use riak::object::ObjectContent;
+    /// use riak::resolver::LongestValue;
     ///
     /// let mut riak = Client::new("10.0.0.2:8087").unwrap();
     ///
-    /// let mut xml: Vec<u8> = Vec::new();
-    /// let mut file = File::open("/tmp/riak-rust-client-default-schema.xml").unwrap();
-    /// let _ = file.read_to_end(&mut xml).unwrap();
-    ///
-    /// let schema_name = "schedule".to_string().into_bytes();
-    /// riak.set_yokozuna_schema(schema_name, xml).unwrap();
+    /// riak.update_object_with_resolver("testbucket", "testkey", &LongestValue, |current| {
+    ///     let value = current.map(|content| content.get_value()).unwrap_or_default();
+    ///     ObjectContent::new(value.to_ascii_uppercase())
+    /// }).unwrap();
     /// ```
     ///
     /// # Errors
     ///
-    /// TODO
-    pub fn set_yokozuna_schema<T: Into<Vec<u8>>>(&mut self,
-                                                 name: T,
-                                                 content: T)
-                                                 -> Result<(), RiakErr> {
-        // build a `RpbYokozunaSchema`
-        let mut schema = RpbYokozunaSchema::new();
-        schema.set_name(name.into());
-        schema.set_content(content.into());
-
-        // build a `RpbYokozunaSchemaPutReq` request
-        let mut req = RpbYokozunaSchemaPutReq::new();
-        req.set_schema(schema);
-
-        // parse the request into bytes to send
-        let bytes = match req.write_to_bytes() {
-            Ok(bytes) => bytes,
-            Err(error) => return Err(RiakErr::ProtobufError(error)),
-        };
-
-        // make the exchange, no return needed on success
-        match self.connection
-            .exchange(codes::RpbYokozunaSchemaPutReq, codes::RpbPutResp, &bytes) {
-            Ok(_) => Ok(()),
-            Err(error) => Err(error),
-        }
+    /// See `update_object`.
+    pub fn update_object_with_resolver<T, R, F>(&mut self,
+                                                bucket: T,
+                                                key: T,
+                                                resolver: &R,
+                                                update: F)
+                                                -> Result<StoreObjectResp, RiakErr>
+        where T: Into<Vec<u8>>,
+              R: ConflictResolver + ?Sized,
+              F: FnMut(Option<ObjectContent>) -> ObjectContent
+    {
+        self.update_object_in(None, bucket.into(), key.into(), resolver, update)
     }
 
-    /// Retrieve a search schema
+    /// Updates an object in a bucket of `bucket_type` with a read-modify-write cycle like
+    /// `update_object`, resolving any siblings with `resolver`.
     ///
     /// # Examples
     ///
     /// ```
     /// use riak::Client;
+    /// use riak::object::ObjectContent;
+    /// use riak::resolver::LastModifiedWins;
     ///
     /// let mut riak = Client::new("10.0.0.2:8087").unwrap();
     ///
-    /// riak.get_yokozuna_schema("schedule").unwrap();
+    /// riak.update_object_with_type("testtype", "testbucket", "testkey", &LastModifiedWins, |_| {
+    ///     ObjectContent::new("replaced")
+    /// }).unwrap();
     /// ```
     ///
     /// # Errors
     ///
-    /// TODO
-    pub fn get_yokozuna_schema<T: Into<Vec<u8>>>(&mut self, name: T) -> Result<Vec<u8>, RiakErr> {
-        // build a request
-        let mut req = RpbYokozunaSchemaGetReq::new();
-        req.set_name(name.into());
-
-        // parse the request into bytes
-        let bytes = match req.write_to_bytes() {
-            Ok(bytes) => bytes,
-            Err(error) => return Err(RiakErr::ProtobufError(error)),
-        };
-
-        // make the exchange and get the response bytes
-        let response = match self.connection.exchange(codes::RpbYokozunaSchemaGetReq,
-                                                      codes::RpbYokozunaSchemaGetResp,
-                                                      &bytes) {
-            Ok(response) => response,
-            Err(error) => return Err(error),
-        };
+    /// See `update_object`.
+    pub fn update_object_with_type<T, R, F>(&mut self,
+                                            bucket_type: T,
+                                            bucket: T,
+                                            key: T,
+                                            resolver: &R,
+                                            update: F)
+                                            -> Result<StoreObjectResp, RiakErr>
+        where T: Into<Vec<u8>>,
+              R: ConflictResolver + ?Sized,
+              F: FnMut(Option<ObjectContent>) -> ObjectContent
+    {
+        self.update_object_in(Some(bucket_type.into()),
+                              bucket.into(),
+                              key.into(),
+                              resolver,
+                              update)
+    }
 
-        // parse the response into a `RpbYokozunaSchemaGetResp`
-        let mut rpb_yokozuna_schema_get_resp =
-            match parse_from_bytes::<RpbYokozunaSchemaGetResp>(&response) {
-                Ok(parsed) => parsed,
-                Err(error) => return Err(RiakErr::ProtobufError(error)),
+    // The read-modify-write cycle of `update_object`, in the default bucket type when
+    // `bucket_type` is `None`.
+    fn update_object_in<R, F>(&mut self,
+                              bucket_type: Option<Vec<u8>>,
+                              bucket: Vec<u8>,
+                              key: Vec<u8>,
+                              resolver: &R,
+                              mut update: F)
+                              -> Result<StoreObjectResp, RiakErr>
+        where R: ConflictResolver + ?Sized,
+              F: FnMut(Option<ObjectContent>) -> ObjectContent
+    {
+        let mut retries = 0;
+
+        loop {
+            // fetch the current value, resolving any siblings
+            let mut fetch_req = FetchObjectReq::new(bucket.clone(), key.clone());
+            if let Some(ref bucket_type) = bucket_type {
+                fetch_req.set_bucket_type(bucket_type.clone());
+            }
+            let fetched = match self.fetch_object(&fetch_req) {
+                Ok(fetched) => fetched,
+                Err(error) => return Err(error),
+            };
+            let siblings = fetched.get_content();
+            let current = match siblings.len() {
+                0 => None,
+                1 => Some(siblings[0].clone()),
+                _ => Some(resolver.resolve(&siblings)),
             };
 
-        // grab the schema out of the response
-        let mut rpb_yokozuna_schema = rpb_yokozuna_schema_get_resp.take_schema();
-
-        // return the contents of the schema
-        Ok(rpb_yokozuna_schema.take_content())
+            // store the new value only if nobody else changed it in the meantime
+            let mut req = StoreObjectReq::new(bucket.clone(), update(current));
+            req.set_key(key.clone());
+            req.set_return_head(true);
+            if let Some(ref bucket_type) = bucket_type {
+                req.set_bucket_type(bucket_type.clone());
+            }
+            let vclock = fetched.get_vclock();
+            if vclock.is_empty() {
+                req.set_if_none_match(true);
+            } else {
+                req.set_vclock(vclock);
+                req.set_if_not_modified(true);
+            }
+
+            match self.store_object(&req) {
+                Err(RiakErr::ServerError(ref error)) if error.is_precondition_failed() &&
+                                                        retries < self.update_retries => {
+                    debug!("object was modified during update, retrying: {}", error);
+                    retries += 1;
+                }
+                result => return result,
+            };
+        }
     }
 
-    /// set a search index
+    /// Stores `value` encoded with `codec`, using `req` for everything but the value.
+    ///
+    /// The value of the content in `req` is replaced with the encoded value and its
+    /// "content_type" and "charset" are set by the codec, any other metadata such as indexes is
+    /// stored as it is.
+    ///
+    /// This requires the "serde" feature.
     ///
     /// # Examples
     ///
     /// ```
     /// use riak::Client;
-    /// use riak::yokozuna::YokozunaIndex;
+    /// use riak::codec::JsonCodec;
+    /// use riak::object::{ObjectContent, StoreObjectReq};
+    /// use std::collections::HashMap;
     ///
     /// let mut riak = Client::new("10.0.0.2:8087").unwrap();
     ///
-    /// let index = YokozunaIndex::new("myindex");
-    /// riak.set_yokozuna_index(index).unwrap();
+    /// let mut user = HashMap::new();
+    /// user.insert("name", "Alice");
+    ///
+    /// let mut req = StoreObjectReq::new("users", ObjectContent::new(Vec::new()));
+    /// req.set_key("alice");
+    /// riak.store_typed(&req, &user, &JsonCodec).unwrap();
     /// ```
     ///
     /// # Errors
     ///
-    /// TODO
-    pub fn set_yokozuna_index(&mut self, index: YokozunaIndex) -> Result<(), RiakErr> {
-        let bytes = match index.write_to_bytes() {
-            Ok(bytes) => bytes,
+    /// Returns `RiakErr::CodecError` if the value can not be encoded, and any error from storing
+    /// the object.
+    #[cfg(feature = "serde")]
+    pub fn store_typed<T, D>(&mut self,
+                             req: &StoreObjectReq,
+                             value: &T,
+                             codec: &D)
+                             -> Result<StoreObjectResp, RiakErr>
+        where T: Serialize + ?Sized,
+              D: Codec
+    {
+        let mut content = req.get_content();
+        match codec.encode_into(value, &mut content) {
+            Ok(()) => (),
+            Err(error) => return Err(error),
+        };
+
+        let mut req = req.clone();
+        req.set_content(content);
+        self.store_object(&req)
+    }
+
+    /// Fetches an object and decodes its value with the codec matching its "content_type", or
+    /// returns `None` if the object does not exist.
+    ///
+    /// This requires the "serde" feature.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use riak::Client;
+    /// use riak::object::FetchObjectReq;
+    /// use std::collections::HashMap;
+    ///
+    /// let mut riak = Client::new("10.0.0.2:8087").unwrap();
+    ///
+    /// let req = FetchObjectReq::new("users", "alice");
+    /// let user: Option<HashMap<String, String>> = riak.fetch_typed(&req).unwrap();
+    /// println!("alice is {:?}", user);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns `RiakErr::CodecError` if the object has siblings, which should be resolved with
+    /// `fetch_resolved` first, or if its value can not be decoded. Any error from fetching the
+    /// object is returned as it is.
+    #[cfg(feature = "serde")]
+    pub fn fetch_typed<T: DeserializeOwned>(&mut self,
+                                            req: &FetchObjectReq)
+                                            -> Result<Option<T>, RiakErr> {
+        let resp = match self.fetch_object(req) {
+            Ok(resp) => resp,
+            Err(error) => return Err(error),
+        };
+
+        let content = resp.get_content();
+        match content.len() {
+            0 => Ok(None),
+            1 => decode_content(&content[0]).map(Some),
+            siblings => {
+                Err(RiakErr::CodecError(format!("object has {} siblings to resolve", siblings)))
+            }
+        }
+    }
+
+    /// Deletes an object from Riak
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use riak::Client;
+    /// use riak::object::DeleteObjectReq;
+    ///
+    /// let mut riak = Client::new("10.0.0.2:8087").unwrap();
+    ///
+    /// let mut request = DeleteObjectReq::new("testbucket", "testkey");
+    /// request.set_dw(3);
+    ///
+    /// riak.delete_object(&request).unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// TODO
+    pub fn delete_object(&mut self, request: &DeleteObjectReq) -> Result<(), RiakErr> {
+        // parse the request into bytes to send out
+        let bytes = match request.write_to_bytes() {
+            Ok(bytes) => bytes,
+            Err(error) => return Err(error),
+        };
+
+        // make the exchange, nothing to return on success
+        match self.connection.exchange(codes::RpbDelReq, codes::RpbDelResp, &bytes) {
+            Ok(_) => Ok(()),
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Increment a legacy counter, returning its new value if "returnvalue" was set.
+    ///
+    /// Legacy counters are only supported in buckets of the "default" bucket type with the
+    /// "allow_mult" property enabled, see `riak::counter`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use riak::Client;
+    /// use riak::counter::IncrementCounterReq;
+    ///
+    /// let mut riak = Client::new("10.0.0.2:8087").unwrap();
+    ///
+    /// let mut request = IncrementCounterReq::new("testbucket", "testcounter", 5);
+    /// request.set_w(2);
+    /// request.set_returnvalue(true);
+    ///
+    /// let value = riak.increment_counter(&request).unwrap();
+    /// println!("testcounter is now {:?}", value);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns `RiakErr::ServerError` if the bucket does not support legacy counters, or if the
+    /// quorum requested can not be met.
+    pub fn increment_counter(&mut self,
+                             request: &IncrementCounterReq)
+                             -> Result<Option<i64>, RiakErr> {
+        // parse the request into bytes to send out
+        let bytes = match request.write_to_bytes() {
+            Ok(bytes) => bytes,
+            Err(error) => return Err(error),
+        };
+
+        // make the exchange and get the response bytes
+        let response = match self.connection
+            .exchange(codes::RpbCounterUpdateReq, codes::RpbCounterUpdateResp, &bytes) {
+            Ok(response) => response,
+            Err(error) => return Err(error),
+        };
+
+        // parse the response, which only has a value if "returnvalue" was set
+        let rpb_counter_update_resp = match parse_from_bytes::<RpbCounterUpdateResp>(&response) {
+            Ok(parsed) => parsed,
+            Err(error) => return Err(RiakErr::ProtobufError(error)),
+        };
+
+        if rpb_counter_update_resp.has_value() {
+            Ok(Some(rpb_counter_update_resp.get_value()))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Get the value of a legacy counter, or `None` if it has never been incremented.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use riak::Client;
+    /// use riak::counter::GetCounterReq;
+    ///
+    /// let mut riak = Client::new("10.0.0.2:8087").unwrap();
+    ///
+    /// let mut request = GetCounterReq::new("testbucket", "testcounter");
+    /// request.set_r(2);
+    ///
+    /// let value = riak.get_counter(&request).unwrap().unwrap_or(0);
+    /// println!("testcounter is {}", value);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns `RiakErr::ServerError` if the bucket does not support legacy counters, or if the
+    /// quorum requested can not be met.
+    pub fn get_counter(&mut self, request: &GetCounterReq) -> Result<Option<i64>, RiakErr> {
+        // parse the request into bytes to send out
+        let bytes = match request.write_to_bytes() {
+            Ok(bytes) => bytes,
+            Err(error) => return Err(error),
+        };
+
+        // make the exchange and get the response bytes
+        let response = match self.connection
+            .exchange(codes::RpbCounterGetReq, codes::RpbCounterGetResp, &bytes) {
+            Ok(response) => response,
+            Err(error) => return Err(error),
+        };
+
+        // parse the response, which has no value if the counter was not found
+        let rpb_counter_get_resp = match parse_from_bytes::<RpbCounterGetResp>(&response) {
+            Ok(parsed) => parsed,
+            Err(error) => return Err(RiakErr::ProtobufError(error)),
+        };
+
+        if rpb_counter_get_resp.has_value() {
+            Ok(Some(rpb_counter_get_resp.get_value()))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Fetch the preflist for a bucket/key combination.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use riak::Client;
+    ///
+    /// let mut riak = Client::new("10.0.0.2:8087").unwrap();
+    /// let preflist = riak.fetch_preflist("testbucket", "testkey").unwrap();
+    ///
+    /// for preflist_item in preflist {
+    ///     if preflist_item.is_primary {
+    ///         println!("found primary partition {:?} for key {:?} on node {:?}", preflist_item.partition, "testkey", preflist_item.node);
+    ///     }
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// TODO
+    pub fn fetch_preflist<T: Into<Vec<u8>>>(&mut self,
+                                            bucket: T,
+                                            key: T)
+                                            -> Result<Vec<PreflistItem>, RiakErr> {
+        // build the request
+        let mut req = RpbGetBucketKeyPreflistReq::new();
+        req.set_bucket(bucket.into());
+        req.set_key(key.into());
+
+        // parse the request into bytes
+        let bytes = match req.write_to_bytes() {
+            Ok(bytes) => bytes,
+            Err(error) => return Err(RiakErr::ProtobufError(error)),
+        };
+
+        // send the request and check the response
+        let response = match self.connection
+            .exchange(codes::RpbGetBucketKeyPreflistReq,
+                      codes::RpbGetBucketKeyPreflistResp,
+                      &bytes) {
+            Ok(response) => response,
+            Err(error) => return Err(error),
+        };
+
+        // parse the response
+        let rpb_preflist_resp = match parse_from_bytes::<RpbGetBucketKeyPreflistResp>(&response) {
+            Ok(parsed) => parsed,
+            Err(err) => return Err(RiakErr::ProtobufError(err)),
+        };
+
+        // get each item and build a `PreflistItem` for it
+        let mut preflist: Vec<PreflistItem> = Vec::new();
+        for preflist_item in rpb_preflist_resp.get_preflist() {
+            let node = String::from_utf8_lossy(preflist_item.get_node()).into_owned();
+            let converted = PreflistItem::new(preflist_item.get_partition(),
+                                              &node,
+                                              preflist_item.get_primary());
+            preflist.push(converted);
+        }
+
+        Ok(preflist)
+    }
+
+    /// Fetch a coverage plan for a bucket, splitting its keyspace into parts that can be read in
+    /// parallel, each from the node listed for it.
+    ///
+    /// Riak plans at least `min_partitions` parts, or its usual plan of one part per few vnodes
+    /// when `min_partitions` is 0. See `CoverageScanner` for reading every key with a plan.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use riak::Client;
+    ///
+    /// let mut riak = Client::new("10.0.0.2:8087").unwrap();
+    /// let plan = riak.coverage_plan("default", "testbucket", 16).unwrap();
+    ///
+    /// for entry in plan {
+    ///     println!("{} is read from {}:{}", entry.keyspace_desc, entry.ip, entry.port);
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns `RiakErr::ServerError` if the plan can not be made, such as when the backend of
+    /// the bucket does not support coverage queries.
+    pub fn coverage_plan<T: Into<Vec<u8>>>(&mut self,
+                                           bucket_type: T,
+                                           bucket: T,
+                                           min_partitions: u32)
+                                           -> Result<Vec<CoverageEntry>, RiakErr> {
+        // build the request
+        let mut req = RpbCoverageReq::new();
+        req.set_field_type(bucket_type.into());
+        req.set_bucket(bucket.into());
+        if min_partitions > 0 {
+            req.set_min_partitions(min_partitions);
+        }
+
+        // parse the request into bytes
+        let bytes = match req.write_to_bytes() {
+            Ok(bytes) => bytes,
+            Err(error) => return Err(RiakErr::ProtobufError(error)),
+        };
+
+        // send the request and parse the response
+        match self.connection.exchange(codes::RpbCoverageReq, codes::RpbCoverageResp, &bytes) {
+            Ok(response) => coverage_response_to_coverage_entries(&response),
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Replaces part of a coverage plan whose node could not be reached, given its cover context
+    /// and the cover contexts of every other part found to be unavailable.
+    ///
+    /// The same part of the keyspace is covered by one or more entries served by other nodes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use riak::Client;
+    ///
+    /// let mut riak = Client::new("10.0.0.2:8087").unwrap();
+    /// let plan = riak.coverage_plan("default", "testbucket", 16).unwrap();
+    ///
+    /// let unavailable = vec![plan[0].cover_context.clone()];
+    /// let replacements = riak.replace_cover("default", "testbucket", &plan[0].cover_context,
+    ///                                       &unavailable).unwrap();
+    /// println!("replaced by {:?}", replacements);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns `RiakErr::ServerError` if no other node can cover that part of the keyspace.
+    pub fn replace_cover<T: Into<Vec<u8>>>(&mut self,
+                                           bucket_type: T,
+                                           bucket: T,
+                                           cover_context: &[u8],
+                                           unavailable_covers: &[Vec<u8>])
+                                           -> Result<Vec<CoverageEntry>, RiakErr> {
+        // build the request
+        let mut req = RpbCoverageReq::new();
+        req.set_field_type(bucket_type.into());
+        req.set_bucket(bucket.into());
+        req.set_replace_cover(cover_context.to_vec());
+        req.set_unavailable_cover(RepeatedField::from_vec(unavailable_covers.to_vec()));
+
+        // parse the request into bytes
+        let bytes = match req.write_to_bytes() {
+            Ok(bytes) => bytes,
+            Err(error) => return Err(RiakErr::ProtobufError(error)),
+        };
+
+        // send the request and parse the response
+        match self.connection.exchange(codes::RpbCoverageReq, codes::RpbCoverageResp, &bytes) {
+            Ok(response) => coverage_response_to_coverage_entries(&response),
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Create a search schema
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use riak::Client;
+    /// use std::fs::File;
+    /// use std::io::Read;
+    ///
+    /// let mut riak = Client::new("10.0.0.2:8087").unwrap();
+    ///
+    /// let mut xml: Vec<u8> = Vec::new();
+    /// let mut file = File::open("/tmp/riak-rust-client-default-schema.xml").unwrap();
+    /// let _ = file.read_to_end(&mut xml).unwrap();
+    ///
+    /// let schema_name = "schedule".to_string().into_bytes();
+    /// riak.set_yokozuna_schema(schema_name, xml).unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// TODO
+    pub fn set_yokozuna_schema<T: Into<Vec<u8>>>(&mut self,
+                                                 name: T,
+                                                 content: T)
+                                                 -> Result<(), RiakErr> {
+        // build a `RpbYokozunaSchema`
+        let mut schema = RpbYokozunaSchema::new();
+        schema.set_name(name.into());
+        schema.set_content(content.into());
+
+        // build a `RpbYokozunaSchemaPutReq` request
+        let mut req = RpbYokozunaSchemaPutReq::new();
+        req.set_schema(schema);
+
+        // parse the request into bytes to send
+        let bytes = match req.write_to_bytes() {
+            Ok(bytes) => bytes,
+            Err(error) => return Err(RiakErr::ProtobufError(error)),
+        };
+
+        // make the exchange, no return needed on success
+        match self.connection
+            .exchange(codes::RpbYokozunaSchemaPutReq, codes::RpbPutResp, &bytes) {
+            Ok(_) => Ok(()),
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Retrieve a search schema
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use riak::Client;
+    ///
+    /// let mut riak = Client::new("10.0.0.2:8087").unwrap();
+    ///
+    /// riak.get_yokozuna_schema("schedule").unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// TODO
+    pub fn get_yokozuna_schema<T: Into<Vec<u8>>>(&mut self, name: T) -> Result<Vec<u8>, RiakErr> {
+        // build a request
+        let mut req = RpbYokozunaSchemaGetReq::new();
+        req.set_name(name.into());
+
+        // parse the request into bytes
+        let bytes = match req.write_to_bytes() {
+            Ok(bytes) => bytes,
+            Err(error) => return Err(RiakErr::ProtobufError(error)),
+        };
+
+        // make the exchange and get the response bytes
+        let response = match self.connection.exchange(codes::RpbYokozunaSchemaGetReq,
+                                                      codes::RpbYokozunaSchemaGetResp,
+                                                      &bytes) {
+            Ok(response) => response,
+            Err(error) => return Err(error),
+        };
+
+        // parse the response into a `RpbYokozunaSchemaGetResp`
+        let mut rpb_yokozuna_schema_get_resp =
+            match parse_from_bytes::<RpbYokozunaSchemaGetResp>(&response) {
+                Ok(parsed) => parsed,
+                Err(error) => return Err(RiakErr::ProtobufError(error)),
+            };
+
+        // grab the schema out of the response
+        let mut rpb_yokozuna_schema = rpb_yokozuna_schema_get_resp.take_schema();
+
+        // return the contents of the schema
+        Ok(rpb_yokozuna_schema.take_content())
+    }
+
+    /// set a search index
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use riak::Client;
+    /// use riak::yokozuna::YokozunaIndex;
+    ///
+    /// let mut riak = Client::new("10.0.0.2:8087").unwrap();
+    ///
+    /// let index = YokozunaIndex::new("myindex");
+    /// riak.set_yokozuna_index(index).unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// TODO
+    pub fn set_yokozuna_index(&mut self, index: YokozunaIndex) -> Result<(), RiakErr> {
+        let bytes = match index.write_to_bytes() {
+            Ok(bytes) => bytes,
             Err(error) => return Err(error),
         };
 
@@ -829,27 +1650,317 @@ impl Client {
         }
     }
 
-    /// get a search index
+    /// get a search index
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use riak::Client;
+    ///
+    /// let mut riak = Client::new("10.0.0.2:8087").unwrap();
+    ///
+    /// riak.get_yokozuna_index("myindex").unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// TODO
+    pub fn get_yokozuna_index<T: Into<Vec<u8>>>(&mut self,
+                                                name: T)
+                                                -> Result<Vec<YokozunaIndex>, RiakErr> {
+        // build the request
+        let mut req = RpbYokozunaIndexGetReq::new();
+        req.set_name(name.into());
+
+        // parse the request into bytes
+        let bytes = match req.write_to_bytes() {
+            Ok(bytes) => bytes,
+            Err(error) => return Err(RiakErr::ProtobufError(error)),
+        };
+
+        // exchange the request and get the response bytes
+        let response = match self.connection.exchange(codes::RpbYokozunaIndexGetReq,
+                                                      codes::RpbYokozunaIndexGetResp,
+                                                      &bytes) {
+            Ok(response) => response,
+            Err(error) => return Err(error),
+        };
+
+        // parse the response
+        let mut rpb_yokozuna_index_get_resp =
+            match parse_from_bytes::<RpbYokozunaIndexGetResp>(&response) {
+                Ok(rpb_yokozuna_index_get_resp) => rpb_yokozuna_index_get_resp,
+                Err(error) => return Err(RiakErr::ProtobufError(error)),
+            };
+
+        // build a Vec of all the indexes in the response and return it
+        let mut indexes: Vec<YokozunaIndex> = Vec::new();
+        for rpb_yokozuna_index in rpb_yokozuna_index_get_resp.take_index().into_iter() {
+            let index = YokozunaIndex::new_from_rpb_yokozuna_index(rpb_yokozuna_index);
+            indexes.push(index);
+        }
+        Ok(indexes)
+    }
+
+    /// Deletes an index
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use riak::Client;
+    /// use riak::yokozuna::YokozunaIndex;
+    ///
+    /// let mut riak = Client::new("10.0.0.2:8087").unwrap();
+    ///
+    /// let index = YokozunaIndex::new("deleteme");
+    /// riak.set_yokozuna_index(index).unwrap();
+    ///
+    /// riak.delete_yokozuna_index("deleteme").unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// TODO
+    pub fn delete_yokozuna_index<T: Into<Vec<u8>>>(&mut self, name: T) -> Result<(), RiakErr> {
+        // build the request
+        let mut req = RpbYokozunaIndexDeleteReq::new();
+        req.set_name(name.into());
+
+        // parse the request into bytes
+        let bytes = match req.write_to_bytes() {
+            Ok(bytes) => bytes,
+            Err(error) => return Err(RiakErr::ProtobufError(error)),
+        };
+
+        // make the exchange with the server and get the response bytes
+        match self.connection
+            .exchange(codes::RpbYokozunaIndexDeleteReq, codes::RpbDelResp, &bytes) {
+            Ok(_) => Ok(()),
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Performs a search query against a Yokozuna index.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use riak::Client;
+    /// use riak::yokozuna::SearchQuery;
+    ///
+    /// let mut riak = Client::new("10.0.0.2:8087").unwrap();
+    ///
+    /// let query = SearchQuery::new("name_s:Lion*", "myindex");
+    /// let results = riak.search(&query).unwrap();
+    ///
+    /// for doc in results.get_docs() {
+    ///     println!("found document {:?}", doc);
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// TODO
+    pub fn search(&mut self, query: &SearchQuery) -> Result<SearchResults, RiakErr> {
+        // parse the query into bytes
+        let bytes = match query.write_to_bytes() {
+            Ok(bytes) => bytes,
+            Err(error) => return Err(error),
+        };
+
+        // make the exchange and get the response bytes
+        let response = match self.connection
+            .exchange(codes::RpbSearchQueryReq, codes::RpbSearchQueryResp, &bytes) {
+            Ok(response) => response,
+            Err(error) => return Err(error),
+        };
+
+        // parse the response into a `RpbSearchQueryResp`
+        let mut rpb_search_query_resp = match parse_from_bytes::<RpbSearchQueryResp>(&response) {
+            Ok(parsed) => parsed,
+            Err(error) => return Err(RiakErr::ProtobufError(error)),
+        };
+
+        // build the `SearchResults` to return
+        Ok(rpb_search_query_resp_to_search_results(&mut rpb_search_query_resp))
+    }
+
+    /// Fetches a Data Type (counter, set, map or HyperLogLog) from Riak.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use riak::Client;
+    /// use riak::datatypes::{DataType, FetchDataTypeReq};
+    ///
+    /// let mut riak = Client::new("10.0.0.2:8087").unwrap();
+    ///
+    /// let req = FetchDataTypeReq::new("counters", "testbucket", "testcounter");
+    /// let resp = riak.fetch_datatype(&req).unwrap();
+    ///
+    /// if let Some(DataType::Counter(counter)) = resp.get_value() {
+    ///     println!("testcounter is at {}", counter.get_value());
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// TODO
+    pub fn fetch_datatype(&mut self, req: &FetchDataTypeReq) -> Result<FetchDataTypeResp, RiakErr> {
+        // convert the request to protobuf bytes
+        let bytes = match req.write_to_bytes() {
+            Ok(bytes) => bytes,
+            Err(error) => return Err(error),
+        };
+
+        // make the exchange and get the response bytes
+        let response = match self.connection
+            .exchange(codes::DtFetchReq, codes::DtFetchResp, &bytes) {
+            Ok(response) => response,
+            Err(error) => return Err(error),
+        };
+
+        // parse the response into a `DtFetchResp`
+        let dt_fetch_resp = match parse_from_bytes::<DtFetchResp>(&response) {
+            Ok(parsed) => parsed,
+            Err(error) => return Err(RiakErr::ProtobufError(error)),
+        };
+
+        Ok(FetchDataTypeResp::new_from_dt_fetch_resp(dt_fetch_resp))
+    }
+
+    /// Sends the local changes made to a Data Type to Riak.
+    ///
+    /// The causal context returned by a fetch should be passed along with any update that removes
+    /// elements from a set or fields from a map.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use riak::Client;
+    /// use riak::datatypes::{DataType, FetchDataTypeReq, Set, UpdateDataTypeReq};
+    ///
+    /// let mut riak = Client::new("10.0.0.2:8087").unwrap();
+    ///
+    /// let req = FetchDataTypeReq::new("sets", "testbucket", "testset");
+    /// let resp = riak.fetch_datatype(&req).unwrap();
+    ///
+    /// let mut set = match resp.get_value() {
+    ///     Some(DataType::Set(set)) => set,
+    ///     _ => Set::new(),
+    /// };
+    /// set.add("newmember");
+    /// set.remove("oldmember");
+    ///
+    /// let mut req = UpdateDataTypeReq::new("sets", "testbucket", &DataType::Set(set));
+    /// req.set_key("testset");
+    /// if let Some(context) = resp.get_context() {
+    ///     req.set_context(context);
+    /// }
+    ///
+    /// riak.update_datatype(&req).unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns `RiakErr::InvalidDataTypeUpdate` if the changes remove anything and no context was
+    /// set on the request, or any error from Riak.
+    pub fn update_datatype(&mut self,
+                           req: &UpdateDataTypeReq)
+                           -> Result<UpdateDataTypeResp, RiakErr> {
+        // convert the request to protobuf bytes
+        let bytes = match req.write_to_bytes() {
+            Ok(bytes) => bytes,
+            Err(error) => return Err(error),
+        };
+
+        // make the exchange and get the response bytes
+        let response = match self.connection
+            .exchange(codes::DtUpdateReq, codes::DtUpdateResp, &bytes) {
+            Ok(response) => response,
+            Err(error) => return Err(error),
+        };
+
+        // parse the response into a `DtUpdateResp`
+        let dt_update_resp = match parse_from_bytes::<DtUpdateResp>(&response) {
+            Ok(parsed) => parsed,
+            Err(error) => return Err(RiakErr::ProtobufError(error)),
+        };
+
+        Ok(req.new_response(dt_update_resp))
+    }
+
+    /// Writes rows to a Riak TS table.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use riak::Client;
+    /// use riak::timeseries::{Row, TsCell};
+    ///
+    /// let mut riak = Client::new("10.0.0.2:8087").unwrap();
+    ///
+    /// let row = Row::new(vec![TsCell::Varchar("South Atlantic".into()),
+    ///                         TsCell::Varchar("South Carolina".into()),
+    ///                         TsCell::Timestamp(1420113600000),
+    ///                         TsCell::Varchar("snow".into()),
+    ///                         TsCell::Double(23.5)]);
+    ///
+    /// riak.ts_put("GeoCheckin", &[row]).unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// TODO
+    pub fn ts_put<T: Into<Vec<u8>>>(&mut self, table: T, rows: &[Row]) -> Result<(), RiakErr> {
+        // build the request
+        let mut req = TsPutReq::new();
+        req.set_table(table.into());
+        let rpb_rows = rows.iter().map(row_to_rpb_ts_row).collect();
+        req.set_rows(RepeatedField::from_vec(rpb_rows));
+
+        // parse the request into bytes
+        let bytes = match req.write_to_bytes() {
+            Ok(bytes) => bytes,
+            Err(error) => return Err(RiakErr::ProtobufError(error)),
+        };
+
+        // make the exchange, no return needed on success
+        match self.connection.exchange(codes::TsPutReq, codes::TsPutResp, &bytes) {
+            Ok(_) => Ok(()),
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Fetches a single row from a Riak TS table given the cells of its primary key.
     ///
     /// # Examples
     ///
     /// ```
     /// use riak::Client;
+    /// use riak::timeseries::TsCell;
     ///
     /// let mut riak = Client::new("10.0.0.2:8087").unwrap();
     ///
-    /// riak.get_yokozuna_index("myindex").unwrap();
+    /// let key = vec![TsCell::Varchar("South Atlantic".into()),
+    ///                TsCell::Varchar("South Carolina".into()),
+    ///                TsCell::Timestamp(1420113600000)];
+    ///
+    /// let results = riak.ts_get("GeoCheckin", &key).unwrap();
+    /// println!("found row {:?}", results.rows);
     /// ```
     ///
     /// # Errors
     ///
     /// TODO
-    pub fn get_yokozuna_index<T: Into<Vec<u8>>>(&mut self,
-                                                name: T)
-                                                -> Result<Vec<YokozunaIndex>, RiakErr> {
+    pub fn ts_get<T: Into<Vec<u8>>>(&mut self,
+                                    table: T,
+                                    key: &[TsCell])
+                                    -> Result<QueryResults, RiakErr> {
         // build the request
-        let mut req = RpbYokozunaIndexGetReq::new();
-        req.set_name(name.into());
+        let mut req = TsGetReq::new();
+        req.set_table(table.into());
+        req.set_key(ts_cells_to_rpb_ts_cells(key));
 
         // parse the request into bytes
         let bytes = match req.write_to_bytes() {
@@ -857,53 +1968,157 @@ impl Client {
             Err(error) => return Err(RiakErr::ProtobufError(error)),
         };
 
-        // exchange the request and get the response bytes
-        let response = match self.connection.exchange(codes::RpbYokozunaIndexGetReq,
-                                                      codes::RpbYokozunaIndexGetResp,
-                                                      &bytes) {
+        // make the exchange and get the response bytes
+        let response = match self.connection.exchange(codes::TsGetReq, codes::TsGetResp, &bytes) {
             Ok(response) => response,
             Err(error) => return Err(error),
         };
 
-        // parse the response
-        let mut rpb_yokozuna_index_get_resp =
-            match parse_from_bytes::<RpbYokozunaIndexGetResp>(&response) {
-                Ok(rpb_yokozuna_index_get_resp) => rpb_yokozuna_index_get_resp,
+        // parse the response into a `TsGetResp`
+        let mut ts_get_resp = match parse_from_bytes::<TsGetResp>(&response) {
+            Ok(parsed) => parsed,
+            Err(error) => return Err(RiakErr::ProtobufError(error)),
+        };
+
+        Ok(rpb_ts_columns_and_rows_to_query_results(ts_get_resp.take_columns(),
+                                                    ts_get_resp.take_rows()))
+    }
+
+    /// Deletes a single row from a Riak TS table given the cells of its primary key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use riak::Client;
+    /// use riak::timeseries::TsCell;
+    ///
+    /// let mut riak = Client::new("10.0.0.2:8087").unwrap();
+    ///
+    /// let key = vec![TsCell::Varchar("South Atlantic".into()),
+    ///                TsCell::Varchar("South Carolina".into()),
+    ///                TsCell::Timestamp(1420113600000)];
+    ///
+    /// riak.ts_delete("GeoCheckin", &key).unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// TODO
+    pub fn ts_delete<T: Into<Vec<u8>>>(&mut self, table: T, key: &[TsCell]) -> Result<(), RiakErr> {
+        // build the request
+        let mut req = TsDelReq::new();
+        req.set_table(table.into());
+        req.set_key(ts_cells_to_rpb_ts_cells(key));
+
+        // parse the request into bytes
+        let bytes = match req.write_to_bytes() {
+            Ok(bytes) => bytes,
+            Err(error) => return Err(RiakErr::ProtobufError(error)),
+        };
+
+        // make the exchange, no return needed on success
+        match self.connection.exchange(codes::TsDelReq, codes::TsDelResp, &bytes) {
+            Ok(_) => Ok(()),
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Runs a SQL query against Riak TS, this is also used for DDL such as `CREATE TABLE`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use riak::Client;
+    ///
+    /// let mut riak = Client::new("10.0.0.2:8087").unwrap();
+    ///
+    /// let query = "SELECT weather, temperature FROM GeoCheckin WHERE time > 1420113500000 AND \
+    ///              time < 1420116000000 AND region = 'South Atlantic' AND state = 'South Carolina'";
+    /// let results = riak.ts_query(query).unwrap();
+    ///
+    /// for row in results.rows {
+    ///     println!("found row {:?}", row);
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// TODO
+    pub fn ts_query<T: Into<Vec<u8>>>(&mut self, query: T) -> Result<QueryResults, RiakErr> {
+        // build the request
+        let mut interpolation = TsInterpolation::new();
+        interpolation.set_base(query.into());
+        let mut req = TsQueryReq::new();
+        req.set_query(interpolation);
+
+        // parse the request into bytes
+        let bytes = match req.write_to_bytes() {
+            Ok(bytes) => bytes,
+            Err(error) => return Err(RiakErr::ProtobufError(error)),
+        };
+
+        // send the request
+        match self.connection.send(codes::TsQueryReq, &bytes) {
+            Ok(()) => (),
+            Err(error) => return Err(error),
+        };
+
+        // receive responses until Riak reports that the query is done
+        let mut results = QueryResults::new(Vec::new(), Vec::new());
+        loop {
+            let response = match self.connection.receive(codes::TsQueryResp) {
+                Ok(response) => response,
+                Err(error) => return Err(error),
+            };
+
+            let mut ts_query_resp = match parse_from_bytes::<TsQueryResp>(&response) {
+                Ok(parsed) => parsed,
                 Err(error) => return Err(RiakErr::ProtobufError(error)),
             };
 
-        // build a Vec of all the indexes in the response and return it
-        let mut indexes: Vec<YokozunaIndex> = Vec::new();
-        for rpb_yokozuna_index in rpb_yokozuna_index_get_resp.take_index().into_iter() {
-            let index = YokozunaIndex::new_from_rpb_yokozuna_index(rpb_yokozuna_index);
-            indexes.push(index);
+            let done = ts_query_resp.get_done();
+            let partial = rpb_ts_columns_and_rows_to_query_results(ts_query_resp.take_columns(),
+                                                                   ts_query_resp.take_rows());
+            if results.columns.is_empty() {
+                results.columns = partial.columns;
+            }
+            results.rows.extend(partial.rows);
+
+            if done {
+                break;
+            }
         }
-        Ok(indexes)
+
+        Ok(results)
     }
 
-    /// Deletes an index
+    /// Produces a list of the primary keys of every row in a Riak TS table.
+    ///
+    /// Note: This operation requires traversing all keys stored in the cluster and should not be used in production.
     ///
     /// # Examples
     ///
     /// ```
     /// use riak::Client;
-    /// use riak::yokozuna::YokozunaIndex;
     ///
     /// let mut riak = Client::new("10.0.0.2:8087").unwrap();
     ///
-    /// let index = YokozunaIndex::new("deleteme");
-    /// riak.set_yokozuna_index(index).unwrap();
+    /// let keys = riak.ts_list_keys("GeoCheckin").unwrap();
     ///
-    /// riak.delete_yokozuna_index("deleteme").unwrap();
+    /// for key in keys.iter() {
+    ///     println!("found key {:?}", key.cells);
+    /// }
     /// ```
     ///
     /// # Errors
     ///
     /// TODO
-    pub fn delete_yokozuna_index<T: Into<Vec<u8>>>(&mut self, name: T) -> Result<(), RiakErr> {
+    pub fn ts_list_keys<T: Into<Vec<u8>>>(&mut self, table: T) -> Result<Vec<Row>, RiakErr> {
         // build the request
-        let mut req = RpbYokozunaIndexDeleteReq::new();
-        req.set_name(name.into());
+        let mut req = TsListKeysReq::new();
+        req.set_table(table.into());
+        // the `Client` timeout is in seconds, Riak expects milliseconds
+        req.set_timeout(self.timeout.saturating_mul(1000));
 
         // parse the request into bytes
         let bytes = match req.write_to_bytes() {
@@ -911,11 +2126,174 @@ impl Client {
             Err(error) => return Err(RiakErr::ProtobufError(error)),
         };
 
-        // make the exchange with the server and get the response bytes
-        match self.connection
-            .exchange(codes::RpbYokozunaIndexDeleteReq, codes::RpbDelResp, &bytes) {
-            Ok(_) => Ok(()),
+        // send the request
+        match self.connection.send(codes::TsListKeysReq, &bytes) {
+            Ok(()) => (),
+            Err(error) => return Err(error),
+        };
+
+        // keys are streamed back until Riak reports that it is done
+        let mut keys: Vec<Row> = Vec::new();
+        loop {
+            let response = match self.connection.receive(codes::TsListKeysResp) {
+                Ok(response) => response,
+                Err(error) => return Err(error),
+            };
+
+            let mut ts_list_keys_resp = match parse_from_bytes::<TsListKeysResp>(&response) {
+                Ok(parsed) => parsed,
+                Err(error) => return Err(RiakErr::ProtobufError(error)),
+            };
+
+            for mut key in ts_list_keys_resp.take_keys().into_iter() {
+                keys.push(rpb_ts_row_to_row(&mut key));
+            }
+
+            if ts_list_keys_resp.get_done() {
+                break;
+            }
+        }
+
+        Ok(keys)
+    }
+
+    /// Queries a secondary index, returning a single page of results.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use riak::Client;
+    /// use riak::index::IndexQuery;
+    ///
+    /// let mut riak = Client::new("10.0.0.2:8087").unwrap();
+    ///
+    /// let mut query = IndexQuery::new_range("testbucket", "age_int", "18", "35");
+    /// query.set_max_results(100);
+    ///
+    /// loop {
+    ///     let results = riak.query_index(&query).unwrap();
+    ///     println!("found keys {:?}", results.get_keys());
+    ///
+    ///     match results.get_continuation() {
+    ///         Some(continuation) => query.set_continuation(continuation),
+    ///         None => break,
+    ///     };
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// TODO
+    pub fn query_index(&mut self, query: &IndexQuery) -> Result<IndexResults, RiakErr> {
+        // parse the query into bytes
+        let bytes = match query.write_to_bytes(false) {
+            Ok(bytes) => bytes,
+            Err(error) => return Err(error),
+        };
+
+        // make the exchange and get the response bytes
+        let return_body = query.get_return_body().unwrap_or(false);
+        let response = match self.connection
+            .exchange(codes::RpbIndexReq, index_response_code(return_body), &bytes) {
+            Ok(response) => response,
+            Err(error) => return Err(error),
+        };
+
+        // parse the response into `IndexResults`
+        match index_response_to_index_results(&response, return_body) {
+            Ok((results, _)) => Ok(results),
             Err(error) => Err(error),
         }
     }
+
+    /// Produces a stream of results for a secondary index query.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use riak::Client;
+    /// use riak::index::IndexQuery;
+    ///
+    /// let mut riak = Client::new("10.0.0.2:8087").unwrap();
+    ///
+    /// let query = IndexQuery::new_eq("testbucket", "email_bin", "user@example.com");
+    /// let mut indexstream = riak.stream_index(&query).unwrap();
+    ///
+    /// loop {
+    ///     match indexstream.next() {
+    ///         Some(results) => println!("found keys {:?}", results.unwrap().get_keys()),
+    ///         None => break,
+    ///     };
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// TODO
+    pub fn stream_index(&mut self, query: &IndexQuery) -> Result<IndexStream<C>, RiakErr> {
+        IndexStream::new(self, query)
+    }
+
+    /// Folds over the objects of a range of keys in a bucket, producing a stream of
+    /// `(key, object)` pairs in key order.
+    ///
+    /// The objects are read a page at a time, which is the most efficient way to read the bodies
+    /// of a whole bucket. Long reads can be restarted from the continuation of the fold.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use riak::Client;
+    ///
+    /// let mut riak = Client::new("10.0.0.2:8087").unwrap();
+    ///
+    /// let mut fold = riak.fold_objects("testbucket", "a", "n").unwrap();
+    /// fold.set_page_size(100);
+    ///
+    /// for object in fold {
+    ///     let (key, object) = object.unwrap();
+    ///     println!("{:?} has {} siblings", key, object.get_content().len());
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a connection for the fold can not be opened, errors that occur while
+    /// reading the objects are returned by the fold.
+    pub fn fold_objects<T: Into<Vec<u8>>>(&mut self,
+                                          bucket: T,
+                                          start_key: T,
+                                          end_key: T)
+                                          -> Result<ObjectFold<C>, RiakErr> {
+        ObjectFold::new(self, bucket.into(), start_key.into(), end_key.into())
+    }
+
+    /// Runs a MapReduce job, producing a stream of `(phase, response)` chunks where the response
+    /// is the JSON encoded result of that phase.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use riak::Client;
+    /// use riak::mapreduce::{MapReduceFunction, MapReduceInputs, MapReduceJob, MapReducePhase};
+    ///
+    /// let mut riak = Client::new("10.0.0.2:8087").unwrap();
+    ///
+    /// let inputs = MapReduceInputs::Keys(vec![("testbucket".into(), "testkey".into())]);
+    /// let mut job = MapReduceJob::new(inputs);
+    /// let map = MapReduceFunction::ErlangModFun("riak_kv_mapreduce".into(),
+    ///                                           "map_object_value".into());
+    /// job.add_phase(MapReducePhase::map(map));
+    ///
+    /// let chunks = riak.mapreduce(&job).unwrap().all().unwrap();
+    /// println!("results: {:?}", chunks);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a connection for the stream can not be opened, errors that occur while
+    /// the job runs are returned by the stream.
+    pub fn mapreduce(&mut self, job: &MapReduceJob) -> Result<MapReduceStream<C>, RiakErr> {
+        MapReduceStream::new(self, job)
+    }
 }
@@ -0,0 +1,498 @@
+/// MapReduce related structs for building jobs that are run across a Riak cluster.
+///
+/// Jobs are sent to Riak as JSON, and the results of each phase are returned as JSON encoded
+/// chunks tagged with the index of the phase that produced them.
+///
+/// For more information: https://docs.basho.com/riak/kv/latest/developing/app-guide/advanced-mapreduce/
+
+use errors::RiakErr;
+use protobuf::Message;
+use rpb::riak_kv::RpbMapRedReq;
+use rpb::utils::RpbGenerator;
+use std::str;
+
+/// `KeyFilter` is a single step of a key filter, used to select which keys of a bucket are fed
+/// into a job without loading the objects.
+///
+/// For more information: https://docs.basho.com/riak/kv/latest/developing/usage/mapreduce/#key-filters
+///
+/// # Examples
+///
+/// ```
+/// use riak::mapreduce::KeyFilter;
+///
+/// // keys like "2017-05-29" where the year is "2017"
+/// let mut tokenize = KeyFilter::new("tokenize");
+/// tokenize.add_string_arg("-");
+/// tokenize.add_int_arg(1);
+///
+/// let mut eq = KeyFilter::new("eq");
+/// eq.add_string_arg("2017");
+///
+/// let filters = vec![tokenize, eq];
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct KeyFilter {
+    name: String,
+    args: Vec<Json>,
+}
+
+impl KeyFilter {
+    /// constructs a new `KeyFilter` for the named transform or predicate, such as "tokenize",
+    /// "to_integer", "eq" or "starts_with"
+    pub fn new<T: Into<String>>(name: T) -> KeyFilter {
+        KeyFilter {
+            name: name.into(),
+            args: Vec::new(),
+        }
+    }
+
+    /// constructs a `KeyFilter` that matches keys matching both lists of filters
+    pub fn and(left: Vec<KeyFilter>, right: Vec<KeyFilter>) -> KeyFilter {
+        KeyFilter {
+            name: "and".to_string(),
+            args: vec![key_filters_to_json(&left), key_filters_to_json(&right)],
+        }
+    }
+
+    /// constructs a `KeyFilter` that matches keys matching either list of filters
+    pub fn or(left: Vec<KeyFilter>, right: Vec<KeyFilter>) -> KeyFilter {
+        KeyFilter {
+            name: "or".to_string(),
+            args: vec![key_filters_to_json(&left), key_filters_to_json(&right)],
+        }
+    }
+
+    /// constructs a `KeyFilter` that matches keys not matching the list of filters
+    pub fn not(filters: Vec<KeyFilter>) -> KeyFilter {
+        KeyFilter {
+            name: "not".to_string(),
+            args: vec![key_filters_to_json(&filters)],
+        }
+    }
+
+    /// get the name of the transform or predicate
+    pub fn get_name(&self) -> String {
+        self.name.clone()
+    }
+
+    /// add a string argument
+    pub fn add_string_arg<T: Into<Vec<u8>>>(&mut self, arg: T) {
+        self.args.push(Json::String(arg.into()));
+    }
+
+    /// add an integer argument
+    pub fn add_int_arg(&mut self, arg: i64) {
+        self.args.push(Json::Int(arg));
+    }
+
+    /// add a floating point argument
+    pub fn add_float_arg(&mut self, arg: f64) {
+        self.args.push(Json::Float(arg));
+    }
+
+    fn to_json(&self) -> Json {
+        let mut filter = vec![Json::String(self.name.clone().into_bytes())];
+        filter.extend(self.args.iter().cloned());
+        Json::Array(filter)
+    }
+}
+
+/// `MapReduceInputs` represents the objects fed into the first phase of a job.
+#[derive(Clone, Debug, PartialEq)]
+pub enum MapReduceInputs {
+    /// every object in a bucket, this requires listing the keys of the bucket
+    Bucket(Vec<u8>),
+    /// every object in a bucket of a bucket type
+    TypedBucket(Vec<u8>, Vec<u8>),
+    /// a list of `(bucket, key)` pairs
+    Keys(Vec<(Vec<u8>, Vec<u8>)>),
+    /// objects in `bucket` whose `index` exactly matches `key`
+    IndexEq {
+        bucket: Vec<u8>,
+        index: Vec<u8>,
+        key: Vec<u8>,
+    },
+    /// objects in `bucket` whose `index` falls between `start` and `end` inclusive
+    IndexRange {
+        bucket: Vec<u8>,
+        index: Vec<u8>,
+        start: Vec<u8>,
+        end: Vec<u8>,
+    },
+    /// objects matching a query against a Yokozuna search index
+    Search { index: Vec<u8>, query: Vec<u8> },
+    /// objects in `bucket` whose keys pass the key filters
+    KeyFilters {
+        bucket: Vec<u8>,
+        filters: Vec<KeyFilter>,
+    },
+}
+
+impl MapReduceInputs {
+    fn to_json(&self) -> Json {
+        match *self {
+            MapReduceInputs::Bucket(ref bucket) => Json::String(bucket.clone()),
+            MapReduceInputs::TypedBucket(ref bucket_type, ref bucket) => {
+                Json::Array(vec![Json::String(bucket_type.clone()), Json::String(bucket.clone())])
+            }
+            MapReduceInputs::Keys(ref keys) => {
+                Json::Array(keys.iter()
+                    .map(|(bucket, key)| {
+                        Json::Array(vec![Json::String(bucket.clone()), Json::String(key.clone())])
+                    })
+                    .collect())
+            }
+            MapReduceInputs::IndexEq { ref bucket, ref index, ref key } => {
+                Json::Object(vec![("bucket", Json::String(bucket.clone())),
+                                  ("index", Json::String(index.clone())),
+                                  ("key", Json::String(key.clone()))])
+            }
+            MapReduceInputs::IndexRange { ref bucket, ref index, ref start, ref end } => {
+                Json::Object(vec![("bucket", Json::String(bucket.clone())),
+                                  ("index", Json::String(index.clone())),
+                                  ("start", Json::String(start.clone())),
+                                  ("end", Json::String(end.clone()))])
+            }
+            MapReduceInputs::Search { ref index, ref query } => {
+                Json::Object(vec![("module", Json::String(b"yokozuna".to_vec())),
+                                  ("function", Json::String(b"mapred_search".to_vec())),
+                                  ("arg",
+                                   Json::Array(vec![Json::String(index.clone()),
+                                                    Json::String(query.clone())]))])
+            }
+            MapReduceInputs::KeyFilters { ref bucket, ref filters } => {
+                Json::Object(vec![("bucket", Json::String(bucket.clone())),
+                                  ("key_filters", key_filters_to_json(filters))])
+            }
+        }
+    }
+}
+
+/// `MapReduceFunction` is the function run by a map or reduce phase.
+#[derive(Clone, Debug, PartialEq)]
+pub enum MapReduceFunction {
+    /// JavaScript source code for an anonymous function
+    JavaScriptSource(String),
+    /// the name of a built in or preloaded JavaScript function, such as "Riak.reduceSum"
+    JavaScriptNamed(String),
+    /// JavaScript source code stored as an object at `(bucket, key)`
+    JavaScriptStored(Vec<u8>, Vec<u8>),
+    /// an Erlang function as `(module, function)`, which must be on the code path of every node
+    ErlangModFun(String, String),
+}
+
+impl MapReduceFunction {
+    fn to_json_fields(&self) -> Vec<(&'static str, Json)> {
+        match *self {
+            MapReduceFunction::JavaScriptSource(ref source) => {
+                vec![("language", Json::String(b"javascript".to_vec())),
+                     ("source", Json::String(source.clone().into_bytes()))]
+            }
+            MapReduceFunction::JavaScriptNamed(ref name) => {
+                vec![("language", Json::String(b"javascript".to_vec())),
+                     ("name", Json::String(name.clone().into_bytes()))]
+            }
+            MapReduceFunction::JavaScriptStored(ref bucket, ref key) => {
+                vec![("language", Json::String(b"javascript".to_vec())),
+                     ("bucket", Json::String(bucket.clone())),
+                     ("key", Json::String(key.clone()))]
+            }
+            MapReduceFunction::ErlangModFun(ref module, ref function) => {
+                vec![("language", Json::String(b"erlang".to_vec())),
+                     ("module", Json::String(module.clone().into_bytes())),
+                     ("function", Json::String(function.clone().into_bytes()))]
+            }
+        }
+    }
+}
+
+// The kind of a phase and the settings specific to that kind.
+#[derive(Clone, Debug, PartialEq)]
+enum PhaseKind {
+    Map(MapReduceFunction),
+    Reduce(MapReduceFunction),
+    Link(Option<Vec<u8>>, Option<Vec<u8>>),
+}
+
+/// `MapReducePhase` represents a single map, reduce or link phase of a job.
+///
+/// Only the results of the last phase are returned unless "keep" is set on earlier phases.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MapReducePhase {
+    kind: PhaseKind,
+    keep: Option<bool>,
+    arg: Option<String>,
+}
+
+impl MapReducePhase {
+    /// constructs a new map phase running `function` against each input
+    pub fn map(function: MapReduceFunction) -> MapReducePhase {
+        MapReducePhase {
+            kind: PhaseKind::Map(function),
+            keep: None,
+            arg: None,
+        }
+    }
+
+    /// constructs a new reduce phase running `function` against the results of the previous phase
+    pub fn reduce(function: MapReduceFunction) -> MapReducePhase {
+        MapReducePhase {
+            kind: PhaseKind::Reduce(function),
+            keep: None,
+            arg: None,
+        }
+    }
+
+    /// constructs a new link phase following links from the inputs, optionally only those to
+    /// `bucket` and with `tag`
+    pub fn link(bucket: Option<Vec<u8>>, tag: Option<Vec<u8>>) -> MapReducePhase {
+        MapReducePhase {
+            kind: PhaseKind::Link(bucket, tag),
+            keep: None,
+            arg: None,
+        }
+    }
+
+    /// get the value of the "keep" property
+    pub fn get_keep(&self) -> Option<bool> {
+        self.keep
+    }
+
+    /// set the value of the "keep" property, returning the results of this phase
+    pub fn set_keep(&mut self, keep: bool) {
+        self.keep = Some(keep);
+    }
+
+    /// get the value of the "arg" property
+    pub fn get_arg(&self) -> Option<String> {
+        self.arg.clone()
+    }
+
+    /// set the value of the "arg" property passed to the function, which must be valid JSON
+    pub fn set_arg<T: Into<String>>(&mut self, arg: T) {
+        self.arg = Some(arg.into());
+    }
+
+    fn to_json(&self) -> Json {
+        let (name, mut fields) = match self.kind {
+            PhaseKind::Map(ref function) => ("map", function.to_json_fields()),
+            PhaseKind::Reduce(ref function) => ("reduce", function.to_json_fields()),
+            PhaseKind::Link(ref bucket, ref tag) => {
+                let mut fields = Vec::new();
+                if let Some(ref bucket) = *bucket {
+                    fields.push(("bucket", Json::String(bucket.clone())));
+                }
+                if let Some(ref tag) = *tag {
+                    fields.push(("tag", Json::String(tag.clone())));
+                }
+                ("link", fields)
+            }
+        };
+
+        if let Some(keep) = self.keep {
+            fields.push(("keep", Json::Bool(keep)));
+        }
+        if let Some(ref arg) = self.arg {
+            fields.push(("arg", Json::Raw(arg.clone())));
+        }
+
+        Json::Object(vec![(name, Json::Object(fields))])
+    }
+}
+
+/// `MapReduceJob` represents a MapReduce job made up of inputs and a list of phases.
+///
+/// # Examples
+///
+/// ```
+/// use riak::Client;
+/// use riak::mapreduce::{MapReduceFunction, MapReduceInputs, MapReduceJob, MapReducePhase};
+///
+/// let mut riak = Client::new("10.0.0.2:8087").unwrap();
+///
+/// let mut job = MapReduceJob::new(MapReduceInputs::Bucket("testbucket".into()));
+/// job.add_phase(MapReducePhase::map(MapReduceFunction::JavaScriptNamed("Riak.mapValuesJson".into())));
+/// job.add_phase(MapReducePhase::reduce(MapReduceFunction::JavaScriptNamed("Riak.reduceSum".into())));
+///
+/// for result in riak.mapreduce(&job).unwrap() {
+///     let (phase, response) = result.unwrap();
+///     println!("phase {} returned {}", phase, String::from_utf8_lossy(&response));
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct MapReduceJob {
+    inputs: MapReduceInputs,
+    phases: Vec<MapReducePhase>,
+    timeout: Option<u32>,
+}
+
+impl MapReduceJob {
+    /// constructs a new `MapReduceJob` with no phases
+    pub fn new(inputs: MapReduceInputs) -> MapReduceJob {
+        MapReduceJob {
+            inputs: inputs,
+            phases: Vec::new(),
+            timeout: None,
+        }
+    }
+
+    /// get the inputs of the job
+    pub fn get_inputs(&self) -> MapReduceInputs {
+        self.inputs.clone()
+    }
+
+    /// set the inputs of the job
+    pub fn set_inputs(&mut self, inputs: MapReduceInputs) {
+        self.inputs = inputs;
+    }
+
+    /// get the phases of the job
+    pub fn get_phases(&self) -> Vec<MapReducePhase> {
+        self.phases.clone()
+    }
+
+    /// add a phase to the end of the job
+    pub fn add_phase(&mut self, phase: MapReducePhase) {
+        self.phases.push(phase);
+    }
+
+    /// get the value of the "timeout" property
+    pub fn get_timeout(&self) -> Option<u32> {
+        self.timeout
+    }
+
+    /// set the value of the "timeout" property, in milliseconds
+    pub fn set_timeout(&mut self, timeout: u32) {
+        self.timeout = Some(timeout);
+    }
+
+    /// renders the job as the JSON sent to Riak
+    ///
+    /// # Errors
+    ///
+    /// Returns `RiakErr::InvalidMapReduce` if a bucket, key or other name is not valid UTF-8, or
+    /// if a key filter argument is not a finite number, as neither can be represented in JSON.
+    pub fn to_json(&self) -> Result<String, RiakErr> {
+        let mut fields = vec![("inputs", self.inputs.to_json()),
+                              ("query",
+                               Json::Array(self.phases
+                                   .iter()
+                                   .map(|phase| phase.to_json())
+                                   .collect()))];
+        if let Some(timeout) = self.timeout {
+            fields.push(("timeout", Json::Int(timeout as i64)));
+        }
+
+        let mut out = String::new();
+        match Json::Object(fields).write(&mut out) {
+            Ok(()) => Ok(out),
+            Err(error) => Err(error),
+        }
+    }
+}
+
+impl RpbGenerator for MapReduceJob {
+    fn write_to_bytes(&self) -> Result<Vec<u8>, RiakErr> {
+        let json = match self.to_json() {
+            Ok(json) => json,
+            Err(error) => return Err(error),
+        };
+
+        let mut req = RpbMapRedReq::new();
+        req.set_request(json.into_bytes());
+        req.set_content_type(b"application/json".to_vec());
+        match req.write_to_bytes() {
+            Ok(bytes) => Ok(bytes),
+            Err(error) => Err(RiakErr::ProtobufError(error)),
+        }
+    }
+}
+
+// A minimal JSON document, only as much as is needed to render jobs.
+#[derive(Clone, Debug, PartialEq)]
+enum Json {
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    String(Vec<u8>),
+    Array(Vec<Json>),
+    Object(Vec<(&'static str, Json)>),
+    Raw(String),
+}
+
+impl Json {
+    fn write(&self, out: &mut String) -> Result<(), RiakErr> {
+        match *self {
+            Json::Bool(value) => out.push_str(if value { "true" } else { "false" }),
+            Json::Int(value) => out.push_str(&value.to_string()),
+            Json::Float(value) => {
+                if !value.is_finite() {
+                    return Err(RiakErr::InvalidMapReduce(format!("{} is not a finite number",
+                                                                 value)));
+                }
+                out.push_str(&value.to_string());
+            }
+            Json::String(ref value) => {
+                // JSON strings can't hold arbitrary bytes, so names must be valid UTF-8
+                match str::from_utf8(value) {
+                    Ok(value) => write_json_string(value, out),
+                    Err(_) => {
+                        return Err(RiakErr::InvalidMapReduce(format!("{:?} is not valid UTF-8",
+                                                                     value)))
+                    }
+                };
+            }
+            Json::Array(ref values) => {
+                out.push('[');
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        out.push(',');
+                    }
+                    match value.write(out) {
+                        Ok(()) => (),
+                        Err(error) => return Err(error),
+                    };
+                }
+                out.push(']');
+            }
+            Json::Object(ref fields) => {
+                out.push('{');
+                for (i, &(name, ref value)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        out.push(',');
+                    }
+                    write_json_string(name, out);
+                    out.push(':');
+                    match value.write(out) {
+                        Ok(()) => (),
+                        Err(error) => return Err(error),
+                    };
+                }
+                out.push('}');
+            }
+            Json::Raw(ref value) => out.push_str(value),
+        }
+        Ok(())
+    }
+}
+
+fn write_json_string(value: &str, out: &mut String) {
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+fn key_filters_to_json(filters: &[KeyFilter]) -> Json {
+    Json::Array(filters.iter().map(|filter| filter.to_json()).collect())
+}
@@ -3,10 +3,11 @@
 /// For more information: https://docs.basho.com/riak/kv/latest/developing/usage/creating-objects/
 
 use errors::RiakErr;
-use private_traits::DeleteObjectReqPrivate;
+use private_traits::{DeleteObjectReqPrivate, ObjectContentPrivate};
 use protobuf::Message;
 use rpb::riak_kv::RpbDelReq;
 use rpb::utils::{fetch_object_req_to_rpb_get_req, store_object_req_to_rpb_put_req, RpbGenerator};
+use std::collections::HashMap;
 
 /// `DeleteObjectReq` represents a request to delete an object from Riak
 #[derive(Clone, Debug)]
@@ -399,6 +400,52 @@ impl RpbGenerator for StoreObjectReq {
     }
 }
 
+/// Represents the response received from Riak after storing an object.
+///
+/// The key is only present when it was generated by Riak, and the vector clock and contents (all
+/// siblings of the object) are only present when "return_body" or "return_head" was set in the
+/// request.
+#[derive(Clone, Debug)]
+pub struct StoreObjectResp {
+    content: Vec<ObjectContent>,
+    vclock: Option<Vec<u8>>,
+    key: Option<Vec<u8>>,
+}
+
+impl StoreObjectResp {
+    pub fn new(content: &Vec<ObjectContent>) -> StoreObjectResp {
+        StoreObjectResp {
+            content: content.clone(),
+            vclock: None,
+            key: None,
+        }
+    }
+
+    pub fn get_content(&self) -> Vec<ObjectContent> {
+        self.content.clone()
+    }
+
+    pub fn set_content(&mut self, content: Vec<ObjectContent>) {
+        self.content = content;
+    }
+
+    pub fn get_vclock(&self) -> Option<Vec<u8>> {
+        self.vclock.clone()
+    }
+
+    pub fn set_vclock(&mut self, vclock: Vec<u8>) {
+        self.vclock = Some(vclock);
+    }
+
+    pub fn get_key(&self) -> Option<Vec<u8>> {
+        self.key.clone()
+    }
+
+    pub fn set_key(&mut self, key: Vec<u8>) {
+        self.key = Some(key);
+    }
+}
+
 /// The data used to perform a fetch object request
 ///
 /// # Examples
@@ -416,7 +463,7 @@ pub struct FetchObjectReq {
     pr: Option<u32>,
     basic_quorum: Option<bool>,
     notfound_ok: Option<bool>,
-    if_modified: Option<bool>,
+    if_modified: Option<Vec<u8>>,
     head: Option<bool>,
     deletedvclock: Option<bool>,
     timeout: Option<u32>,
@@ -459,6 +506,103 @@ impl FetchObjectReq {
     pub fn set_key<T: Into<Vec<u8>>>(&mut self, key: T) {
         self.key = key.into()
     }
+
+    pub fn get_r(&self) -> Option<u32> {
+        self.r
+    }
+
+    pub fn set_r(&mut self, r: u32) {
+        self.r = Some(r);
+    }
+
+    pub fn get_pr(&self) -> Option<u32> {
+        self.pr
+    }
+
+    pub fn set_pr(&mut self, pr: u32) {
+        self.pr = Some(pr);
+    }
+
+    pub fn get_basic_quorum(&self) -> Option<bool> {
+        self.basic_quorum
+    }
+
+    pub fn set_basic_quorum(&mut self, basic_quorum: bool) {
+        self.basic_quorum = Some(basic_quorum);
+    }
+
+    pub fn get_notfound_ok(&self) -> Option<bool> {
+        self.notfound_ok
+    }
+
+    pub fn set_notfound_ok(&mut self, notfound_ok: bool) {
+        self.notfound_ok = Some(notfound_ok);
+    }
+
+    pub fn get_if_modified(&self) -> Option<Vec<u8>> {
+        self.if_modified.clone()
+    }
+
+    /// only return the object if its vclock differs from `vclock`, otherwise the response will
+    /// have no content and "unchanged" will be set
+    pub fn set_if_modified<T: Into<Vec<u8>>>(&mut self, vclock: T) {
+        self.if_modified = Some(vclock.into());
+    }
+
+    /// remove the "if_modified" vclock, so the object is always returned
+    pub fn clear_if_modified(&mut self) {
+        self.if_modified = None;
+    }
+
+    pub fn get_head(&self) -> Option<bool> {
+        self.head
+    }
+
+    /// only return the metadata of the object, the values of the contents will be empty
+    pub fn set_head(&mut self, head: bool) {
+        self.head = Some(head);
+    }
+
+    pub fn get_deletedvclock(&self) -> Option<bool> {
+        self.deletedvclock
+    }
+
+    /// return the vclock of the tombstone if the object has been deleted
+    pub fn set_deletedvclock(&mut self, deletedvclock: bool) {
+        self.deletedvclock = Some(deletedvclock);
+    }
+
+    pub fn get_timeout(&self) -> Option<u32> {
+        self.timeout
+    }
+
+    pub fn set_timeout(&mut self, timeout: u32) {
+        self.timeout = Some(timeout);
+    }
+
+    pub fn get_sloppy_quorum(&self) -> Option<bool> {
+        self.sloppy_quorum
+    }
+
+    pub fn set_sloppy_quorum(&mut self, sloppy_quorum: bool) {
+        self.sloppy_quorum = Some(sloppy_quorum);
+    }
+
+    pub fn get_n_val(&self) -> Option<u32> {
+        self.n_val
+    }
+
+    pub fn set_n_val(&mut self, n_val: u32) {
+        self.n_val = Some(n_val);
+    }
+
+    pub fn get_bucket_type(&self) -> Option<Vec<u8>> {
+        self.bucket_type.clone()
+    }
+
+    pub fn set_bucket_type<T: Into<Vec<u8>>>(&mut self, bucket_type: T) {
+        self.bucket_type = Some(bucket_type.into());
+    }
 }
 
 impl RpbGenerator for FetchObjectReq {
@@ -530,11 +674,11 @@ pub struct ObjectContent {
     charset: Option<Vec<u8>>,
     content_encoding: Option<Vec<u8>>,
     vtag: Option<Vec<u8>>,
-    // links: Vec<RpbLink>,
+    links: Vec<Link>,
     last_mod: Option<u32>,
     last_mod_usecs: Option<u32>,
-    // repeated RpbPair usermeta = 9;
-    // repeated RpbPair indexes = 10;
+    usermeta: HashMap<Vec<u8>, Vec<u8>>,
+    indexes: Vec<(Vec<u8>, Vec<u8>)>,
     deleted: Option<bool>,
 }
 
@@ -546,8 +690,11 @@ impl ObjectContent {
             charset: None,
             content_encoding: None,
             vtag: None,
+            links: Vec::new(),
             last_mod: None,
             last_mod_usecs: None,
+            usermeta: HashMap::new(),
+            indexes: Vec::new(),
             deleted: None,
         }
     }
@@ -584,6 +731,11 @@ impl ObjectContent {
         self.content_encoding = Some(content_encoding.into());
     }
 
+    /// remove the "content_encoding", for a value that is no longer encoded
+    pub fn clear_content_encoding(&mut self) {
+        self.content_encoding = None;
+    }
+
     pub fn get_vtag(&self) -> Option<Vec<u8>> {
         self.vtag.clone()
     }
@@ -615,4 +767,109 @@ impl ObjectContent {
     pub fn set_deleted(&mut self, deleted: bool) {
         self.deleted = Some(deleted);
     }
+
+    pub fn get_links(&self) -> Vec<Link> {
+        self.links.clone()
+    }
+
+    pub fn set_links(&mut self, links: Vec<Link>) {
+        self.links = links;
+    }
+
+    pub fn add_link<T: Into<Vec<u8>>>(&mut self, bucket: T, key: T, tag: T) {
+        self.links.push(Link::new(bucket, key, tag));
+    }
+
+    pub fn get_usermeta(&self) -> HashMap<Vec<u8>, Vec<u8>> {
+        self.usermeta.clone()
+    }
+
+    pub fn set_usermeta(&mut self, usermeta: HashMap<Vec<u8>, Vec<u8>>) {
+        self.usermeta = usermeta;
+    }
+
+    pub fn add_usermeta<T: Into<Vec<u8>>>(&mut self, key: T, value: T) {
+        self.usermeta.insert(key.into(), value.into());
+    }
+
+    /// get every secondary index entry as `(index name, value)` pairs
+    pub fn get_indexes(&self) -> Vec<(Vec<u8>, Vec<u8>)> {
+        self.indexes.clone()
+    }
+
+    /// get the values of a single secondary index
+    pub fn get_index<T: Into<Vec<u8>>>(&self, name: T) -> Vec<Vec<u8>> {
+        let name = name.into();
+        self.indexes
+            .iter()
+            .filter(|(index, _)| *index == name)
+            .map(|(_, value)| value.clone())
+            .collect()
+    }
+
+    /// add a secondary index entry.
+    ///
+    /// The index name must end in `_bin` for binary values or `_int` for integer values, and
+    /// values for `_int` indexes must be integers.
+    pub fn add_index<T: Into<Vec<u8>>>(&mut self, name: T, value: T) -> Result<(), RiakErr> {
+        let name = name.into();
+        let value = value.into();
+
+        if name.ends_with(b"_int") {
+            let is_integer = match String::from_utf8(value.clone()) {
+                Ok(string) => string.parse::<i64>().is_ok(),
+                Err(_) => false,
+            };
+            if !is_integer {
+                return Err(RiakErr::InvalidIndex(format!("value {} for index {} is not an integer",
+                                                         String::from_utf8_lossy(&value),
+                                                         String::from_utf8_lossy(&name))));
+            }
+        } else if !name.ends_with(b"_bin") {
+            return Err(RiakErr::InvalidIndex(format!("index {} does not end in _bin or _int",
+                                                     String::from_utf8_lossy(&name))));
+        }
+
+        if !self.indexes.contains(&(name.clone(), value.clone())) {
+            self.indexes.push((name, value));
+        }
+
+        Ok(())
+    }
+
+    /// add an entry to an integer secondary index, `name` must end in `_int`
+    pub fn add_int_index<T: Into<Vec<u8>>>(&mut self, name: T, value: i64) -> Result<(), RiakErr> {
+        self.add_index(name.into(), value.to_string().into_bytes())
+    }
+
+    /// remove every entry for a secondary index
+    pub fn remove_index<T: Into<Vec<u8>>>(&mut self, name: T) {
+        let name = name.into();
+        self.indexes.retain(|(index, _)| *index != name);
+    }
+}
+
+impl ObjectContentPrivate for ObjectContent {
+    fn set_indexes_unchecked(&mut self, indexes: Vec<(Vec<u8>, Vec<u8>)>) {
+        self.indexes = indexes;
+    }
+}
+
+/// `Link` represents a one-way link from an object to the object at `bucket` and `key`, tagged
+/// with `tag` to describe the relationship.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Link {
+    pub bucket: Vec<u8>,
+    pub key: Vec<u8>,
+    pub tag: Vec<u8>,
+}
+
+impl Link {
+    pub fn new<T: Into<Vec<u8>>>(bucket: T, key: T, tag: T) -> Link {
+        Link {
+            bucket: bucket.into(),
+            key: key.into(),
+            tag: tag.into(),
+        }
+    }
 }
@@ -0,0 +1,424 @@
+/// A thread-safe pool of connections to a single Riak node.
+///
+/// Connections are checked out of the `Pool` as `PooledClient`s, which can be used exactly like a
+/// `Client` and are returned to the pool when dropped. Idle connections are verified with a ping
+/// before being handed out, reconnected if they have broken, and closed once they have been idle
+/// for longer than the idle timeout.
+
+use Client;
+use errors::RiakErr;
+#[cfg(feature = "tls")]
+use security::SecurityConfig;
+use std::collections::VecDeque;
+use std::io;
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+// Defaults
+static DEFAULT_MIN_SIZE: usize = 1;
+static DEFAULT_MAX_SIZE: usize = 10;
+static DEFAULT_CHECKOUT_TIMEOUT_SECS: u64 = 30;
+static DEFAULT_IDLE_TIMEOUT_SECS: u64 = 300;
+static DEFAULT_REQUEST_TIMEOUT: u32 = 3600;
+
+/// `PoolConfig` represents the settings used to build a `Pool`.
+#[derive(Clone, Debug)]
+pub struct PoolConfig {
+    min_size: usize,
+    max_size: usize,
+    checkout_timeout: Duration,
+    idle_timeout: Duration,
+    request_timeout: u32,
+    health_check: bool,
+    #[cfg(feature = "tls")]
+    security: Option<SecurityConfig>,
+}
+
+impl PoolConfig {
+    /// constructs a new `PoolConfig` with default settings
+    pub fn new() -> PoolConfig {
+        PoolConfig {
+            min_size: DEFAULT_MIN_SIZE,
+            max_size: DEFAULT_MAX_SIZE,
+            checkout_timeout: Duration::from_secs(DEFAULT_CHECKOUT_TIMEOUT_SECS),
+            idle_timeout: Duration::from_secs(DEFAULT_IDLE_TIMEOUT_SECS),
+            request_timeout: DEFAULT_REQUEST_TIMEOUT,
+            health_check: true,
+            #[cfg(feature = "tls")]
+            security: None,
+        }
+    }
+
+    /// get the number of connections opened when the pool is built and kept open while idle
+    pub fn get_min_size(&self) -> usize {
+        self.min_size
+    }
+
+    /// set the number of connections opened when the pool is built and kept open while idle
+    pub fn set_min_size(&mut self, min_size: usize) {
+        self.min_size = min_size;
+    }
+
+    /// get the maximum number of connections the pool will open
+    pub fn get_max_size(&self) -> usize {
+        self.max_size
+    }
+
+    /// set the maximum number of connections the pool will open
+    pub fn set_max_size(&mut self, max_size: usize) {
+        self.max_size = max_size;
+    }
+
+    /// get how long a checkout will wait for a connection to become available
+    pub fn get_checkout_timeout(&self) -> Duration {
+        self.checkout_timeout
+    }
+
+    /// set how long a checkout will wait for a connection to become available
+    pub fn set_checkout_timeout(&mut self, checkout_timeout: Duration) {
+        self.checkout_timeout = checkout_timeout;
+    }
+
+    /// get how long a connection may sit idle before it is closed
+    pub fn get_idle_timeout(&self) -> Duration {
+        self.idle_timeout
+    }
+
+    /// set how long a connection may sit idle before it is closed
+    pub fn set_idle_timeout(&mut self, idle_timeout: Duration) {
+        self.idle_timeout = idle_timeout;
+    }
+
+    /// get the timeout (in seconds) used by each connection for requests
+    pub fn get_request_timeout(&self) -> u32 {
+        self.request_timeout
+    }
+
+    /// set the timeout (in seconds) used by each connection for requests
+    pub fn set_request_timeout(&mut self, request_timeout: u32) {
+        self.request_timeout = request_timeout;
+    }
+
+    /// get whether or not idle connections are pinged before being checked out
+    pub fn get_health_check(&self) -> bool {
+        self.health_check
+    }
+
+    /// set whether or not idle connections are pinged before being checked out
+    pub fn set_health_check(&mut self, health_check: bool) {
+        self.health_check = health_check;
+    }
+
+    /// get the security settings connections are opened with
+    #[cfg(feature = "tls")]
+    pub fn get_security(&self) -> Option<SecurityConfig> {
+        self.security.clone()
+    }
+
+    /// set the security settings connections are opened with, upgrading them to TLS and
+    /// authenticating
+    #[cfg(feature = "tls")]
+    pub fn set_security(&mut self, security: SecurityConfig) {
+        self.security = Some(security);
+    }
+
+    // Open a new connection with these settings.
+    #[cfg(not(feature = "tls"))]
+    fn connect(&self, addr: SocketAddr) -> Result<Client, RiakErr> {
+        Client::new_with_timeout(addr, self.request_timeout)
+    }
+
+    // Open a new connection with these settings.
+    #[cfg(feature = "tls")]
+    fn connect(&self, addr: SocketAddr) -> Result<Client, RiakErr> {
+        match self.security {
+            Some(ref security) => {
+                Client::new_secure_with_timeout(addr, security, self.request_timeout)
+            }
+            None => Client::new_with_timeout(addr, self.request_timeout),
+        }
+    }
+}
+
+// An idle connection along with when it was returned to the pool.
+#[derive(Debug)]
+struct IdleClient {
+    client: Client,
+    since: Instant,
+}
+
+// The mutable state of the pool, `total` counts idle and checked out connections.
+#[derive(Debug)]
+struct PoolState {
+    idle: VecDeque<IdleClient>,
+    total: usize,
+}
+
+#[derive(Debug)]
+struct PoolInner {
+    addr: SocketAddr,
+    config: PoolConfig,
+    state: Mutex<PoolState>,
+    available: Condvar,
+}
+
+impl PoolInner {
+    fn lock(&self) -> MutexGuard<'_, PoolState> {
+        match self.state.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+
+    // Close connections that have been idle too long, keeping at least `min_size` open.
+    fn evict_idle(&self, state: &mut PoolState) {
+        let now = Instant::now();
+        let idle_timeout = self.config.idle_timeout;
+        while state.total > self.config.min_size {
+            let expired = match state.idle.front() {
+                Some(idle) => now.duration_since(idle.since) >= idle_timeout,
+                None => false,
+            };
+            if !expired {
+                break;
+            }
+            debug!("closing connection to {} after being idle", self.addr);
+            state.idle.pop_front();
+            state.total -= 1;
+        }
+    }
+
+    // Give up a connection slot, letting a waiting checkout open a new connection.
+    fn release_slot(&self) {
+        let mut state = self.lock();
+        state.total -= 1;
+        self.available.notify_one();
+    }
+
+    // Verify an idle connection with a ping, reconnecting it if it has broken.
+    fn check_health(&self, client: &mut Client) -> Result<(), RiakErr> {
+        if !self.config.health_check {
+            return Ok(());
+        }
+
+        match client.ping() {
+            Ok(()) => return Ok(()),
+            Err(error) => debug!("pooled connection failed health check: {:?}", error),
+        };
+
+        match client.reconnect() {
+            Ok(()) => client.ping(),
+            Err(error) => Err(error),
+        }
+    }
+}
+
+/// `Pool` represents a thread-safe pool of `Client` connections to a single Riak node.
+///
+/// Cloning a `Pool` is cheap and produces a handle to the same set of connections.
+///
+/// # Examples
+///
+/// ```
+/// use riak::pool::{Pool, PoolConfig};
+/// use std::thread;
+///
+/// let mut config = PoolConfig::new();
+/// config.set_max_size(4);
+/// let pool = Pool::new("10.0.0.2:8087", config).unwrap();
+///
+/// let handles: Vec<_> = (0..8).map(|_| {
+///     let pool = pool.clone();
+///     thread::spawn(move || {
+///         let mut riak = pool.get().unwrap();
+///         riak.ping().unwrap();
+///     })
+/// }).collect();
+///
+/// for handle in handles {
+///     handle.join().unwrap();
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct Pool {
+    inner: Arc<PoolInner>,
+}
+
+impl Pool {
+    /// Constructs a new `Pool`, opening `min_size` connections up front.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `min_size` is larger than `max_size`, the address can not be resolved
+    /// or any of the initial connections can not be established.
+    pub fn new<A: ToSocketAddrs>(addr: A, config: PoolConfig) -> Result<Pool, RiakErr> {
+        if config.min_size > config.max_size {
+            return Err(RiakErr::IoError(io::Error::new(io::ErrorKind::InvalidInput,
+                                                       "pool min_size is larger than max_size")));
+        }
+
+        let addr = match addr.to_socket_addrs() {
+            Ok(mut addrs) => {
+                match addrs.next() {
+                    Some(addr) => addr,
+                    None => {
+                        return Err(RiakErr::IoError(io::Error::new(io::ErrorKind::InvalidInput,
+                                                                   "no address to connect to")))
+                    }
+                }
+            }
+            Err(error) => return Err(RiakErr::IoError(error)),
+        };
+
+        let mut idle: VecDeque<IdleClient> = VecDeque::new();
+        for _ in 0..config.min_size {
+            let client = match config.connect(addr) {
+                Ok(client) => client,
+                Err(error) => return Err(error),
+            };
+            idle.push_back(IdleClient {
+                client: client,
+                since: Instant::now(),
+            });
+        }
+
+        let total = idle.len();
+        Ok(Pool {
+            inner: Arc::new(PoolInner {
+                addr: addr,
+                config: config,
+                state: Mutex::new(PoolState {
+                    idle: idle,
+                    total: total,
+                }),
+                available: Condvar::new(),
+            }),
+        })
+    }
+
+    /// Checks a connection out of the pool, waiting up to the checkout timeout for one to become
+    /// available if the pool is at its maximum size.
+    ///
+    /// # Errors
+    ///
+    /// Returns `RiakErr::PoolTimeout` if no connection became available in time, or any error
+    /// that occurred while opening or reconnecting a connection.
+    pub fn get(&self) -> Result<PooledClient, RiakErr> {
+        let deadline = Instant::now() + self.inner.config.checkout_timeout;
+
+        loop {
+            let mut state = self.inner.lock();
+            self.inner.evict_idle(&mut state);
+
+            // reuse the most recently returned connection, so older ones can expire
+            if let Some(idle) = state.idle.pop_back() {
+                drop(state);
+                let mut client = idle.client;
+                match self.inner.check_health(&mut client) {
+                    Ok(()) => return Ok(self.wrap(client)),
+                    Err(error) => {
+                        debug!("dropping pooled connection that could not reconnect: {:?}",
+                               error);
+                        self.inner.release_slot();
+                        continue;
+                    }
+                };
+            }
+
+            // open a new connection if there's room for one
+            if state.total < self.inner.config.max_size {
+                state.total += 1;
+                drop(state);
+                return match self.inner.config.connect(self.inner.addr) {
+                    Ok(client) => Ok(self.wrap(client)),
+                    Err(error) => {
+                        self.inner.release_slot();
+                        Err(error)
+                    }
+                };
+            }
+
+            // otherwise wait for a connection to be returned
+            let now = Instant::now();
+            if now >= deadline {
+                return Err(RiakErr::PoolTimeout);
+            }
+            let _ = match self.inner.available.wait_timeout(state, deadline - now) {
+                Ok(result) => result,
+                Err(poisoned) => poisoned.into_inner(),
+            };
+        }
+    }
+
+    /// Closes any connections that have been idle longer than the idle timeout.
+    ///
+    /// This also happens whenever a connection is checked out or returned.
+    pub fn evict_idle(&self) {
+        let mut state = self.inner.lock();
+        self.inner.evict_idle(&mut state);
+    }
+
+    /// The number of connections currently open, whether idle or checked out.
+    pub fn size(&self) -> usize {
+        self.inner.lock().total
+    }
+
+    /// The number of idle connections waiting to be checked out.
+    pub fn idle_count(&self) -> usize {
+        self.inner.lock().idle.len()
+    }
+
+    fn wrap(&self, client: Client) -> PooledClient {
+        PooledClient {
+            client: Some(client),
+            pool: self.inner.clone(),
+        }
+    }
+}
+
+/// `PooledClient` is a `Client` checked out of a `Pool`, it is returned to the pool when dropped.
+#[derive(Debug)]
+pub struct PooledClient {
+    client: Option<Client>,
+    pool: Arc<PoolInner>,
+}
+
+impl PooledClient {
+    /// Closes the connection rather than returning it to the pool when dropped, freeing its slot
+    /// for a new connection.
+    pub fn discard(mut self) {
+        if self.client.take().is_some() {
+            self.pool.release_slot();
+        }
+    }
+}
+
+impl Deref for PooledClient {
+    type Target = Client;
+
+    fn deref(&self) -> &Client {
+        self.client.as_ref().expect("pooled client used after being returned")
+    }
+}
+
+impl DerefMut for PooledClient {
+    fn deref_mut(&mut self) -> &mut Client {
+        self.client.as_mut().expect("pooled client used after being returned")
+    }
+}
+
+impl Drop for PooledClient {
+    fn drop(&mut self) {
+        if let Some(client) = self.client.take() {
+            let mut state = self.pool.lock();
+            state.idle.push_back(IdleClient {
+                client: client,
+                since: Instant::now(),
+            });
+            self.pool.evict_idle(&mut state);
+            self.pool.available.notify_one();
+        }
+    }
+}
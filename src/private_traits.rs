@@ -1,5 +1,9 @@
+use datatypes::{FetchDataTypeResp, UpdateDataTypeResp};
 use errors::RiakErr;
+#[cfg(feature = "tls")]
+use native_tls::TlsConnector;
 use rpb::riak::RpbBucketProps;
+use rpb::riak_dt::{DtFetchResp, DtUpdateResp};
 use rpb::riak_yokozuna::RpbYokozunaIndex;
 use yokozuna::YokozunaIndex;
 
@@ -8,6 +12,28 @@ pub trait BucketPropsPrivate {
     fn write_to_bytes<T: Into<Vec<u8>>>(&self, T, bool) -> Result<Vec<u8>, RiakErr>;
 }
 
+pub trait FetchDataTypeRespPrivate {
+    fn new_from_dt_fetch_resp(DtFetchResp) -> FetchDataTypeResp;
+}
+
+pub trait UpdateDataTypeReqPrivate {
+    fn new_response(&self, DtUpdateResp) -> UpdateDataTypeResp;
+}
+
+pub trait IndexQueryPrivate {
+    fn write_to_bytes(&self, bool) -> Result<Vec<u8>, RiakErr>;
+}
+
+pub trait ObjectContentPrivate {
+    fn set_indexes_unchecked(&mut self, Vec<(Vec<u8>, Vec<u8>)>);
+}
+
+#[cfg(feature = "tls")]
+pub trait SecurityConfigPrivate {
+    fn auth_req_bytes(&self) -> Result<Vec<u8>, RiakErr>;
+    fn connector(&self) -> Result<TlsConnector, RiakErr>;
+}
+
 pub trait DeleteObjectReqPrivate {
     fn write_to_bytes(&self) -> Result<Vec<u8>, RiakErr>;
 }
@@ -0,0 +1,102 @@
+/// Sibling resolution for objects stored in buckets with "allow_mult" enabled.
+///
+/// When concurrent writes conflict Riak keeps every version as a sibling. A `ConflictResolver`
+/// picks or merges them back into a single value, which `Client::fetch_resolved` can then store
+/// with the fetched vclock so the siblings are replaced.
+///
+/// For more information: https://docs.basho.com/riak/kv/latest/developing/usage/conflict-resolution/
+
+use object::ObjectContent;
+
+/// `ConflictResolver` is implemented by types that can resolve a list of siblings into a single
+/// value.
+pub trait ConflictResolver {
+    /// resolves `siblings` into a single value, this is only called with at least two siblings
+    fn resolve(&self, siblings: &[ObjectContent]) -> ObjectContent;
+}
+
+/// `LastModifiedWins` resolves siblings by picking the one modified most recently, according to
+/// "last_mod" and "last_mod_usecs".
+///
+/// Siblings without a modification time are treated as the oldest, and ties are won by the first
+/// sibling.
+#[derive(Clone, Copy, Debug)]
+pub struct LastModifiedWins;
+
+impl ConflictResolver for LastModifiedWins {
+    fn resolve(&self, siblings: &[ObjectContent]) -> ObjectContent {
+        let mut newest = &siblings[0];
+        for sibling in siblings.iter().skip(1) {
+            if last_modified(sibling) > last_modified(newest) {
+                newest = sibling;
+            }
+        }
+        newest.clone()
+    }
+}
+
+// The modification time of a sibling as (seconds, microseconds).
+fn last_modified(content: &ObjectContent) -> (u32, u32) {
+    (content.get_last_mod().unwrap_or(0), content.get_last_mod_usecs().unwrap_or(0))
+}
+
+/// `LongestValue` resolves siblings by picking the one with the longest value, ties are won by
+/// the first sibling.
+#[derive(Clone, Copy, Debug)]
+pub struct LongestValue;
+
+impl ConflictResolver for LongestValue {
+    fn resolve(&self, siblings: &[ObjectContent]) -> ObjectContent {
+        let mut longest = &siblings[0];
+        for sibling in siblings.iter().skip(1) {
+            if sibling.get_value().len() > longest.get_value().len() {
+                longest = sibling;
+            }
+        }
+        longest.clone()
+    }
+}
+
+/// `ClosureResolver` resolves siblings with a user provided closure, such as one that merges the
+/// values of every sibling.
+///
+/// # Examples
+///
+/// ```
+/// use riak::object::ObjectContent;
+/// use riak::resolver::{ClosureResolver, ConflictResolver};
+///
+/// // merge the siblings by concatenating their values
+/// let resolver = ClosureResolver::new(|siblings: &[ObjectContent]| {
+///     let mut merged = Vec::new();
+///     for sibling in siblings {
+///         merged.extend(sibling.get_value());
+///     }
+///     ObjectContent::new(merged)
+/// });
+///
+/// let siblings = vec![ObjectContent::new("a"), ObjectContent::new("b")];
+/// assert_eq!(resolver.resolve(&siblings).get_value(), b"ab".to_vec());
+/// ```
+pub struct ClosureResolver<F>
+    where F: Fn(&[ObjectContent]) -> ObjectContent
+{
+    closure: F,
+}
+
+impl<F> ClosureResolver<F>
+    where F: Fn(&[ObjectContent]) -> ObjectContent
+{
+    /// constructs a new `ClosureResolver` from a closure
+    pub fn new(closure: F) -> ClosureResolver<F> {
+        ClosureResolver { closure: closure }
+    }
+}
+
+impl<F> ConflictResolver for ClosureResolver<F>
+    where F: Fn(&[ObjectContent]) -> ObjectContent
+{
+    fn resolve(&self, siblings: &[ObjectContent]) -> ObjectContent {
+        (self.closure)(siblings)
+    }
+}
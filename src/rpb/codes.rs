@@ -43,6 +43,7 @@ pub const RpbGetBucketKeyPreflistReq: u8 = 33;
 pub const RpbGetBucketKeyPreflistResp: u8 = 34;
 pub const RpbCSBucketReq: u8 = 40;
 pub const RpbCSBucketResp: u8 = 41;
+pub const RpbIndexBodyResp: u8 = 42;
 pub const RpbCounterUpdateReq: u8 = 50;
 pub const RpbCounterUpdateResp: u8 = 51;
 pub const RpbCounterGetReq: u8 = 52;
@@ -54,6 +55,8 @@ pub const RpbYokozunaIndexDeleteReq: u8 = 57;
 pub const RpbYokozunaSchemaGetReq: u8 = 58;
 pub const RpbYokozunaSchemaGetResp: u8 = 59;
 pub const RpbYokozunaSchemaPutReq: u8 = 60;
+pub const RpbCoverageReq: u8 = 70;
+pub const RpbCoverageResp: u8 = 71;
 pub const DtFetchReq: u8 = 80;
 pub const DtFetchResp: u8 = 81;
 pub const DtUpdateReq: u8 = 82;
@@ -1,8 +1,21 @@
 // Utilities for helping in conversion to and from protobuf data
 
+use coverage::CoverageEntry;
 use errors::RiakErr;
-use object::{ObjectContent, StoreObjectReq, FetchObjectReq, FetchObjectResp};
-use rpb::riak_kv::{RpbContent, RpbPutReq, RpbGetReq, RpbGetResp};
+use index::IndexResults;
+use object::{Link, ObjectContent, StoreObjectReq, StoreObjectResp, FetchObjectReq, FetchObjectResp};
+use private_traits::ObjectContentPrivate;
+use rpb::codes;
+use rpb::riak_kv::{RpbContent, RpbPutReq, RpbPutResp, RpbGetReq, RpbGetResp, RpbIndexBodyResp, RpbIndexObject,
+                   RpbIndexResp, RpbLink, RpbCoverageResp};
+use protobuf::{RepeatedField, parse_from_bytes};
+use rpb::riak::RpbPair;
+use rpb::riak_search::RpbSearchQueryResp;
+use rpb::riak_ts;
+use rpb::riak_ts::{TsColumnDescription, TsColumnType, TsRow};
+use std::collections::HashMap;
+use timeseries::{Column, ColumnType, QueryResults, Row, TsCell};
+use yokozuna::SearchResults;
 
 // `RpbGeneratorID` is a trait for structs that can be converted to protobuf bytes, with some
 // identifying piece of information needed to add to the protobuf conversion.
@@ -86,6 +99,26 @@ pub fn store_object_req_to_rpb_put_req(store_object_req: &StoreObjectReq) -> Rpb
     rpb_put_req
 }
 
+// Renders a `StoreObjectResp` from `RpbPutResp`
+pub fn rpb_put_resp_to_store_object_resp(rpb_put_resp: &mut RpbPutResp) -> StoreObjectResp {
+    let mut siblings: Vec<ObjectContent> = Vec::new();
+    for mut content in rpb_put_resp.take_content().into_iter() {
+        siblings.push(rpb_content_to_object_content(&mut content));
+    }
+
+    let mut store_object_resp = StoreObjectResp::new(&siblings);
+
+    if rpb_put_resp.has_vclock() {
+        store_object_resp.set_vclock(rpb_put_resp.take_vclock());
+    }
+
+    if rpb_put_resp.has_key() {
+        store_object_resp.set_key(rpb_put_resp.take_key());
+    }
+
+    store_object_resp
+}
+
 // Renders a `RpbContent` given an `ObjectContent`.
 pub fn object_content_to_rpb_content(object_content: &ObjectContent) -> RpbContent {
     let mut rpb_content = RpbContent::new();
@@ -121,6 +154,28 @@ pub fn object_content_to_rpb_content(object_content: &ObjectContent) -> RpbConte
         None => (),
     };
 
+    let mut links: Vec<RpbLink> = Vec::new();
+    for link in object_content.get_links().into_iter() {
+        let mut rpb_link = RpbLink::new();
+        rpb_link.set_bucket(link.bucket);
+        rpb_link.set_key(link.key);
+        rpb_link.set_tag(link.tag);
+        links.push(rpb_link);
+    }
+    rpb_content.set_links(RepeatedField::from_vec(links));
+
+    let mut usermeta: Vec<RpbPair> = Vec::new();
+    for (key, value) in object_content.get_usermeta().into_iter() {
+        usermeta.push(new_rpb_pair(key, value));
+    }
+    rpb_content.set_usermeta(RepeatedField::from_vec(usermeta));
+
+    let mut indexes: Vec<RpbPair> = Vec::new();
+    for (key, value) in object_content.get_indexes().into_iter() {
+        indexes.push(new_rpb_pair(key, value));
+    }
+    rpb_content.set_indexes(RepeatedField::from_vec(indexes));
+
     rpb_content
 }
 
@@ -144,9 +199,51 @@ pub fn rpb_content_to_object_content(rpb_content: &mut RpbContent) -> ObjectCont
         object_content.set_content_encoding(content_encoding);
     }
 
+    if rpb_content.has_vtag() {
+        object_content.set_vtag(rpb_content.get_vtag());
+    }
+
+    if rpb_content.has_last_mod() {
+        object_content.set_last_mod(rpb_content.get_last_mod());
+    }
+
+    if rpb_content.has_last_mod_usecs() {
+        object_content.set_last_mod_usecs(rpb_content.get_last_mod_usecs());
+    }
+
+    if rpb_content.has_deleted() {
+        object_content.set_deleted(rpb_content.get_deleted());
+    }
+
+    let mut links: Vec<Link> = Vec::new();
+    for mut rpb_link in rpb_content.take_links().into_iter() {
+        links.push(Link::new(rpb_link.take_bucket(), rpb_link.take_key(), rpb_link.take_tag()));
+    }
+    object_content.set_links(links);
+
+    let mut usermeta: HashMap<Vec<u8>, Vec<u8>> = HashMap::new();
+    for mut pair in rpb_content.take_usermeta().into_iter() {
+        usermeta.insert(pair.take_key(), pair.take_value());
+    }
+    object_content.set_usermeta(usermeta);
+
+    let mut indexes: Vec<(Vec<u8>, Vec<u8>)> = Vec::new();
+    for mut pair in rpb_content.take_indexes().into_iter() {
+        indexes.push((pair.take_key(), pair.take_value()));
+    }
+    object_content.set_indexes_unchecked(indexes);
+
     object_content
 }
 
+// Builds an `RpbPair` from a key and value.
+pub fn new_rpb_pair(key: Vec<u8>, value: Vec<u8>) -> RpbPair {
+    let mut rpb_pair = RpbPair::new();
+    rpb_pair.set_key(key);
+    rpb_pair.set_value(value);
+    rpb_pair
+}
+
 // Renders a `RpbGetReq` from a `FetchObjectReq`.
 pub fn fetch_object_req_to_rpb_get_req(fetch_object_req: &FetchObjectReq) -> RpbGetReq {
     let mut rpb_get_req = RpbGetReq::new();
@@ -154,7 +251,50 @@ pub fn fetch_object_req_to_rpb_get_req(fetch_object_req: &FetchObjectReq) -> Rpb
     rpb_get_req.set_bucket(fetch_object_req.get_bucket().clone());
     rpb_get_req.set_key(fetch_object_req.get_key().clone());
 
-    // TODO
+    match fetch_object_req.get_r() {
+        Some(v) => rpb_get_req.set_r(v),
+        None => (),
+    };
+    match fetch_object_req.get_pr() {
+        Some(v) => rpb_get_req.set_pr(v),
+        None => (),
+    };
+    match fetch_object_req.get_basic_quorum() {
+        Some(v) => rpb_get_req.set_basic_quorum(v),
+        None => (),
+    };
+    match fetch_object_req.get_notfound_ok() {
+        Some(v) => rpb_get_req.set_notfound_ok(v),
+        None => (),
+    };
+    match fetch_object_req.get_if_modified() {
+        Some(v) => rpb_get_req.set_if_modified(v),
+        None => (),
+    };
+    match fetch_object_req.get_head() {
+        Some(v) => rpb_get_req.set_head(v),
+        None => (),
+    };
+    match fetch_object_req.get_deletedvclock() {
+        Some(v) => rpb_get_req.set_deletedvclock(v),
+        None => (),
+    };
+    match fetch_object_req.get_timeout() {
+        Some(v) => rpb_get_req.set_timeout(v),
+        None => (),
+    };
+    match fetch_object_req.get_sloppy_quorum() {
+        Some(v) => rpb_get_req.set_sloppy_quorum(v),
+        None => (),
+    };
+    match fetch_object_req.get_n_val() {
+        Some(v) => rpb_get_req.set_n_val(v),
+        None => (),
+    };
+    match fetch_object_req.get_bucket_type() {
+        Some(v) => rpb_get_req.set_field_type(v),
+        None => (),
+    };
 
     rpb_get_req
 }
@@ -178,3 +318,798 @@ pub fn rpb_get_resp_to_fetch_object_resp(rpb_get_resp: &mut RpbGetResp) -> Fetch
 
     fetch_object_resp
 }
+
+// Renders a `SearchResults` from `RpbSearchQueryResp`
+pub fn rpb_search_query_resp_to_search_results(rpb_search_query_resp: &mut RpbSearchQueryResp)
+                                               -> SearchResults {
+    let mut docs: Vec<HashMap<Vec<u8>, Vec<Vec<u8>>>> = Vec::new();
+
+    for mut rpb_search_doc in rpb_search_query_resp.take_docs().into_iter() {
+        let mut doc: HashMap<Vec<u8>, Vec<Vec<u8>>> = HashMap::new();
+        for mut field in rpb_search_doc.take_fields().into_iter() {
+            doc.entry(field.take_key()).or_insert_with(Vec::new).push(field.take_value());
+        }
+        docs.push(doc);
+    }
+
+    let max_score = if rpb_search_query_resp.has_max_score() {
+        Some(rpb_search_query_resp.get_max_score())
+    } else {
+        None
+    };
+
+    let num_found = if rpb_search_query_resp.has_num_found() {
+        Some(rpb_search_query_resp.get_num_found())
+    } else {
+        None
+    };
+
+    SearchResults::new(docs, max_score, num_found)
+}
+
+// Renders a `riak_ts::TsCell` given a `TsCell`.
+pub fn ts_cell_to_rpb_ts_cell(ts_cell: &TsCell) -> riak_ts::TsCell {
+    let mut rpb_ts_cell = riak_ts::TsCell::new();
+
+    match *ts_cell {
+        TsCell::Varchar(ref v) => rpb_ts_cell.set_varchar_value(v.clone()),
+        TsCell::Sint64(v) => rpb_ts_cell.set_sint64_value(v),
+        TsCell::Double(v) => rpb_ts_cell.set_double_value(v),
+        TsCell::Timestamp(v) => rpb_ts_cell.set_timestamp_value(v),
+        TsCell::Boolean(v) => rpb_ts_cell.set_boolean_value(v),
+        TsCell::Null => (),
+    };
+
+    rpb_ts_cell
+}
+
+// Renders a `TsCell` given a `riak_ts::TsCell`, a cell without any value set is a null.
+pub fn rpb_ts_cell_to_ts_cell(rpb_ts_cell: &mut riak_ts::TsCell) -> TsCell {
+    if rpb_ts_cell.has_varchar_value() {
+        TsCell::Varchar(rpb_ts_cell.take_varchar_value())
+    } else if rpb_ts_cell.has_sint64_value() {
+        TsCell::Sint64(rpb_ts_cell.get_sint64_value())
+    } else if rpb_ts_cell.has_double_value() {
+        TsCell::Double(rpb_ts_cell.get_double_value())
+    } else if rpb_ts_cell.has_timestamp_value() {
+        TsCell::Timestamp(rpb_ts_cell.get_timestamp_value())
+    } else if rpb_ts_cell.has_boolean_value() {
+        TsCell::Boolean(rpb_ts_cell.get_boolean_value())
+    } else {
+        TsCell::Null
+    }
+}
+
+// Renders a `RepeatedField<riak_ts::TsCell>` given a slice of `TsCell`.
+pub fn ts_cells_to_rpb_ts_cells(ts_cells: &[TsCell]) -> RepeatedField<riak_ts::TsCell> {
+    let cells: Vec<riak_ts::TsCell> = ts_cells.iter().map(ts_cell_to_rpb_ts_cell).collect();
+    RepeatedField::from_vec(cells)
+}
+
+// Renders a `TsRow` given a `Row`.
+pub fn row_to_rpb_ts_row(row: &Row) -> TsRow {
+    let mut rpb_ts_row = TsRow::new();
+    rpb_ts_row.set_cells(ts_cells_to_rpb_ts_cells(&row.cells));
+    rpb_ts_row
+}
+
+// Renders a `Row` given a `TsRow`.
+pub fn rpb_ts_row_to_row(rpb_ts_row: &mut TsRow) -> Row {
+    let mut cells: Vec<TsCell> = Vec::new();
+    for mut cell in rpb_ts_row.take_cells().into_iter() {
+        cells.push(rpb_ts_cell_to_ts_cell(&mut cell));
+    }
+    Row::new(cells)
+}
+
+// Renders a `Column` given a `TsColumnDescription`.
+pub fn rpb_ts_column_description_to_column(description: &mut TsColumnDescription) -> Column {
+    let column_type = match description.get_field_type() {
+        TsColumnType::VARCHAR => ColumnType::Varchar,
+        TsColumnType::SINT64 => ColumnType::Sint64,
+        TsColumnType::DOUBLE => ColumnType::Double,
+        TsColumnType::TIMESTAMP => ColumnType::Timestamp,
+        TsColumnType::BOOLEAN => ColumnType::Boolean,
+    };
+    Column::new(description.take_name(), column_type)
+}
+
+// Renders a `QueryResults` given the columns and rows of a `TsGetResp` or `TsQueryResp`.
+pub fn rpb_ts_columns_and_rows_to_query_results(columns: RepeatedField<TsColumnDescription>,
+                                                rows: RepeatedField<TsRow>)
+                                                -> QueryResults {
+    let mut converted_columns: Vec<Column> = Vec::new();
+    for mut column in columns.into_iter() {
+        converted_columns.push(rpb_ts_column_description_to_column(&mut column));
+    }
+
+    let mut converted_rows: Vec<Row> = Vec::new();
+    for mut row in rows.into_iter() {
+        converted_rows.push(rpb_ts_row_to_row(&mut row));
+    }
+
+    QueryResults::new(converted_columns, converted_rows)
+}
+
+// Renders a `(key, FetchObjectResp)` pair from an `RpbIndexObject`
+pub fn rpb_index_object_to_key_and_fetch_object_resp(rpb_index_object: &mut RpbIndexObject)
+                                                     -> (Vec<u8>, FetchObjectResp) {
+    let key = rpb_index_object.take_key();
+    let mut rpb_get_resp = rpb_index_object.take_object();
+    (key, rpb_get_resp_to_fetch_object_resp(&mut rpb_get_resp))
+}
+
+// The code of the responses to an index query, `RpbIndexBodyResp` when the query requested
+// "return_body" and `RpbIndexResp` otherwise.
+pub fn index_response_code(return_body: bool) -> u8 {
+    if return_body {
+        codes::RpbIndexBodyResp
+    } else {
+        codes::RpbIndexResp
+    }
+}
+
+// Parses the bytes of an `RpbIndexResp`, or an `RpbIndexBodyResp` when the query requested
+// "return_body", into `IndexResults` along with whether or not the response was the last one.
+pub fn index_response_to_index_results(response: &[u8],
+                                       return_body: bool)
+                                       -> Result<(IndexResults, bool), RiakErr> {
+    let mut index_results = IndexResults::new();
+
+    if return_body {
+        let mut rpb_index_body_resp = match parse_from_bytes::<RpbIndexBodyResp>(response) {
+            Ok(parsed) => parsed,
+            Err(error) => return Err(RiakErr::ProtobufError(error)),
+        };
+
+        let mut objects: Vec<(Vec<u8>, FetchObjectResp)> = Vec::new();
+        for mut rpb_index_object in rpb_index_body_resp.take_objects().into_iter() {
+            objects.push(rpb_index_object_to_key_and_fetch_object_resp(&mut rpb_index_object));
+        }
+        index_results.set_objects(objects);
+
+        if rpb_index_body_resp.has_continuation() {
+            index_results.set_continuation(rpb_index_body_resp.take_continuation());
+        }
+
+        Ok((index_results, rpb_index_body_resp.get_done()))
+    } else {
+        let mut rpb_index_resp = match parse_from_bytes::<RpbIndexResp>(response) {
+            Ok(parsed) => parsed,
+            Err(error) => return Err(RiakErr::ProtobufError(error)),
+        };
+
+        index_results.set_keys(rpb_index_resp.take_keys().into_vec());
+
+        let mut terms: Vec<(Vec<u8>, Vec<u8>)> = Vec::new();
+        for mut result in rpb_index_resp.take_results().into_iter() {
+            terms.push((result.take_key(), result.take_value()));
+        }
+        index_results.set_terms(terms);
+
+        if rpb_index_resp.has_continuation() {
+            index_results.set_continuation(rpb_index_resp.take_continuation());
+        }
+
+        Ok((index_results, rpb_index_resp.get_done()))
+    }
+}
+
+// Parses the bytes of an `RpbCoverageResp` into a `CoverageEntry` for each of its entries.
+pub fn coverage_response_to_coverage_entries(response: &[u8])
+                                             -> Result<Vec<CoverageEntry>, RiakErr> {
+    let mut rpb_coverage_resp = match parse_from_bytes::<RpbCoverageResp>(response) {
+        Ok(parsed) => parsed,
+        Err(error) => return Err(RiakErr::ProtobufError(error)),
+    };
+
+    let mut entries: Vec<CoverageEntry> = Vec::new();
+    for mut entry in rpb_coverage_resp.take_entries().into_iter() {
+        let ip = String::from_utf8_lossy(entry.get_ip()).into_owned();
+        let keyspace_desc = String::from_utf8_lossy(entry.get_keyspace_desc()).into_owned();
+        entries.push(CoverageEntry::new(&ip,
+                                        entry.get_port() as u16,
+                                        &keyspace_desc,
+                                        entry.take_cover_context()));
+    }
+
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use datatypes::{Counter, DataType, FetchDataTypeResp, Flag, Hll, Map, MapFieldType, Register,
+                    Set, UpdateDataTypeReq};
+    use errors::RiakErr;
+    use private_traits::{FetchDataTypeRespPrivate, UpdateDataTypeReqPrivate};
+    use object::{FetchObjectReq, ObjectContent, StoreObjectReq};
+    use protobuf::{Message, MessageStatic, RepeatedField, parse_from_bytes};
+    use rpb::riak_dt::{DtFetchResp, DtFetchResp_DataType, DtUpdateReq, DtUpdateResp, DtValue,
+                       MapEntry, MapField, MapField_MapFieldType, MapOp, MapUpdate,
+                       MapUpdate_FlagOp};
+    use rpb::riak_kv::{RpbCoverageEntry, RpbCoverageResp, RpbGetResp, RpbIndexBodyResp,
+                       RpbIndexObject, RpbIndexResp, RpbPutResp};
+    use rpb::riak_search::{RpbSearchDoc, RpbSearchQueryResp};
+    use rpb::riak_ts;
+    use rpb::riak_ts::{TsColumnDescription, TsColumnType, TsRow};
+    use timeseries::{Column, ColumnType, Row, TsCell};
+
+    fn full_object_content() -> ObjectContent {
+        let mut content = ObjectContent::new("value");
+        content.set_content_type("application/json");
+        content.set_charset("utf-8");
+        content.set_content_encoding("gzip");
+        content.set_vtag(b"vtag");
+        content.set_last_mod(1496000000);
+        content.set_last_mod_usecs(123456);
+        content.set_deleted(true);
+        content.add_link("people", "bob", "friend");
+        content.add_usermeta("owner", "alice");
+        content.add_index("email_bin", "alice@example.com").unwrap();
+        content.add_int_index("age_int", 30).unwrap();
+        content
+    }
+
+    fn assert_object_content_eq(left: &ObjectContent, right: &ObjectContent) {
+        assert_eq!(left.get_value(), right.get_value());
+        assert_eq!(left.get_content_type(), right.get_content_type());
+        assert_eq!(left.get_charset(), right.get_charset());
+        assert_eq!(left.get_content_encoding(), right.get_content_encoding());
+        assert_eq!(left.get_vtag(), right.get_vtag());
+        assert_eq!(left.get_last_mod(), right.get_last_mod());
+        assert_eq!(left.get_last_mod_usecs(), right.get_last_mod_usecs());
+        assert_eq!(left.get_deleted(), right.get_deleted());
+        assert_eq!(left.get_links(), right.get_links());
+        assert_eq!(left.get_usermeta(), right.get_usermeta());
+        assert_eq!(left.get_indexes(), right.get_indexes());
+    }
+
+    // Encode and decode a message, as happens when it crosses the wire.
+    fn over_the_wire<M: MessageStatic>(message: &M) -> M {
+        parse_from_bytes::<M>(&message.write_to_bytes().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn object_content_round_trip() {
+        let content = full_object_content();
+        let mut rpb_content = over_the_wire(&object_content_to_rpb_content(&content));
+        assert_object_content_eq(&rpb_content_to_object_content(&mut rpb_content), &content);
+    }
+
+    #[test]
+    fn object_content_round_trip_leaves_unset_fields_unset() {
+        let content = ObjectContent::new("bare");
+        let mut rpb_content = over_the_wire(&object_content_to_rpb_content(&content));
+        assert!(!rpb_content.has_vtag());
+        assert!(!rpb_content.has_last_mod());
+        assert!(!rpb_content.has_deleted());
+        assert_object_content_eq(&rpb_content_to_object_content(&mut rpb_content), &content);
+    }
+
+    #[test]
+    fn rpb_content_to_object_content_decodes_last_mod() {
+        // as Riak sends it, with the time stored split into seconds and microseconds
+        let mut rpb_content = RpbContent::new();
+        rpb_content.set_value(b"value".to_vec());
+        rpb_content.set_last_mod(1496000000);
+        rpb_content.set_last_mod_usecs(123456);
+        let content = rpb_content_to_object_content(&mut over_the_wire(&rpb_content));
+        assert_eq!(content.get_last_mod(), Some(1496000000));
+        assert_eq!(content.get_last_mod_usecs(), Some(123456));
+
+        let mut rpb_content = RpbContent::new();
+        rpb_content.set_value(b"value".to_vec());
+        let content = rpb_content_to_object_content(&mut over_the_wire(&rpb_content));
+        assert_eq!(content.get_last_mod(), None);
+        assert_eq!(content.get_last_mod_usecs(), None);
+    }
+
+    #[test]
+    fn store_object_req_to_rpb_put_req_copies_options() {
+        let mut req = StoreObjectReq::new("bucket", full_object_content());
+        req.set_key("key");
+        req.set_vclock("vclock");
+        req.set_w(3);
+        req.set_dw(2);
+        req.set_pw(1);
+        req.set_return_body(true);
+        req.set_if_not_modified(true);
+        req.set_timeout(5000);
+        req.set_n_val(5);
+        req.set_bucket_type("maps");
+
+        let mut rpb_put_req = over_the_wire(&store_object_req_to_rpb_put_req(&req));
+        assert_eq!(rpb_put_req.get_bucket(), b"bucket");
+        assert_eq!(rpb_put_req.get_key(), b"key");
+        assert_eq!(rpb_put_req.get_vclock(), b"vclock");
+        assert_eq!(rpb_put_req.get_w(), 3);
+        assert_eq!(rpb_put_req.get_dw(), 2);
+        assert_eq!(rpb_put_req.get_pw(), 1);
+        assert!(rpb_put_req.get_return_body());
+        assert!(rpb_put_req.get_if_not_modified());
+        assert!(!rpb_put_req.has_if_none_match());
+        assert_eq!(rpb_put_req.get_timeout(), 5000);
+        assert_eq!(rpb_put_req.get_n_val(), 5);
+        assert_eq!(rpb_put_req.get_field_type(), b"maps");
+        assert_object_content_eq(&rpb_content_to_object_content(rpb_put_req.mut_content()),
+                                 &full_object_content());
+    }
+
+    #[test]
+    fn rpb_put_resp_to_store_object_resp_keeps_siblings() {
+        let mut rpb_put_resp = RpbPutResp::new();
+        rpb_put_resp.set_content(RepeatedField::from_vec(vec![
+            object_content_to_rpb_content(&full_object_content()),
+            object_content_to_rpb_content(&ObjectContent::new("other")),
+        ]));
+        rpb_put_resp.set_vclock(b"vclock".to_vec());
+        rpb_put_resp.set_key(b"generated".to_vec());
+
+        let resp = rpb_put_resp_to_store_object_resp(&mut over_the_wire(&rpb_put_resp));
+        let content = resp.get_content();
+        assert_eq!(content.len(), 2);
+        assert_object_content_eq(&content[0], &full_object_content());
+        assert_eq!(content[1].get_value(), b"other".to_vec());
+        assert_eq!(resp.get_vclock(), Some(b"vclock".to_vec()));
+        assert_eq!(resp.get_key(), Some(b"generated".to_vec()));
+    }
+
+    #[test]
+    fn fetch_object_req_to_rpb_get_req_copies_options() {
+        let mut req = FetchObjectReq::new("bucket", "key");
+        req.set_r(2);
+        req.set_pr(1);
+        req.set_basic_quorum(true);
+        req.set_notfound_ok(false);
+        req.set_if_modified("vclock");
+        req.set_head(true);
+        req.set_deletedvclock(true);
+        req.set_timeout(1000);
+        req.set_sloppy_quorum(false);
+        req.set_n_val(3);
+        req.set_bucket_type("sets");
+
+        let rpb_get_req = over_the_wire(&fetch_object_req_to_rpb_get_req(&req));
+        assert_eq!(rpb_get_req.get_bucket(), b"bucket");
+        assert_eq!(rpb_get_req.get_key(), b"key");
+        assert_eq!(rpb_get_req.get_r(), 2);
+        assert_eq!(rpb_get_req.get_pr(), 1);
+        assert!(rpb_get_req.get_basic_quorum());
+        assert!(rpb_get_req.has_notfound_ok() && !rpb_get_req.get_notfound_ok());
+        assert_eq!(rpb_get_req.get_if_modified(), b"vclock");
+        assert!(rpb_get_req.get_head());
+        assert!(rpb_get_req.get_deletedvclock());
+        assert_eq!(rpb_get_req.get_timeout(), 1000);
+        assert!(rpb_get_req.has_sloppy_quorum() && !rpb_get_req.get_sloppy_quorum());
+        assert_eq!(rpb_get_req.get_n_val(), 3);
+        assert_eq!(rpb_get_req.get_field_type(), b"sets");
+
+        let bare = fetch_object_req_to_rpb_get_req(&FetchObjectReq::new("bucket", "key"));
+        assert!(!bare.has_r() && !bare.has_if_modified() && !bare.has_field_type());
+    }
+
+    #[test]
+    fn rpb_get_resp_to_fetch_object_resp_keeps_siblings() {
+        let mut older = ObjectContent::new("older");
+        older.set_last_mod(100);
+        let mut rpb_get_resp = RpbGetResp::new();
+        rpb_get_resp.set_content(RepeatedField::from_vec(vec![
+            object_content_to_rpb_content(&older),
+            object_content_to_rpb_content(&full_object_content()),
+        ]));
+        rpb_get_resp.set_vclock(b"vclock".to_vec());
+        rpb_get_resp.set_unchanged(false);
+
+        let resp = rpb_get_resp_to_fetch_object_resp(&mut over_the_wire(&rpb_get_resp));
+        let content = resp.get_content();
+        assert_eq!(content.len(), 2);
+        assert_object_content_eq(&content[0], &older);
+        assert_object_content_eq(&content[1], &full_object_content());
+        assert_eq!(resp.get_vclock(), b"vclock".to_vec());
+        assert_eq!(resp.get_unchanged(), Some(false));
+
+        let empty = rpb_get_resp_to_fetch_object_resp(&mut RpbGetResp::new());
+        assert!(empty.get_content().is_empty());
+        assert!(empty.get_vclock().is_empty());
+        assert_eq!(empty.get_unchanged(), None);
+    }
+
+    #[test]
+    fn rpb_search_query_resp_to_search_results_groups_fields() {
+        let mut doc = RpbSearchDoc::new();
+        doc.set_fields(RepeatedField::from_vec(vec![new_rpb_pair(b"tag".to_vec(), b"a".to_vec()),
+                                                    new_rpb_pair(b"tag".to_vec(), b"b".to_vec()),
+                                                    new_rpb_pair(b"_yz_rk".to_vec(),
+                                                                 b"key".to_vec())]));
+        let mut resp = RpbSearchQueryResp::new();
+        resp.set_docs(RepeatedField::from_vec(vec![doc]));
+        resp.set_max_score(1.5);
+        resp.set_num_found(1);
+
+        let results = rpb_search_query_resp_to_search_results(&mut over_the_wire(&resp));
+        let docs = results.get_docs();
+        assert_eq!(docs.len(), 1);
+        assert_eq!(docs[0][&b"tag".to_vec()], vec![b"a".to_vec(), b"b".to_vec()]);
+        assert_eq!(docs[0][&b"_yz_rk".to_vec()], vec![b"key".to_vec()]);
+        assert_eq!(results.get_max_score(), Some(1.5));
+        assert_eq!(results.get_num_found(), Some(1));
+
+        let empty = rpb_search_query_resp_to_search_results(&mut RpbSearchQueryResp::new());
+        assert!(empty.get_docs().is_empty());
+        assert_eq!(empty.get_max_score(), None);
+        assert_eq!(empty.get_num_found(), None);
+    }
+
+    #[test]
+    fn ts_cell_round_trip() {
+        let cells = vec![TsCell::Varchar(b"host".to_vec()),
+                         TsCell::Sint64(-42),
+                         TsCell::Double(3.25),
+                         TsCell::Timestamp(1496000000000),
+                         TsCell::Boolean(true),
+                         TsCell::Null];
+        for cell in cells.iter() {
+            let mut rpb_ts_cell = over_the_wire(&ts_cell_to_rpb_ts_cell(cell));
+            assert_eq!(&rpb_ts_cell_to_ts_cell(&mut rpb_ts_cell), cell);
+        }
+
+        let row = Row::new(cells.clone());
+        let mut rpb_ts_row = over_the_wire(&row_to_rpb_ts_row(&row));
+        assert_eq!(rpb_ts_row_to_row(&mut rpb_ts_row), row);
+    }
+
+    #[test]
+    fn rpb_ts_columns_and_rows_to_query_results_converts_types() {
+        let types = [(TsColumnType::VARCHAR, ColumnType::Varchar),
+                         (TsColumnType::SINT64, ColumnType::Sint64),
+                         (TsColumnType::DOUBLE, ColumnType::Double),
+                         (TsColumnType::TIMESTAMP, ColumnType::Timestamp),
+                         (TsColumnType::BOOLEAN, ColumnType::Boolean)];
+
+        let mut columns: Vec<TsColumnDescription> = Vec::new();
+        let mut expected: Vec<Column> = Vec::new();
+        for (i, &(rpb_type, column_type)) in types.iter().enumerate() {
+            let name = format!("column{}", i);
+            let mut description = TsColumnDescription::new();
+            description.set_name(name.clone().into_bytes());
+            description.set_field_type(rpb_type);
+            columns.push(description);
+            expected.push(Column::new(name, column_type));
+        }
+
+        let mut rpb_row = TsRow::new();
+        rpb_row.set_cells(ts_cells_to_rpb_ts_cells(&[TsCell::Sint64(1), TsCell::Null]));
+
+        let results =
+            rpb_ts_columns_and_rows_to_query_results(RepeatedField::from_vec(columns),
+                                                     RepeatedField::from_vec(vec![rpb_row]));
+        assert_eq!(results.columns, expected);
+        assert_eq!(results.rows, vec![Row::new(vec![TsCell::Sint64(1), TsCell::Null])]);
+
+        assert_eq!(rpb_ts_cell_to_ts_cell(&mut riak_ts::TsCell::new()), TsCell::Null);
+    }
+
+    #[test]
+    fn index_response_to_index_results_parses_keys_and_terms() {
+        let mut resp = RpbIndexResp::new();
+        resp.set_keys(RepeatedField::from_vec(vec![b"k1".to_vec(), b"k2".to_vec()]));
+        resp.set_results(RepeatedField::from_vec(vec![new_rpb_pair(b"18".to_vec(),
+                                                                   b"k1".to_vec())]));
+        resp.set_continuation(b"next".to_vec());
+
+        let bytes = resp.write_to_bytes().unwrap();
+        let (results, done) = index_response_to_index_results(&bytes, false).unwrap();
+        assert_eq!(results.get_keys(), vec![b"k1".to_vec(), b"k2".to_vec()]);
+        assert_eq!(results.get_terms(), vec![(b"18".to_vec(), b"k1".to_vec())]);
+        assert_eq!(results.get_continuation(), Some(b"next".to_vec()));
+        assert!(!done);
+
+        resp.set_done(true);
+        let bytes = resp.write_to_bytes().unwrap();
+        let (_, done) = index_response_to_index_results(&bytes, false).unwrap();
+        assert!(done);
+    }
+
+    #[test]
+    fn index_response_to_index_results_parses_objects() {
+        let mut rpb_get_resp = RpbGetResp::new();
+        rpb_get_resp.set_content(RepeatedField::from_vec(vec![
+            object_content_to_rpb_content(&full_object_content()),
+        ]));
+        rpb_get_resp.set_vclock(b"vclock".to_vec());
+        let mut object = RpbIndexObject::new();
+        object.set_key(b"k1".to_vec());
+        object.set_object(rpb_get_resp);
+
+        let mut resp = RpbIndexBodyResp::new();
+        resp.set_objects(RepeatedField::from_vec(vec![object]));
+        resp.set_done(true);
+
+        let bytes = resp.write_to_bytes().unwrap();
+        let (results, done) = index_response_to_index_results(&bytes, true).unwrap();
+        let objects = results.get_objects();
+        assert_eq!(objects.len(), 1);
+        assert_eq!(objects[0].0, b"k1".to_vec());
+        assert_eq!(objects[0].1.get_vclock(), b"vclock".to_vec());
+        assert_object_content_eq(&objects[0].1.get_content()[0], &full_object_content());
+        assert_eq!(results.get_continuation(), None);
+        assert!(done);
+
+        assert!(index_response_to_index_results(&[0xff], true).is_err());
+    }
+
+    #[test]
+    fn new_rpb_pair_sets_key_and_value() {
+        let pair = new_rpb_pair(b"key".to_vec(), b"value".to_vec());
+        assert_eq!(pair.get_key(), b"key");
+        assert_eq!(pair.get_value(), b"value");
+    }
+
+    #[test]
+    fn coverage_response_to_coverage_entries_parses_entries() {
+        let mut entry = RpbCoverageEntry::new();
+        entry.set_ip(b"10.0.0.2".to_vec());
+        entry.set_port(8087);
+        entry.set_keyspace_desc(b"StartPartition: 0, PartitionCount: 1".to_vec());
+        entry.set_cover_context(b"context".to_vec());
+
+        let mut resp = RpbCoverageResp::new();
+        resp.set_entries(RepeatedField::from_vec(vec![entry]));
+
+        let bytes = resp.write_to_bytes().unwrap();
+        let entries = coverage_response_to_coverage_entries(&bytes).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].ip, "10.0.0.2");
+        assert_eq!(entries[0].port, 8087);
+        assert_eq!(entries[0].keyspace_desc, "StartPartition: 0, PartitionCount: 1");
+        assert_eq!(entries[0].cover_context, b"context".to_vec());
+
+        assert!(coverage_response_to_coverage_entries(&[0xff]).is_err());
+    }
+
+    // Encode an update request and parse it back, as Riak would receive it.
+    fn sent_update(req: &UpdateDataTypeReq) -> DtUpdateReq {
+        parse_from_bytes::<DtUpdateReq>(&req.write_to_bytes().unwrap()).unwrap()
+    }
+
+    fn map_field(name: &str, field_type: MapField_MapFieldType) -> MapField {
+        let mut field = MapField::new();
+        field.set_name(name.as_bytes().to_vec());
+        field.set_field_type(field_type);
+        field
+    }
+
+    fn map_update<'a>(op: &'a MapOp, name: &str) -> &'a MapUpdate {
+        op.get_updates().iter().find(|update| update.get_field().get_name() == name.as_bytes()).unwrap()
+    }
+
+    fn bytes_of(values: &[&str]) -> Vec<Vec<u8>> {
+        values.iter().map(|value| value.as_bytes().to_vec()).collect()
+    }
+
+    #[test]
+    fn counter_increments_become_a_counter_op() {
+        let mut counter = Counter::new();
+        assert!(!counter.is_modified());
+        counter.increment(5);
+        counter.decrement(2);
+        assert_eq!(counter.get_value(), 3);
+
+        let mut req = UpdateDataTypeReq::new("counters", "bucket", &DataType::Counter(counter));
+        req.set_key("key");
+        let sent = sent_update(&req);
+        assert_eq!(sent.get_field_type(), b"counters");
+        assert_eq!(sent.get_bucket(), b"bucket");
+        assert_eq!(sent.get_key(), b"key");
+        assert_eq!(sent.get_op().get_counter_op().get_increment(), 3);
+    }
+
+    #[test]
+    fn set_adds_and_removes_become_a_set_op() {
+        let mut set = Set::new();
+        set.add("a");
+        set.add("b");
+        set.add("a");
+        set.remove("b");
+        set.remove("c");
+        assert_eq!(set.get_value(), bytes_of(&["a"]));
+        assert!(set.contains("a"));
+
+        let mut req = UpdateDataTypeReq::new("sets", "bucket", &DataType::Set(set));
+        req.set_context("context");
+        let sent = sent_update(&req);
+        assert_eq!(sent.get_context(), b"context");
+        assert_eq!(sent.get_op().get_set_op().get_adds(), &bytes_of(&["a"])[..]);
+        assert_eq!(sent.get_op().get_set_op().get_removes(), &bytes_of(&["b", "c"])[..]);
+    }
+
+    #[test]
+    fn hll_adds_become_an_hll_op() {
+        let mut hll = Hll::new();
+        hll.add("a");
+        hll.add("b");
+        assert!(hll.is_modified());
+
+        let req = UpdateDataTypeReq::new("hlls", "bucket", &DataType::Hll(hll));
+        assert_eq!(sent_update(&req).get_op().get_hll_op().get_adds(),
+                   &bytes_of(&["a", "b"])[..]);
+    }
+
+    #[test]
+    fn map_changes_become_a_map_op() {
+        let mut map = Map::new();
+        map.flag_mut("enabled").enable();
+        map.flag_mut("disabled").disable();
+        map.register_mut("name").set_value("alice");
+        map.counter_mut("untouched");
+        {
+            let nested = map.map_mut("nested");
+            nested.counter_mut("visits").increment(2);
+            nested.set_mut("tags").add("new");
+            nested.remove("old", MapFieldType::Register);
+        }
+        map.remove("gone", MapFieldType::Set);
+        assert!(map.is_modified());
+        assert_eq!(map.get_register("name").map(Register::get_value), Some(b"alice".to_vec()));
+        assert_eq!(map.get_flag("disabled").map(Flag::get_value), Some(false));
+
+        let mut req = UpdateDataTypeReq::new("maps", "bucket", &DataType::Map(map));
+        req.set_context("context");
+        let sent = sent_update(&req);
+        let op = sent.get_op().get_map_op();
+
+        // unmodified fields are left out
+        assert_eq!(op.get_updates().len(), 4);
+        assert_eq!(op.get_removes(), &[map_field("gone", MapField_MapFieldType::SET)][..]);
+        assert_eq!(map_update(op, "enabled").get_flag_op(), MapUpdate_FlagOp::ENABLE);
+        assert_eq!(map_update(op, "disabled").get_flag_op(), MapUpdate_FlagOp::DISABLE);
+        assert_eq!(map_update(op, "name").get_register_op(), b"alice");
+
+        let nested = map_update(op, "nested");
+        assert_eq!(nested.get_field().get_field_type(), MapField_MapFieldType::MAP);
+        let nested = nested.get_map_op();
+        assert_eq!(nested.get_removes(), &[map_field("old", MapField_MapFieldType::REGISTER)][..]);
+        assert_eq!(map_update(nested, "visits").get_counter_op().get_increment(), 2);
+        assert_eq!(map_update(nested, "tags").get_set_op().get_adds(), &bytes_of(&["new"])[..]);
+    }
+
+    #[test]
+    fn updates_without_context_reject_removes() {
+        let mut set = Set::new();
+        set.add("a");
+        let req = UpdateDataTypeReq::new("sets", "bucket", &DataType::Set(set.clone()));
+        assert!(req.write_to_bytes().is_ok());
+
+        set.remove("b");
+        let req = UpdateDataTypeReq::new("sets", "bucket", &DataType::Set(set));
+        match req.write_to_bytes() {
+            Err(RiakErr::InvalidDataTypeUpdate(_)) => (),
+            other => panic!("expected an invalid data type update, got {:?}", other),
+        };
+
+        // removes nested in maps, and disabling flags, need the context too
+        let mut disabled = Map::new();
+        disabled.map_mut("nested").flag_mut("flag").disable();
+        let mut removed = Map::new();
+        removed.map_mut("nested").remove("field", MapFieldType::Counter);
+        let mut nested_set = Map::new();
+        nested_set.set_mut("set").remove("member");
+        for map in [disabled, removed, nested_set].iter().cloned() {
+            let mut req = UpdateDataTypeReq::new("maps", "bucket", &DataType::Map(map));
+            match req.write_to_bytes() {
+                Err(RiakErr::InvalidDataTypeUpdate(_)) => (),
+                other => panic!("expected an invalid data type update, got {:?}", other),
+            };
+            req.set_context("context");
+            assert!(req.write_to_bytes().is_ok());
+        }
+    }
+
+    fn map_entries() -> Vec<MapEntry> {
+        let mut counter = MapEntry::new();
+        counter.set_field(map_field("visits", MapField_MapFieldType::COUNTER));
+        counter.set_counter_value(7);
+
+        let mut flag = MapEntry::new();
+        flag.set_field(map_field("enabled", MapField_MapFieldType::FLAG));
+        flag.set_flag_value(true);
+
+        let mut set = MapEntry::new();
+        set.set_field(map_field("tags", MapField_MapFieldType::SET));
+        set.set_set_value(RepeatedField::from_vec(bytes_of(&["a", "b"])));
+
+        let mut register = MapEntry::new();
+        register.set_field(map_field("name", MapField_MapFieldType::REGISTER));
+        register.set_register_value(b"bob".to_vec());
+
+        let mut nested = MapEntry::new();
+        nested.set_field(map_field("nested", MapField_MapFieldType::MAP));
+        nested.set_map_value(RepeatedField::from_vec(vec![register]));
+
+        vec![counter, flag, set, nested]
+    }
+
+    fn assert_map_entries(map: &Map) {
+        assert_eq!(map.get_counter("visits").map(Counter::get_value), Some(7));
+        assert_eq!(map.get_flag("enabled").map(Flag::get_value), Some(true));
+        assert_eq!(map.get_set("tags").map(Set::get_value), Some(bytes_of(&["a", "b"])));
+        assert!(!map.is_modified());
+
+        let nested = map.get_map("nested").unwrap();
+        assert_eq!(nested.get_register("name").map(Register::get_value), Some(b"bob".to_vec()));
+        assert!(nested.get_counters().is_empty());
+    }
+
+    #[test]
+    fn dt_fetch_resp_to_fetch_data_type_resp() {
+        let mut value = DtValue::new();
+        value.set_map_value(RepeatedField::from_vec(map_entries()));
+        let mut dt_fetch_resp = DtFetchResp::new();
+        dt_fetch_resp.set_context(b"context".to_vec());
+        dt_fetch_resp.set_field_type(DtFetchResp_DataType::MAP);
+        dt_fetch_resp.set_value(value);
+
+        let resp = FetchDataTypeResp::new_from_dt_fetch_resp(over_the_wire(&dt_fetch_resp));
+        assert_eq!(resp.get_context(), Some(b"context".to_vec()));
+        match resp.get_value() {
+            Some(DataType::Map(map)) => assert_map_entries(&map),
+            other => panic!("expected a map, got {:?}", other),
+        };
+
+        let mut value = DtValue::new();
+        value.set_hll_value(42);
+        let mut dt_fetch_resp = DtFetchResp::new();
+        dt_fetch_resp.set_field_type(DtFetchResp_DataType::HLL);
+        dt_fetch_resp.set_value(value);
+        match FetchDataTypeResp::new_from_dt_fetch_resp(dt_fetch_resp).get_value() {
+            Some(DataType::Hll(hll)) => assert_eq!(hll.get_value(), 42),
+            other => panic!("expected an hll, got {:?}", other),
+        };
+
+        // nothing stored at the key
+        let mut dt_fetch_resp = DtFetchResp::new();
+        dt_fetch_resp.set_field_type(DtFetchResp_DataType::COUNTER);
+        let resp = FetchDataTypeResp::new_from_dt_fetch_resp(dt_fetch_resp);
+        assert!(resp.get_value().is_none());
+        assert_eq!(resp.get_context(), None);
+    }
+
+    #[test]
+    fn dt_update_resp_to_update_data_type_resp() {
+        let mut map = Map::new();
+        map.counter_mut("visits").increment(1);
+        let mut req = UpdateDataTypeReq::new("maps", "bucket", &DataType::Map(map));
+
+        let mut dt_update_resp = DtUpdateResp::new();
+        dt_update_resp.set_key(b"generated".to_vec());
+        dt_update_resp.set_context(b"context".to_vec());
+        dt_update_resp.set_map_value(RepeatedField::from_vec(map_entries()));
+        let dt_update_resp = over_the_wire(&dt_update_resp);
+
+        // the value is only decoded when it was asked for
+        let resp = req.new_response(dt_update_resp.clone());
+        assert_eq!(resp.get_key(), Some(b"generated".to_vec()));
+        assert_eq!(resp.get_context(), Some(b"context".to_vec()));
+        assert!(resp.get_value().is_none());
+
+        req.set_return_body(true);
+        match req.new_response(dt_update_resp).get_value() {
+            Some(DataType::Map(map)) => assert_map_entries(&map),
+            other => panic!("expected a map, got {:?}", other),
+        };
+
+        let mut counter = Counter::new();
+        counter.increment(1);
+        let mut req = UpdateDataTypeReq::new("counters", "bucket", &DataType::Counter(counter));
+        req.set_return_body(true);
+        let mut dt_update_resp = DtUpdateResp::new();
+        dt_update_resp.set_counter_value(10);
+        let resp = req.new_response(dt_update_resp);
+        assert_eq!(resp.get_key(), None);
+        match resp.get_value() {
+            Some(DataType::Counter(counter)) => assert_eq!(counter.get_value(), 10),
+            other => panic!("expected a counter, got {:?}", other),
+        };
+    }
+}
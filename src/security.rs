@@ -0,0 +1,185 @@
+/// Settings for connecting to Riak nodes with security enabled.
+///
+/// Riak only accepts authentication over TLS, so a secure connection first upgrades the plain
+/// connection with `RpbStartTls` and then authenticates with `RpbAuthReq`.
+///
+/// For more information: https://docs.basho.com/riak/kv/latest/using/security/basics/
+
+use errors::RiakErr;
+use native_tls::{Certificate, Identity, TlsConnector};
+use private_traits::SecurityConfigPrivate;
+use protobuf::Message;
+use rpb::riak::RpbAuthReq;
+use std::fmt;
+
+/// `SecurityConfig` represents the credentials and TLS settings used for secure connections.
+///
+/// Certificates and keys are provided PEM encoded, client keys must be in PKCS #8 format.
+///
+/// # Examples
+///
+/// ```
+/// use riak::Client;
+/// use riak::security::SecurityConfig;
+/// use std::fs::File;
+/// use std::io::Read;
+///
+/// let mut ca = Vec::new();
+/// File::open("/etc/riak/ca.pem").unwrap().read_to_end(&mut ca).unwrap();
+///
+/// let mut security = SecurityConfig::new("riakuser", "riakpass");
+/// security.set_domain("riak.example.com");
+/// security.add_root_certificate(ca);
+///
+/// let mut riak = Client::new_secure("10.0.0.2:8087", &security).unwrap();
+/// riak.ping().unwrap();
+/// ```
+#[derive(Clone)]
+pub struct SecurityConfig {
+    user: Vec<u8>,
+    password: Vec<u8>,
+    domain: Option<String>,
+    root_certificates: Vec<Vec<u8>>,
+    client_identity: Option<(Vec<u8>, Vec<u8>)>,
+    accept_invalid_certs: bool,
+    accept_invalid_hostnames: bool,
+}
+
+// The password and the client's private key are redacted, so that clients can be logged safely.
+impl fmt::Debug for SecurityConfig {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let client_identity = self.client_identity
+            .as_ref()
+            .map(|identity| (&identity.0, "<redacted>"));
+        f.debug_struct("SecurityConfig")
+            .field("user", &String::from_utf8_lossy(&self.user))
+            .field("password", &"<redacted>")
+            .field("domain", &self.domain)
+            .field("root_certificates", &self.root_certificates)
+            .field("client_identity", &client_identity)
+            .field("accept_invalid_certs", &self.accept_invalid_certs)
+            .field("accept_invalid_hostnames", &self.accept_invalid_hostnames)
+            .finish()
+    }
+}
+
+impl SecurityConfig {
+    /// constructs a new `SecurityConfig` that authenticates as `user` with `password`
+    pub fn new<T: Into<Vec<u8>>>(user: T, password: T) -> SecurityConfig {
+        SecurityConfig {
+            user: user.into(),
+            password: password.into(),
+            domain: None,
+            root_certificates: Vec::new(),
+            client_identity: None,
+            accept_invalid_certs: false,
+            accept_invalid_hostnames: false,
+        }
+    }
+
+    /// get the user to authenticate as
+    pub fn get_user(&self) -> Vec<u8> {
+        self.user.clone()
+    }
+
+    /// set the user to authenticate as
+    pub fn set_user<T: Into<Vec<u8>>>(&mut self, user: T) {
+        self.user = user.into();
+    }
+
+    /// set the password to authenticate with
+    pub fn set_password<T: Into<Vec<u8>>>(&mut self, password: T) {
+        self.password = password.into();
+    }
+
+    /// get the domain the server certificate is verified against
+    pub fn get_domain(&self) -> Option<String> {
+        self.domain.clone()
+    }
+
+    /// set the domain the server certificate is verified against, when not set the IP address
+    /// of the node is used
+    pub fn set_domain<T: Into<String>>(&mut self, domain: T) {
+        self.domain = Some(domain.into());
+    }
+
+    /// get the PEM encoded certificates trusted in addition to the system's
+    pub fn get_root_certificates(&self) -> Vec<Vec<u8>> {
+        self.root_certificates.clone()
+    }
+
+    /// add a PEM encoded certificate to trust in addition to the system's, such as the CA that
+    /// signed the Riak node's certificate
+    pub fn add_root_certificate<T: Into<Vec<u8>>>(&mut self, certificate: T) {
+        self.root_certificates.push(certificate.into());
+    }
+
+    /// set the PEM encoded certificate and PKCS #8 key presented to the server, for Riak users
+    /// that authenticate with the "certificate" security source
+    pub fn set_client_identity<T: Into<Vec<u8>>>(&mut self, certificate: T, key: T) {
+        self.client_identity = Some((certificate.into(), key.into()));
+    }
+
+    /// get whether or not invalid server certificates are accepted
+    pub fn get_accept_invalid_certs(&self) -> bool {
+        self.accept_invalid_certs
+    }
+
+    /// set whether or not invalid server certificates are accepted, this should only be used
+    /// for testing as it makes the connection vulnerable to interception
+    pub fn set_accept_invalid_certs(&mut self, accept_invalid_certs: bool) {
+        self.accept_invalid_certs = accept_invalid_certs;
+    }
+
+    /// get whether or not server certificates that don't match the domain are accepted
+    pub fn get_accept_invalid_hostnames(&self) -> bool {
+        self.accept_invalid_hostnames
+    }
+
+    /// set whether or not server certificates that don't match the domain are accepted
+    pub fn set_accept_invalid_hostnames(&mut self, accept_invalid_hostnames: bool) {
+        self.accept_invalid_hostnames = accept_invalid_hostnames;
+    }
+}
+
+impl SecurityConfigPrivate for SecurityConfig {
+    // Render the RpbAuthReq sent once the connection has been upgraded.
+    fn auth_req_bytes(&self) -> Result<Vec<u8>, RiakErr> {
+        let mut req = RpbAuthReq::new();
+        req.set_user(self.user.clone());
+        req.set_password(self.password.clone());
+        match req.write_to_bytes() {
+            Ok(bytes) => Ok(bytes),
+            Err(error) => Err(RiakErr::ProtobufError(error)),
+        }
+    }
+
+    // Build the TLS connector described by this configuration.
+    fn connector(&self) -> Result<TlsConnector, RiakErr> {
+        let mut builder = TlsConnector::builder();
+
+        for pem in self.root_certificates.iter() {
+            let certificate = match Certificate::from_pem(pem) {
+                Ok(certificate) => certificate,
+                Err(error) => return Err(RiakErr::TlsError(error.to_string())),
+            };
+            builder.add_root_certificate(certificate);
+        }
+
+        if let Some((ref certificate, ref key)) = self.client_identity {
+            let identity = match Identity::from_pkcs8(certificate, key) {
+                Ok(identity) => identity,
+                Err(error) => return Err(RiakErr::TlsError(error.to_string())),
+            };
+            builder.identity(identity);
+        }
+
+        builder.danger_accept_invalid_certs(self.accept_invalid_certs);
+        builder.danger_accept_invalid_hostnames(self.accept_invalid_hostnames);
+
+        match builder.build() {
+            Ok(connector) => Ok(connector),
+            Err(error) => Err(RiakErr::TlsError(error.to_string())),
+        }
+    }
+}
@@ -1,33 +1,82 @@
 use Client;
 use connection::RiakConn;
+use transport::{TcpTransport, Transport};
 use errors::RiakErr;
+use index::{IndexQuery, IndexResults};
+use mapreduce::MapReduceJob;
+use object::FetchObjectResp;
+use private_traits::IndexQueryPrivate;
 use protobuf::{Message, parse_from_bytes};
 use rpb::codes;
-use rpb::riak_kv::{RpbListBucketsReq, RpbListBucketsResp, RpbListKeysReq, RpbListKeysResp};
+use rpb::riak_kv::{RpbCSBucketReq, RpbCSBucketResp, RpbListBucketsReq, RpbListBucketsResp,
+                   RpbListKeysReq, RpbListKeysResp, RpbMapRedResp};
+use rpb::utils::{index_response_code, index_response_to_index_results,
+                 rpb_index_object_to_key_and_fetch_object_resp, RpbGenerator};
+use std::collections::VecDeque;
+
+// The timeout (in milliseconds) sent to Riak for a request, the one set on the stream or else the
+// timeout (in seconds) of its connection.
+fn request_timeout<C: Transport>(timeout: Option<u32>, connection: &RiakConn<C>) -> u32 {
+    match timeout {
+        Some(timeout) => timeout,
+        None => connection.get_timeout().saturating_mul(1000),
+    }
+}
 
 /// `BucketStream` represents a list of bucket names in Riak
+///
+/// The stream is an `Iterator` over groups of bucket names as Riak sends them, use `items()` to
+/// iterate over the bucket names one at a time instead.
+///
+/// Each stream uses its own connection, which is closed if the stream is dropped before it has
+/// been read to the end. The stream ends at the first error, as the connection can not pick up a
+/// response part way through, start a new stream to try again.
 #[derive(Debug)]
-pub struct BucketStream {
-    connection: RiakConn,
+pub struct BucketStream<C: Transport = TcpTransport> {
+    connection: RiakConn<C>,
+    bucket_type: Option<Vec<u8>>,
+    timeout: Option<u32>,
     done: bool,
     first_request_made: bool,
 }
 
-impl BucketStream {
+impl<C: Transport> BucketStream<C> {
     /// constructs a new `BucketStream`
-    pub fn new(client: &mut Client) -> Result<BucketStream, RiakErr> {
-        let connection = match RiakConn::new(client.connection.peer_addr,
-                                             client.connection.timeout) {
+    pub fn new(client: &mut Client<C>) -> Result<BucketStream<C>, RiakErr> {
+        let connection = match client.connection.duplicate() {
             Ok(connection) => connection,
             Err(error) => return Err(error),
         };
         Ok(BucketStream {
             connection: connection,
+            bucket_type: None,
+            timeout: None,
             done: false,
             first_request_made: false,
         })
     }
 
+    /// get the bucket type that buckets are listed from
+    pub fn get_bucket_type(&self) -> Option<Vec<u8>> {
+        self.bucket_type.clone()
+    }
+
+    /// set the bucket type to list buckets from, this must be set before the stream is read
+    pub fn set_bucket_type<T: Into<Vec<u8>>>(&mut self, bucket_type: T) {
+        self.bucket_type = Some(bucket_type.into());
+    }
+
+    /// get the timeout sent to Riak for this request
+    pub fn get_timeout(&self) -> Option<u32> {
+        self.timeout
+    }
+
+    /// set the timeout (in milliseconds) sent to Riak for this request instead of the one the
+    /// `Client` was created with, this must be set before the stream is read
+    pub fn set_timeout(&mut self, timeout: u32) {
+        self.timeout = Some(timeout);
+    }
+
     /// return a list of every bucket from the stream
     pub fn all(&mut self) -> Result<Vec<Vec<u8>>, RiakErr> {
         let mut buckets: Vec<Vec<u8>> = Vec::new();
@@ -48,129 +97,135 @@ impl BucketStream {
         Ok(buckets)
     }
 
-    /// return the next group of buckets from the stream
-    pub fn next(&mut self) -> Option<Result<Vec<Vec<u8>>, RiakErr>> {
+    /// return an iterator over the bucket names from the stream one at a time
+    pub fn items(self) -> StreamItems<BucketStream<C>> {
+        StreamItems::new(self)
+    }
+}
+
+impl<C: Transport> Iterator for BucketStream<C> {
+    type Item = Result<Vec<Vec<u8>>, RiakErr>;
+
+    fn next(&mut self) -> Option<Result<Vec<Vec<u8>>, RiakErr>> {
         if self.done {
             return None;
         }
 
-        if self.first_request_made {
-            // get the next response from Riak
-            let response = match self.connection.receive(codes::RpbListBucketsResp) {
-                Ok(response) => response,
-                Err(error) => return Some(Err(error)),
-            };
-
-            // parse the response
-            let mut rpb_resp = match parse_from_bytes::<RpbListBucketsResp>(&response) {
-                Ok(parsed) => parsed,
-                Err(error) => {
-                    match self.connection.reconnect() {
-                        Ok(()) => (),
-                        Err(error) => {
-                            debug!("failure during reconnect: {:?}", error);
-                            return Some(Err(error));
-                        }
-                    };
-                    return Some(Err(RiakErr::ProtobufError(error)));
-                }
-            };
-
-            // retrieve the buckets from the rpb response
-            let resp_buckets = rpb_resp.take_buckets();
-
-            // get the buckets and convert them into a Vec<Vec<u8>>
-            let mut buckets: Vec<Vec<u8>> = Vec::new();
-            for bucket in resp_buckets.into_iter() {
-                buckets.push(bucket);
-            }
-
-            self.done = rpb_resp.get_done();
-
-            Some(Ok(buckets))
-        } else {
-            // build the request
+        // send the request if it hasn't been sent yet
+        if !self.first_request_made {
             let mut request = RpbListBucketsReq::new();
             request.set_stream(true);
-            request.set_timeout(self.connection.timeout);
+            request.set_timeout(request_timeout(self.timeout, &self.connection));
+            if let Some(ref bucket_type) = self.bucket_type {
+                request.set_field_type(bucket_type.clone());
+            }
+
             let bytes = match request.write_to_bytes() {
                 Ok(bytes) => bytes,
-                Err(error) => return Some(Err(RiakErr::ProtobufError(error))),
-            };
-
-            // send the request and get the response
-            let response = match self.connection
-                .exchange(codes::RpbListBucketsReq, codes::RpbListBucketsResp, &bytes) {
-                Ok(response) => response,
                 Err(error) => {
-                    match self.connection.reconnect() {
-                        Ok(()) => (),
-                        Err(error) => {
-                            debug!("failure during reconnect: {:?}", error);
-                            return Some(Err(error));
-                        }
-                    };
-                    return Some(Err(error));
+                    self.done = true;
+                    return Some(Err(RiakErr::ProtobufError(error)));
                 }
             };
 
-            // parse the response
-            let mut rpb_resp = match parse_from_bytes::<RpbListBucketsResp>(&response) {
-                Ok(parsed) => parsed,
+            match self.connection.send(codes::RpbListBucketsReq, &bytes) {
+                Ok(()) => (),
                 Err(error) => {
-                    match self.connection.reconnect() {
-                        Ok(()) => (),
-                        Err(error) => {
-                            debug!("failure during reconnect: {:?}", error);
-                            return Some(Err(error));
-                        }
-                    };
-                    return Some(Err(RiakErr::ProtobufError(error)));
+                    self.done = true;
+                    return Some(Err(error));
                 }
             };
 
-            // retrieve the buckets from the first response
-            let resp_buckets = rpb_resp.take_buckets();
+            self.first_request_made = true;
+        }
 
-            // store all found buckets
-            let mut buckets: Vec<Vec<u8>> = Vec::new();
-            for bucket in resp_buckets.into_iter() {
-                buckets.push(bucket);
+        // get the next response from Riak
+        let response = match self.connection.receive(codes::RpbListBucketsResp) {
+            Ok(response) => response,
+            Err(error) => {
+                self.done = true;
+                return Some(Err(error));
             }
+        };
 
-            self.first_request_made = true;
-            self.done = rpb_resp.get_done();
+        // parse the response
+        let mut rpb_resp = match parse_from_bytes::<RpbListBucketsResp>(&response) {
+            Ok(parsed) => parsed,
+            Err(error) => {
+                self.done = true;
+                return Some(Err(RiakErr::ProtobufError(error)));
+            }
+        };
 
-            Some(Ok(buckets))
-        }
+        self.done = rpb_resp.get_done();
+
+        Some(Ok(rpb_resp.take_buckets().into_vec()))
+    }
+}
+
+impl<C: Transport> Drop for BucketStream<C> {
+    fn drop(&mut self) {
+        close_unfinished(&mut self.connection, self.first_request_made, self.done);
     }
 }
 
 /// `KeyStream` represents a list of keys in a Riak bucket
+///
+/// The stream is an `Iterator` over groups of keys as Riak sends them, use `items()` to iterate
+/// over the keys one at a time instead.
+///
+/// Each stream uses its own connection, which is closed if the stream is dropped before it has
+/// been read to the end. The stream ends at the first error, as the connection can not pick up a
+/// response part way through, start a new stream to try again.
 #[derive(Debug)]
-pub struct KeyStream {
+pub struct KeyStream<C: Transport = TcpTransport> {
     bucket: Vec<u8>,
-    connection: RiakConn,
+    bucket_type: Option<Vec<u8>>,
+    timeout: Option<u32>,
+    connection: RiakConn<C>,
     done: bool,
     first_request_made: bool,
 }
 
-impl KeyStream {
+impl<C: Transport> KeyStream<C> {
     /// constructs a new `KeyStream`
-    pub fn new(client: &mut Client, bucket: Vec<u8>) -> Result<KeyStream, RiakErr> {
-        let connection = match RiakConn::new(client.connection.peer_addr,
-                                             client.connection.timeout) {
+    pub fn new(client: &mut Client<C>, bucket: Vec<u8>) -> Result<KeyStream<C>, RiakErr> {
+        let connection = match client.connection.duplicate() {
             Ok(connection) => connection,
             Err(error) => return Err(error),
         };
         Ok(KeyStream {
             bucket: bucket,
+            bucket_type: None,
+            timeout: None,
             connection: connection,
             done: false,
             first_request_made: false,
         })
     }
 
+    /// get the bucket type of the bucket the keys are listed from
+    pub fn get_bucket_type(&self) -> Option<Vec<u8>> {
+        self.bucket_type.clone()
+    }
+
+    /// set the bucket type of the bucket to list keys from, this must be set before the stream
+    /// is read
+    pub fn set_bucket_type<T: Into<Vec<u8>>>(&mut self, bucket_type: T) {
+        self.bucket_type = Some(bucket_type.into());
+    }
+
+    /// get the timeout sent to Riak for this request
+    pub fn get_timeout(&self) -> Option<u32> {
+        self.timeout
+    }
+
+    /// set the timeout (in milliseconds) sent to Riak for this request instead of the one the
+    /// `Client` was created with, this must be set before the stream is read
+    pub fn set_timeout(&mut self, timeout: u32) {
+        self.timeout = Some(timeout);
+    }
+
     /// return a list of all the keys from the stream
     pub fn all(&mut self) -> Result<Vec<Vec<u8>>, RiakErr> {
         let mut keys: Vec<Vec<u8>> = Vec::new();
@@ -191,102 +246,609 @@ impl KeyStream {
         Ok(keys)
     }
 
-    /// return the next group of keys from the stream
-    pub fn next(&mut self) -> Option<Result<Vec<Vec<u8>>, RiakErr>> {
+    /// return an iterator over the keys from the stream one at a time
+    pub fn items(self) -> StreamItems<KeyStream<C>> {
+        StreamItems::new(self)
+    }
+}
+
+impl<C: Transport> Iterator for KeyStream<C> {
+    type Item = Result<Vec<Vec<u8>>, RiakErr>;
+
+    fn next(&mut self) -> Option<Result<Vec<Vec<u8>>, RiakErr>> {
         if self.done {
             return None;
         }
 
-        if self.first_request_made {
-            // get the next response from Riak
-            let response = match self.connection.receive(codes::RpbListKeysResp) {
-                Ok(response) => response,
-                Err(error) => return Some(Err(error)),
-            };
+        // send the request if it hasn't been sent yet
+        if !self.first_request_made {
+            let mut request = RpbListKeysReq::new();
+            request.set_bucket(self.bucket.clone());
+            request.set_timeout(request_timeout(self.timeout, &self.connection));
+            if let Some(ref bucket_type) = self.bucket_type {
+                request.set_field_type(bucket_type.clone());
+            }
 
-            // parse the response
-            let mut rpb_resp = match parse_from_bytes::<RpbListKeysResp>(&response) {
-                Ok(parsed) => parsed,
+            let bytes = match request.write_to_bytes() {
+                Ok(bytes) => bytes,
                 Err(error) => {
-                    match self.connection.reconnect() {
-                        Ok(()) => (),
-                        Err(error) => {
-                            debug!("failure during reconnect: {:?}", error);
-                            return Some(Err(error));
-                        }
-                    };
+                    self.done = true;
                     return Some(Err(RiakErr::ProtobufError(error)));
                 }
             };
 
-            // retrieve the keys from the response
-            let resp_keys = rpb_resp.take_keys();
+            match self.connection.send(codes::RpbListKeysReq, &bytes) {
+                Ok(()) => (),
+                Err(error) => {
+                    self.done = true;
+                    return Some(Err(error));
+                }
+            };
+
+            self.first_request_made = true;
+        }
 
-            // get the keys and convert them into a Vec<Vec<u8>>
-            let mut keys: Vec<Vec<u8>> = Vec::new();
-            for key in resp_keys.into_iter() {
-                keys.push(key);
+        // get the next response from Riak
+        let response = match self.connection.receive(codes::RpbListKeysResp) {
+            Ok(response) => response,
+            Err(error) => {
+                self.done = true;
+                return Some(Err(error));
             }
+        };
 
-            self.done = rpb_resp.get_done();
+        // parse the response
+        let mut rpb_resp = match parse_from_bytes::<RpbListKeysResp>(&response) {
+            Ok(parsed) => parsed,
+            Err(error) => {
+                self.done = true;
+                return Some(Err(RiakErr::ProtobufError(error)));
+            }
+        };
 
-            Some(Ok(keys))
-        } else {
-            // build the request
-            let mut request = RpbListKeysReq::new();
+        self.done = rpb_resp.get_done();
 
-            request.set_bucket(self.bucket.clone());
-            request.set_timeout(self.connection.timeout);
+        Some(Ok(rpb_resp.take_keys().into_vec()))
+    }
+}
 
-            let bytes = match request.write_to_bytes() {
+impl<C: Transport> Drop for KeyStream<C> {
+    fn drop(&mut self) {
+        close_unfinished(&mut self.connection, self.first_request_made, self.done);
+    }
+}
+
+/// `StreamItems` flattens a `BucketStream` or `KeyStream` so that it yields a single bucket name
+/// or key at a time
+///
+/// # Examples
+///
+/// ```
+/// use riak::Client;
+///
+/// let mut riak = Client::new("10.0.0.2:8087").unwrap();
+///
+/// for key in riak.stream_keys("testbucket").unwrap().items().take(10) {
+///     println!("found key {:?}", key.unwrap());
+/// }
+/// ```
+#[derive(Debug)]
+pub struct StreamItems<S> {
+    stream: S,
+    buffered: VecDeque<Vec<u8>>,
+}
+
+impl<S> StreamItems<S>
+    where S: Iterator<Item = Result<Vec<Vec<u8>>, RiakErr>>
+{
+    /// constructs a new `StreamItems` from a stream of groups of items
+    pub fn new(stream: S) -> StreamItems<S> {
+        StreamItems {
+            stream: stream,
+            buffered: VecDeque::new(),
+        }
+    }
+
+    /// consumes the `StreamItems`, returning the underlying stream
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+}
+
+impl<S> Iterator for StreamItems<S>
+    where S: Iterator<Item = Result<Vec<Vec<u8>>, RiakErr>>
+{
+    type Item = Result<Vec<u8>, RiakErr>;
+
+    fn next(&mut self) -> Option<Result<Vec<u8>, RiakErr>> {
+        // Riak may send empty groups, so keep reading until an item turns up
+        while self.buffered.is_empty() {
+            match self.stream.next() {
+                Some(Ok(items)) => self.buffered.extend(items),
+                Some(Err(error)) => return Some(Err(error)),
+                None => return None,
+            };
+        }
+        self.buffered.pop_front().map(Ok)
+    }
+}
+
+/// `IndexStream` represents the results of a secondary index query streamed from Riak
+///
+/// Each stream uses its own connection, which is closed if the stream is dropped before it has
+/// been read to the end. The stream ends at the first error, as the connection can not pick up a
+/// response part way through, start a new stream to try again.
+#[derive(Debug)]
+pub struct IndexStream<C: Transport = TcpTransport> {
+    query: IndexQuery,
+    connection: RiakConn<C>,
+    done: bool,
+    first_request_made: bool,
+}
+
+impl<C: Transport> IndexStream<C> {
+    /// constructs a new `IndexStream`
+    pub fn new(client: &mut Client<C>, query: &IndexQuery) -> Result<IndexStream<C>, RiakErr> {
+        let connection = match client.connection.duplicate() {
+            Ok(connection) => connection,
+            Err(error) => return Err(error),
+        };
+        Ok(IndexStream {
+            query: query.clone(),
+            connection: connection,
+            done: false,
+            first_request_made: false,
+        })
+    }
+
+    /// return all of the results from the stream merged together
+    pub fn all(&mut self) -> Result<IndexResults, RiakErr> {
+        let mut keys: Vec<Vec<u8>> = Vec::new();
+        let mut terms: Vec<(Vec<u8>, Vec<u8>)> = Vec::new();
+        let mut objects = Vec::new();
+        let mut continuation: Option<Vec<u8>> = None;
+
+        loop {
+            let results = match self.next() {
+                Some(result) => {
+                    match result {
+                        Ok(results) => results,
+                        Err(error) => return Err(error),
+                    }
+                }
+                None => break,
+            };
+            keys.extend(results.get_keys());
+            terms.extend(results.get_terms());
+            objects.extend(results.get_objects());
+            if let Some(next_continuation) = results.get_continuation() {
+                continuation = Some(next_continuation);
+            }
+        }
+
+        let mut merged = IndexResults::new();
+        merged.set_keys(keys);
+        merged.set_terms(terms);
+        merged.set_objects(objects);
+        if let Some(continuation) = continuation {
+            merged.set_continuation(continuation);
+        }
+
+        Ok(merged)
+    }
+}
+
+impl<C: Transport> Iterator for IndexStream<C> {
+    type Item = Result<IndexResults, RiakErr>;
+
+    fn next(&mut self) -> Option<Result<IndexResults, RiakErr>> {
+        if self.done {
+            return None;
+        }
+
+        // send the request if it hasn't been sent yet
+        if !self.first_request_made {
+            let bytes = match self.query.write_to_bytes(true) {
+                Ok(bytes) => bytes,
+                Err(error) => {
+                    self.done = true;
+                    return Some(Err(error));
+                }
+            };
+
+            match self.connection.send(codes::RpbIndexReq, &bytes) {
+                Ok(()) => (),
+                Err(error) => {
+                    self.done = true;
+                    return Some(Err(error));
+                }
+            };
+
+            self.first_request_made = true;
+        }
+
+        // get the next response from Riak
+        let return_body = self.query.get_return_body().unwrap_or(false);
+        let response = match self.connection.receive(index_response_code(return_body)) {
+            Ok(response) => response,
+            Err(error) => {
+                self.done = true;
+                return Some(Err(error));
+            }
+        };
+
+        // parse the response
+        let (results, done) = match index_response_to_index_results(&response, return_body) {
+            Ok(parsed) => parsed,
+            Err(error) => {
+                self.done = true;
+                return Some(Err(error));
+            }
+        };
+
+        self.done = done;
+
+        Some(Ok(results))
+    }
+}
+
+impl<C: Transport> Drop for IndexStream<C> {
+    fn drop(&mut self) {
+        close_unfinished(&mut self.connection, self.first_request_made, self.done);
+    }
+}
+
+// Defaults
+static DEFAULT_FOLD_PAGE_SIZE: u32 = 1000;
+
+/// `ObjectFold` represents the objects of a range of keys in a bucket, read from Riak a page at
+/// a time as `(key, object)` pairs in key order
+///
+/// Each page is requested once the objects of the page before it have been read, so a fold can
+/// be stopped part way through and restarted later from `get_continuation()`.
+///
+/// Each fold uses its own connection, which is closed if the fold is dropped part way through a
+/// page.
+#[derive(Debug)]
+pub struct ObjectFold<C: Transport = TcpTransport> {
+    bucket: Vec<u8>,
+    start_key: Vec<u8>,
+    end_key: Vec<u8>,
+    bucket_type: Option<Vec<u8>>,
+    start_incl: Option<bool>,
+    end_incl: Option<bool>,
+    page_size: u32,
+    timeout: Option<u32>,
+    continuation: Option<Vec<u8>>,
+    next_continuation: Option<Vec<u8>>,
+    buffered: VecDeque<(Vec<u8>, FetchObjectResp)>,
+    connection: RiakConn<C>,
+    done: bool,
+    page_requested: bool,
+}
+
+impl<C: Transport> ObjectFold<C> {
+    /// constructs a new `ObjectFold`
+    pub fn new(client: &mut Client<C>,
+               bucket: Vec<u8>,
+               start_key: Vec<u8>,
+               end_key: Vec<u8>)
+               -> Result<ObjectFold<C>, RiakErr> {
+        let connection = match client.connection.duplicate() {
+            Ok(connection) => connection,
+            Err(error) => return Err(error),
+        };
+        Ok(ObjectFold {
+            bucket: bucket,
+            start_key: start_key,
+            end_key: end_key,
+            bucket_type: None,
+            start_incl: None,
+            end_incl: None,
+            page_size: DEFAULT_FOLD_PAGE_SIZE,
+            timeout: None,
+            continuation: None,
+            next_continuation: None,
+            buffered: VecDeque::new(),
+            connection: connection,
+            done: false,
+            page_requested: false,
+        })
+    }
+
+    /// get the bucket type of the bucket the objects are read from
+    pub fn get_bucket_type(&self) -> Option<Vec<u8>> {
+        self.bucket_type.clone()
+    }
+
+    /// set the bucket type of the bucket to read objects from, this must be set before the fold
+    /// is read
+    pub fn set_bucket_type<T: Into<Vec<u8>>>(&mut self, bucket_type: T) {
+        self.bucket_type = Some(bucket_type.into());
+    }
+
+    /// get whether the start key is included in the range
+    pub fn get_start_incl(&self) -> Option<bool> {
+        self.start_incl
+    }
+
+    /// set whether the start key is included in the range, Riak includes it by default
+    pub fn set_start_incl(&mut self, start_incl: bool) {
+        self.start_incl = Some(start_incl);
+    }
+
+    /// get whether the end key is included in the range
+    pub fn get_end_incl(&self) -> Option<bool> {
+        self.end_incl
+    }
+
+    /// set whether the end key is included in the range, Riak excludes it by default
+    pub fn set_end_incl(&mut self, end_incl: bool) {
+        self.end_incl = Some(end_incl);
+    }
+
+    /// get how many objects are requested from Riak at a time
+    pub fn get_page_size(&self) -> u32 {
+        self.page_size
+    }
+
+    /// set how many objects are requested from Riak at a time
+    pub fn set_page_size(&mut self, page_size: u32) {
+        self.page_size = page_size;
+    }
+
+    /// get the timeout sent to Riak for each page
+    pub fn get_timeout(&self) -> Option<u32> {
+        self.timeout
+    }
+
+    /// set the timeout (in milliseconds) sent to Riak for each page instead of the one the
+    /// `Client` was created with
+    pub fn set_timeout(&mut self, timeout: u32) {
+        self.timeout = Some(timeout);
+    }
+
+    /// get the continuation the page currently being read was requested with, a fold restarted
+    /// from it reads that page again, including any objects of it already returned
+    pub fn get_continuation(&self) -> Option<Vec<u8>> {
+        self.continuation.clone()
+    }
+
+    /// set the continuation to restart a fold from, this must be set before the fold is read
+    pub fn set_continuation<T: Into<Vec<u8>>>(&mut self, continuation: T) {
+        self.continuation = Some(continuation.into());
+    }
+
+    /// return every remaining object from the fold
+    pub fn all(&mut self) -> Result<Vec<(Vec<u8>, FetchObjectResp)>, RiakErr> {
+        let mut objects: Vec<(Vec<u8>, FetchObjectResp)> = Vec::new();
+        for result in self {
+            match result {
+                Ok(object) => objects.push(object),
+                Err(error) => return Err(error),
+            };
+        }
+        Ok(objects)
+    }
+
+    // Sends the request for the next page, starting from the current continuation.
+    fn request_page(&mut self) -> Result<(), RiakErr> {
+        let mut request = RpbCSBucketReq::new();
+        request.set_bucket(self.bucket.clone());
+        request.set_start_key(self.start_key.clone());
+        request.set_end_key(self.end_key.clone());
+        request.set_max_results(self.page_size);
+        request.set_timeout(request_timeout(self.timeout, &self.connection));
+        if let Some(ref bucket_type) = self.bucket_type {
+            request.set_field_type(bucket_type.clone());
+        }
+        if let Some(start_incl) = self.start_incl {
+            request.set_start_incl(start_incl);
+        }
+        if let Some(end_incl) = self.end_incl {
+            request.set_end_incl(end_incl);
+        }
+        if let Some(ref continuation) = self.continuation {
+            request.set_continuation(continuation.clone());
+        }
+
+        let bytes = match request.write_to_bytes() {
+            Ok(bytes) => bytes,
+            Err(error) => return Err(RiakErr::ProtobufError(error)),
+        };
+
+        self.connection.send(codes::RpbCSBucketReq, &bytes)
+    }
+
+    // Reads the next response of the page being read, buffering its objects.
+    fn read_response(&mut self) -> Result<(), RiakErr> {
+        let response = match self.connection.receive(codes::RpbCSBucketResp) {
+            Ok(response) => response,
+            Err(error) => return Err(error),
+        };
+
+        let mut rpb_resp = match parse_from_bytes::<RpbCSBucketResp>(&response) {
+            Ok(parsed) => parsed,
+            Err(error) => return Err(RiakErr::ProtobufError(error)),
+        };
+
+        for mut rpb_index_object in rpb_resp.take_objects().into_iter() {
+            let object = rpb_index_object_to_key_and_fetch_object_resp(&mut rpb_index_object);
+            self.buffered.push_back(object);
+        }
+        if rpb_resp.has_continuation() {
+            self.next_continuation = Some(rpb_resp.take_continuation());
+        }
+
+        // the page is over, and so is the fold unless Riak says where the next page starts
+        if rpb_resp.get_done() {
+            self.page_requested = false;
+            if self.next_continuation.is_none() {
+                self.done = true;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<C: Transport> Iterator for ObjectFold<C> {
+    type Item = Result<(Vec<u8>, FetchObjectResp), RiakErr>;
+
+    fn next(&mut self) -> Option<Result<(Vec<u8>, FetchObjectResp), RiakErr>> {
+        // Riak may send responses without any objects, so keep reading until one turns up
+        while self.buffered.is_empty() {
+            if self.done {
+                return None;
+            }
+
+            // request the next page once every object of the one before it has been read
+            if !self.page_requested {
+                if let Some(continuation) = self.next_continuation.take() {
+                    self.continuation = Some(continuation);
+                }
+
+                match self.request_page() {
+                    Ok(()) => (),
+                    Err(error) => {
+                        self.done = true;
+                        return Some(Err(error));
+                    }
+                };
+
+                self.page_requested = true;
+            }
+
+            match self.read_response() {
+                Ok(()) => (),
+                Err(error) => {
+                    self.done = true;
+                    return Some(Err(error));
+                }
+            };
+        }
+
+        self.buffered.pop_front().map(Ok)
+    }
+}
+
+impl<C: Transport> Drop for ObjectFold<C> {
+    fn drop(&mut self) {
+        close_unfinished(&mut self.connection, self.page_requested, false);
+    }
+}
+
+// Closes the dedicated connection of a stream dropped part way through, so Riak stops sending
+// the rest of the responses.
+fn close_unfinished<C: Transport>(connection: &mut RiakConn<C>,
+                                  first_request_made: bool,
+                                  done: bool) {
+    if first_request_made && !done {
+        match connection.shutdown() {
+            Ok(()) => (),
+            Err(error) => debug!("failure closing unfinished stream: {:?}", error),
+        };
+    }
+}
+
+/// `MapReduceStream` represents the results of a MapReduce job streamed from Riak, as
+/// `(phase, response)` chunks where the response is JSON encoded
+#[derive(Debug)]
+pub struct MapReduceStream<C: Transport = TcpTransport> {
+    job: MapReduceJob,
+    connection: RiakConn<C>,
+    done: bool,
+    first_request_made: bool,
+}
+
+impl<C: Transport> MapReduceStream<C> {
+    /// constructs a new `MapReduceStream`
+    pub fn new(client: &mut Client<C>, job: &MapReduceJob) -> Result<MapReduceStream<C>, RiakErr> {
+        let connection = match client.connection.duplicate() {
+            Ok(connection) => connection,
+            Err(error) => return Err(error),
+        };
+        Ok(MapReduceStream {
+            job: job.clone(),
+            connection: connection,
+            done: false,
+            first_request_made: false,
+        })
+    }
+
+    /// return all of the remaining chunks from the stream
+    pub fn all(&mut self) -> Result<Vec<(u32, Vec<u8>)>, RiakErr> {
+        let mut chunks: Vec<(u32, Vec<u8>)> = Vec::new();
+        for result in self {
+            match result {
+                Ok(chunk) => chunks.push(chunk),
+                Err(error) => return Err(error),
+            };
+        }
+        Ok(chunks)
+    }
+}
+
+impl<C: Transport> Iterator for MapReduceStream<C> {
+    type Item = Result<(u32, Vec<u8>), RiakErr>;
+
+    fn next(&mut self) -> Option<Result<(u32, Vec<u8>), RiakErr>> {
+        // send the job if it hasn't been sent yet
+        if !self.done && !self.first_request_made {
+            let bytes = match self.job.write_to_bytes() {
                 Ok(bytes) => bytes,
-                Err(error) => return Some(Err(RiakErr::ProtobufError(error))),
+                Err(error) => {
+                    self.done = true;
+                    return Some(Err(error));
+                }
             };
 
-            // send the request and get the response
-            let response = match self.connection
-                .exchange(codes::RpbListKeysReq, codes::RpbListKeysResp, &bytes) {
+            match self.connection.send(codes::RpbMapRedReq, &bytes) {
+                Ok(()) => (),
+                Err(error) => {
+                    self.done = true;
+                    return Some(Err(error));
+                }
+            };
+
+            self.first_request_made = true;
+        }
+
+        // responses without any results (such as the final one) are skipped
+        while !self.done {
+            let response = match self.connection.receive(codes::RpbMapRedResp) {
                 Ok(response) => response,
                 Err(error) => {
-                    match self.connection.reconnect() {
-                        Ok(()) => (),
-                        Err(error) => {
-                            debug!("failure during reconnect: {:?}", error);
-                            return Some(Err(error));
-                        }
-                    };
+                    self.done = true;
                     return Some(Err(error));
                 }
             };
 
-            // parse the response
-            let mut rpb_resp = match parse_from_bytes::<RpbListKeysResp>(&response) {
-                Ok(parsed) => parsed,
+            let mut mapred_resp = match parse_from_bytes::<RpbMapRedResp>(&response) {
+                Ok(mapred_resp) => mapred_resp,
                 Err(error) => {
+                    self.done = true;
                     match self.connection.reconnect() {
                         Ok(()) => (),
-                        Err(error) => {
-                            debug!("failure during reconnect: {:?}", error);
-                            return Some(Err(error));
-                        }
+                        Err(error) => debug!("failure during reconnect: {:?}", error),
                     };
                     return Some(Err(RiakErr::ProtobufError(error)));
                 }
             };
 
-            // retrieve the keys from the first response
-            let resp_keys = rpb_resp.take_keys();
+            self.done = mapred_resp.get_done();
 
-            // store all found keys
-            let mut keys: Vec<Vec<u8>> = Vec::new();
-            for key in resp_keys.into_iter() {
-                keys.push(key);
+            if mapred_resp.has_response() {
+                return Some(Ok((mapred_resp.get_phase(), mapred_resp.take_response())));
             }
+        }
 
-            self.first_request_made = true;
-            self.done = rpb_resp.get_done();
+        None
+    }
+}
 
-            Some(Ok(keys))
-        }
+impl<C: Transport> Drop for MapReduceStream<C> {
+    fn drop(&mut self) {
+        close_unfinished(&mut self.connection, self.first_request_made, self.done);
     }
 }
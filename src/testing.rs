@@ -0,0 +1,1080 @@
+/// An in-process stand-in for a Riak node, for testing code that uses this crate without a Riak
+/// cluster.
+///
+/// `MockServer` listens on localhost and speaks Riak's Protocol Buffers API, keeping everything
+/// in memory. It supports:
+///
+/// * pings and server info
+/// * storing, fetching and deleting objects, with vclocks, siblings in buckets with
+///   "allow_mult" set and the "if_not_modified" and "if_none_match" preconditions
+/// * bucket and bucket type properties
+/// * listing buckets and keys
+/// * preflists
+/// * coverage plans, with a part of the plan replaced when asked
+/// * secondary index queries on the "$bucket" and "$key" indexes, with cover contexts and
+///   pagination
+/// * folding over the objects of a range of keys, with pagination
+/// * legacy counters, in buckets with "allow_mult" set
+/// * storing Yokozuna schemas and indexes (searching them is not supported)
+///
+/// As in Riak, buckets in the "default" bucket type have "allow_mult" disabled and buckets in
+/// any other bucket type have it enabled. Other requests are answered with an error.
+///
+/// Failures can be scheduled per request message code with `MockServer::inject_fault`, to test
+/// how code behaves when responses are truncated, have the wrong message code, are errors, are
+/// slow or when the connection is closed partway through a stream.
+///
+/// This module requires the "testing" feature.
+///
+/// # Examples
+///
+/// ```
+/// use riak::object::{FetchObjectReq, ObjectContent, StoreObjectReq};
+/// use riak::testing::MockServer;
+///
+/// let server = MockServer::start().unwrap();
+/// let mut riak = server.client().unwrap();
+///
+/// let mut req = StoreObjectReq::new("testbucket", ObjectContent::new("testvalue"));
+/// req.set_key("testkey");
+/// riak.store_object(&req).unwrap();
+///
+/// let req = FetchObjectReq::new("testbucket", "testkey");
+/// let resp = riak.fetch_object(&req).unwrap();
+/// assert_eq!(resp.get_content()[0].get_value(), b"testvalue".to_vec());
+/// ```
+
+use Client;
+use errors::RiakErr;
+use protobuf::{Message, MessageStatic, RepeatedField, parse_from_bytes};
+use rpb::riak::{RpbBucketProps, RpbErrorResp, RpbGetBucketReq, RpbGetBucketResp,
+                RpbGetBucketTypeReq, RpbGetServerInfoResp, RpbResetBucketReq, RpbSetBucketReq,
+                RpbSetBucketTypeReq};
+use rpb::riak_kv::{RpbBucketKeyPreflistItem, RpbCSBucketReq, RpbCSBucketResp, RpbContent,
+                   RpbCounterGetReq, RpbCounterGetResp, RpbCounterUpdateReq,
+                   RpbCounterUpdateResp, RpbCoverageEntry, RpbCoverageReq, RpbCoverageResp,
+                   RpbDelReq, RpbGetBucketKeyPreflistReq, RpbGetBucketKeyPreflistResp, RpbGetReq,
+                   RpbGetResp, RpbIndexBodyResp, RpbIndexObject, RpbIndexReq,
+                   RpbIndexReq_IndexQueryType, RpbIndexResp, RpbListBucketsReq,
+                   RpbListBucketsResp, RpbListKeysReq, RpbListKeysResp, RpbPutReq, RpbPutResp};
+use rpb::riak_yokozuna::{RpbYokozunaIndex, RpbYokozunaIndexDeleteReq, RpbYokozunaIndexGetReq,
+                         RpbYokozunaIndexGetResp, RpbYokozunaIndexPutReq, RpbYokozunaSchema,
+                         RpbYokozunaSchemaGetReq, RpbYokozunaSchemaGetResp,
+                         RpbYokozunaSchemaPutReq};
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+use std::io;
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+pub use rpb::codes;
+
+// The bucket type of requests that don't name one.
+static DEFAULT_BUCKET_TYPE: &[u8] = b"default";
+
+// The schema Yokozuna indexes use when they don't name one.
+static DEFAULT_SCHEMA: &[u8] = b"_yz_default";
+
+// The name the server reports for itself, in server info and preflists.
+static NODE_NAME: &str = "riak@127.0.0.1";
+
+// The number of partitions in the pretend ring, used for preflists.
+static RING_SIZE: u64 = 64;
+
+// The number of parts in coverage plans that don't ask for a least number of partitions.
+static COVERAGE_PARTS: u64 = 4;
+
+// The prefix of the cover contexts in coverage plans, followed by the part and number of parts.
+static COVER_CONTEXT_PREFIX: &str = "mock-cover:";
+
+// Faults waiting to be injected, keyed by the request message code they apply to.
+type Faults = HashMap<u8, VecDeque<Fault>>;
+
+/// `Fault` is a failure a `MockServer` injects in place of the normal response to a request
+#[derive(Clone, Debug)]
+pub enum Fault {
+    /// sends the first half of the response frame, then closes the connection
+    TruncatedFrame,
+    /// sends the response with the given message code in place of the expected one
+    WrongResponseCode(u8),
+    /// responds with an `RpbErrorResp` holding the given message, without handling the request
+    ErrorResp(String),
+    /// waits for the given duration before responding, to exceed the client's timeout
+    Delay(Duration),
+    /// closes the connection without handling the request
+    Disconnect,
+    /// sends the given number of response frames, then closes the connection, to interrupt a
+    /// stream of responses
+    DisconnectAfter(usize),
+}
+
+/// `MockServer` is an in-memory stand-in for a Riak node listening on localhost
+///
+/// The server stops accepting connections when it is dropped.
+#[derive(Debug)]
+pub struct MockServer {
+    addr: SocketAddr,
+    stopped: Arc<AtomicBool>,
+    faults: Arc<Mutex<Faults>>,
+}
+
+impl MockServer {
+    /// starts a new `MockServer` with nothing stored, on a free port on localhost
+    pub fn start() -> Result<MockServer, RiakErr> {
+        let listener = match TcpListener::bind("127.0.0.1:0") {
+            Ok(listener) => listener,
+            Err(error) => return Err(RiakErr::IoError(error)),
+        };
+        let addr = match listener.local_addr() {
+            Ok(addr) => addr,
+            Err(error) => return Err(RiakErr::IoError(error)),
+        };
+
+        let state = Arc::new(Mutex::new(MockState::new(addr)));
+        let stopped = Arc::new(AtomicBool::new(false));
+        let faults = Arc::new(Mutex::new(HashMap::new()));
+
+        let accepting = stopped.clone();
+        let scheduled = faults.clone();
+        thread::spawn(move || for stream in listener.incoming() {
+            if accepting.load(Ordering::SeqCst) {
+                break;
+            }
+            match stream {
+                Ok(stream) => {
+                    let state = state.clone();
+                    let faults = scheduled.clone();
+                    thread::spawn(move || serve(stream, state, faults));
+                }
+                Err(error) => debug!("mock server failed to accept a connection: {:?}", error),
+            };
+        });
+
+        Ok(MockServer {
+            addr: addr,
+            stopped: stopped,
+            faults: faults,
+        })
+    }
+
+    /// get the address the server is listening on
+    pub fn get_addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// connects a new `Client` to the server
+    pub fn client(&self) -> Result<Client, RiakErr> {
+        Client::new(self.addr)
+    }
+
+    /// schedules a `Fault` for the next request with the given message code, on any connection
+    ///
+    /// Each fault is injected once, faults scheduled for the same message code are injected in
+    /// the order they were scheduled.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use riak::errors::RiakErr;
+    /// use riak::testing::{Fault, MockServer, codes};
+    ///
+    /// let server = MockServer::start().unwrap();
+    /// server.inject_fault(codes::RpbPingReq, Fault::ErrorResp("overload".to_string()));
+    ///
+    /// let mut riak = server.client().unwrap();
+    /// match riak.ping() {
+    ///     Err(RiakErr::ServerError(error)) => assert!(error.is_overload()),
+    ///     other => panic!("expected an overload error, got {:?}", other),
+    /// };
+    /// riak.ping().unwrap();
+    /// ```
+    pub fn inject_fault(&self, code: u8, fault: Fault) {
+        if let Ok(mut faults) = self.faults.lock() {
+            faults.entry(code).or_insert_with(VecDeque::new).push_back(fault);
+        }
+    }
+
+    /// discards every scheduled `Fault` that has not been injected yet
+    pub fn clear_faults(&self) {
+        if let Ok(mut faults) = self.faults.lock() {
+            faults.clear();
+        }
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.stopped.store(true, Ordering::SeqCst);
+        // wake up the accepting thread so that it notices
+        let _ = TcpStream::connect(self.addr);
+    }
+}
+
+// Answers the requests on a single connection until it is closed.
+fn serve(mut stream: TcpStream, state: Arc<Mutex<MockState>>, faults: Arc<Mutex<Faults>>) {
+    loop {
+        let (code, message) = match read_frame(&mut stream) {
+            Ok(frame) => frame,
+            Err(_) => return,
+        };
+
+        let fault = match faults.lock() {
+            Ok(mut faults) => {
+                match faults.get_mut(&code) {
+                    Some(scheduled) => scheduled.pop_front(),
+                    None => None,
+                }
+            }
+            Err(_) => return,
+        };
+
+        // some faults replace handling the request altogether
+        let responses = match fault {
+            Some(Fault::Disconnect) => return,
+            Some(Fault::ErrorResp(ref errmsg)) => Err(errmsg.clone()),
+            _ => {
+                match state.lock() {
+                    Ok(mut state) => state.handle(code, &message),
+                    Err(_) => return,
+                }
+            }
+        };
+
+        let mut frames = match responses {
+            Ok(frames) => frames,
+            Err(errmsg) => vec![error_frame(&errmsg)],
+        };
+
+        match fault {
+            Some(Fault::TruncatedFrame) => {
+                if let Some(&(code, ref message)) = frames.first() {
+                    let bytes = encode_frame(code, message);
+                    let _ = stream.write_all(&bytes[..bytes.len() / 2]);
+                }
+                return;
+            }
+            Some(Fault::WrongResponseCode(wrong_code)) => {
+                if let Some(first) = frames.first_mut() {
+                    first.0 = wrong_code;
+                }
+            }
+            Some(Fault::Delay(delay)) => thread::sleep(delay),
+            Some(Fault::DisconnectAfter(count)) => frames.truncate(count),
+            _ => (),
+        };
+
+        for &(code, ref message) in &frames {
+            if stream.write_all(&encode_frame(code, message)).is_err() {
+                return;
+            }
+        }
+
+        if let Some(Fault::DisconnectAfter(_)) = fault {
+            return;
+        }
+    }
+}
+
+fn read_frame(stream: &mut TcpStream) -> io::Result<(u8, Vec<u8>)> {
+    let mut header = [0u8; 5];
+    match stream.read_exact(&mut header) {
+        Ok(()) => (),
+        Err(error) => return Err(error),
+    };
+    let length = ((header[0] as usize) << 24) | ((header[1] as usize) << 16) |
+                 ((header[2] as usize) << 8) | (header[3] as usize);
+    if length == 0 {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "frame without a message code"));
+    }
+    let mut message = vec![0u8; length - 1];
+    match stream.read_exact(&mut message) {
+        Ok(()) => Ok((header[4], message)),
+        Err(error) => Err(error),
+    }
+}
+
+fn encode_frame(code: u8, message: &[u8]) -> Vec<u8> {
+    let length = (message.len() + 1) as u32;
+    let mut frame = vec![(length >> 24) as u8,
+                         (length >> 16) as u8,
+                         (length >> 8) as u8,
+                         length as u8,
+                         code];
+    frame.extend_from_slice(message);
+    frame
+}
+
+// Renders an `RpbErrorResp` frame given an error message.
+fn error_frame(errmsg: &str) -> (u8, Vec<u8>) {
+    let mut error = RpbErrorResp::new();
+    error.set_errmsg(errmsg.as_bytes().to_vec());
+    error.set_errcode(0);
+    (codes::RpbErrorResp, error.write_to_bytes().unwrap_or_default())
+}
+
+fn parse<M: MessageStatic>(message: &[u8]) -> Result<M, String> {
+    match parse_from_bytes::<M>(message) {
+        Ok(parsed) => Ok(parsed),
+        Err(error) => Err(format!("could not parse request: {}", error)),
+    }
+}
+
+fn frame<M: Message>(code: u8, message: &M) -> Result<(u8, Vec<u8>), String> {
+    match message.write_to_bytes() {
+        Ok(bytes) => Ok((code, bytes)),
+        Err(error) => Err(format!("could not encode response: {}", error)),
+    }
+}
+
+// The bucket type named by a request, or the default one.
+fn bucket_type(has_type: bool, bucket_type: &[u8]) -> Vec<u8> {
+    if has_type {
+        bucket_type.to_vec()
+    } else {
+        DEFAULT_BUCKET_TYPE.to_vec()
+    }
+}
+
+fn vclock(version: u64) -> Vec<u8> {
+    format!("mock-vclock-{}", version).into_bytes()
+}
+
+// An object with its key, as index queries and folds return them.
+fn index_object(key: &[u8], object: &MockObject) -> RpbIndexObject {
+    let mut rpb_get_resp = RpbGetResp::new();
+    rpb_get_resp.set_content(RepeatedField::from_vec(object.siblings.clone()));
+    rpb_get_resp.set_vclock(vclock(object.version));
+
+    let mut rpb_index_object = RpbIndexObject::new();
+    rpb_index_object.set_key(key.to_vec());
+    rpb_index_object.set_object(rpb_get_resp);
+    rpb_index_object
+}
+
+// Frames the response to an index query, streams end with a separate response marking the end.
+fn index_frames<M: Message>(code: u8,
+                            resp: &M,
+                            done: &M,
+                            stream: bool)
+                            -> Result<Vec<(u8, Vec<u8>)>, String> {
+    if !stream {
+        return frame(code, resp).map(|frame| vec![frame]);
+    }
+
+    let mut frames = Vec::new();
+    for resp in &[resp, done] {
+        match frame(code, *resp) {
+            Ok(frame) => frames.push(frame),
+            Err(error) => return Err(error),
+        };
+    }
+    Ok(frames)
+}
+
+// Hashes a list of byte strings with FNV-1a.
+fn fnv_hash(parts: &[&[u8]]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for part in parts {
+        for byte in part.iter() {
+            hash ^= *byte as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+    }
+    hash
+}
+
+// Parses a cover context from a coverage plan into its part and number of parts.
+fn parse_cover_context(cover_context: &[u8]) -> Option<(u64, u64)> {
+    let cover_context = String::from_utf8_lossy(cover_context);
+    if !cover_context.starts_with(COVER_CONTEXT_PREFIX) {
+        return None;
+    }
+
+    let numbers: Vec<Option<u64>> = cover_context[COVER_CONTEXT_PREFIX.len()..]
+        .split(':')
+        .map(|number| number.parse().ok())
+        .collect();
+    match numbers.as_slice() {
+        [Some(part), Some(parts)] if part < parts => Some((*part, *parts)),
+        _ => None,
+    }
+}
+
+// Applies every property set in `overrides` on top of `props`.
+fn merge_props(props: &mut RpbBucketProps, overrides: &RpbBucketProps) {
+    if let Ok(bytes) = overrides.write_to_bytes() {
+        let _ = props.merge_from_bytes(&bytes);
+    }
+}
+
+// Objects are identified by their bucket type, bucket and key.
+type ObjectId = (Vec<u8>, Vec<u8>, Vec<u8>);
+
+// An object stored by a `MockServer`, every store gives it a new version.
+struct MockObject {
+    version: u64,
+    siblings: Vec<RpbContent>,
+}
+
+// Everything stored by a `MockServer`, keyed by bucket type and bucket.
+struct MockState {
+    addr: SocketAddr,
+    objects: BTreeMap<ObjectId, MockObject>,
+    counters: HashMap<(Vec<u8>, Vec<u8>), i64>,
+    bucket_props: HashMap<(Vec<u8>, Vec<u8>), RpbBucketProps>,
+    bucket_type_props: HashMap<Vec<u8>, RpbBucketProps>,
+    schemas: HashMap<Vec<u8>, Vec<u8>>,
+    indexes: BTreeMap<Vec<u8>, RpbYokozunaIndex>,
+    last_version: u64,
+}
+
+impl MockState {
+    fn new(addr: SocketAddr) -> MockState {
+        MockState {
+            addr: addr,
+            objects: BTreeMap::new(),
+            counters: HashMap::new(),
+            bucket_props: HashMap::new(),
+            bucket_type_props: HashMap::new(),
+            schemas: HashMap::new(),
+            indexes: BTreeMap::new(),
+            last_version: 0,
+        }
+    }
+
+    // Answers a request with the frames to send back, or the message of an error.
+    fn handle(&mut self, code: u8, message: &[u8]) -> Result<Vec<(u8, Vec<u8>)>, String> {
+        match code {
+            codes::RpbPingReq => Ok(vec![(codes::RpbPingResp, Vec::new())]),
+            codes::RpbGetServerInfoReq => self.server_info(),
+            codes::RpbGetReq => self.get(message),
+            codes::RpbPutReq => self.put(message),
+            codes::RpbDelReq => self.delete(message),
+            codes::RpbListBucketsReq => self.list_buckets(message),
+            codes::RpbListKeysReq => self.list_keys(message),
+            codes::RpbGetBucketReq => self.get_bucket(message),
+            codes::RpbSetBucketReq => self.set_bucket(message),
+            codes::RpbResetBucketReq => self.reset_bucket(message),
+            codes::RpbGetBucketTypeReq => self.get_bucket_type(message),
+            codes::RpbSetBucketTypeReq => self.set_bucket_type(message),
+            codes::RpbGetBucketKeyPreflistReq => self.preflist(message),
+            codes::RpbCoverageReq => self.coverage(message),
+            codes::RpbIndexReq => self.index(message),
+            codes::RpbCSBucketReq => self.fold_objects(message),
+            codes::RpbCounterUpdateReq => self.update_counter(message),
+            codes::RpbCounterGetReq => self.get_counter(message),
+            codes::RpbYokozunaSchemaPutReq => self.put_schema(message),
+            codes::RpbYokozunaSchemaGetReq => self.get_schema(message),
+            codes::RpbYokozunaIndexPutReq => self.put_index(message),
+            codes::RpbYokozunaIndexGetReq => self.get_index(message),
+            codes::RpbYokozunaIndexDeleteReq => self.delete_index(message),
+            _ => Err(format!("unsupported message code {}", code)),
+        }
+    }
+
+    // The properties of a bucket type, or of a bucket within it.
+    fn props(&self, bucket_type: &[u8], bucket: Option<&[u8]>) -> RpbBucketProps {
+        let mut props = RpbBucketProps::new();
+        props.set_n_val(3);
+        props.set_allow_mult(bucket_type != DEFAULT_BUCKET_TYPE);
+        props.set_last_write_wins(false);
+
+        if let Some(type_props) = self.bucket_type_props.get(bucket_type) {
+            merge_props(&mut props, type_props);
+        }
+        if let Some(bucket) = bucket {
+            let id = (bucket_type.to_vec(), bucket.to_vec());
+            if let Some(bucket_props) = self.bucket_props.get(&id) {
+                merge_props(&mut props, bucket_props);
+            }
+        }
+
+        props
+    }
+
+    fn next_version(&mut self) -> u64 {
+        self.last_version += 1;
+        self.last_version
+    }
+
+    fn server_info(&self) -> Result<Vec<(u8, Vec<u8>)>, String> {
+        let mut resp = RpbGetServerInfoResp::new();
+        resp.set_node(NODE_NAME.as_bytes().to_vec());
+        resp.set_server_version(b"mock".to_vec());
+        frame(codes::RpbGetServerInfoResp, &resp).map(|frame| vec![frame])
+    }
+
+    fn get_bucket(&self, message: &[u8]) -> Result<Vec<(u8, Vec<u8>)>, String> {
+        let req = match parse::<RpbGetBucketReq>(message) {
+            Ok(req) => req,
+            Err(error) => return Err(error),
+        };
+
+        let bucket_type = bucket_type(req.has_field_type(), req.get_field_type());
+        let mut resp = RpbGetBucketResp::new();
+        resp.set_props(self.props(&bucket_type, Some(req.get_bucket())));
+        frame(codes::RpbGetBucketResp, &resp).map(|frame| vec![frame])
+    }
+
+    fn set_bucket(&mut self, message: &[u8]) -> Result<Vec<(u8, Vec<u8>)>, String> {
+        let mut req = match parse::<RpbSetBucketReq>(message) {
+            Ok(req) => req,
+            Err(error) => return Err(error),
+        };
+
+        let bucket_type = bucket_type(req.has_field_type(), req.get_field_type());
+        let props = self.bucket_props
+            .entry((bucket_type, req.take_bucket()))
+            .or_insert_with(RpbBucketProps::new);
+        merge_props(props, req.get_props());
+        Ok(vec![(codes::RpbSetBucketResp, Vec::new())])
+    }
+
+    fn reset_bucket(&mut self, message: &[u8]) -> Result<Vec<(u8, Vec<u8>)>, String> {
+        let mut req = match parse::<RpbResetBucketReq>(message) {
+            Ok(req) => req,
+            Err(error) => return Err(error),
+        };
+
+        let bucket_type = bucket_type(req.has_field_type(), req.get_field_type());
+        self.bucket_props.remove(&(bucket_type, req.take_bucket()));
+        Ok(vec![(codes::RpbResetBucketResp, Vec::new())])
+    }
+
+    fn get_bucket_type(&self, message: &[u8]) -> Result<Vec<(u8, Vec<u8>)>, String> {
+        let req = match parse::<RpbGetBucketTypeReq>(message) {
+            Ok(req) => req,
+            Err(error) => return Err(error),
+        };
+
+        let mut resp = RpbGetBucketResp::new();
+        resp.set_props(self.props(req.get_field_type(), None));
+        frame(codes::RpbGetBucketResp, &resp).map(|frame| vec![frame])
+    }
+
+    fn set_bucket_type(&mut self, message: &[u8]) -> Result<Vec<(u8, Vec<u8>)>, String> {
+        let mut req = match parse::<RpbSetBucketTypeReq>(message) {
+            Ok(req) => req,
+            Err(error) => return Err(error),
+        };
+
+        let props = self.bucket_type_props
+            .entry(req.take_field_type())
+            .or_insert_with(RpbBucketProps::new);
+        merge_props(props, req.get_props());
+        Ok(vec![(codes::RpbSetBucketResp, Vec::new())])
+    }
+
+    fn get(&self, message: &[u8]) -> Result<Vec<(u8, Vec<u8>)>, String> {
+        let req = match parse::<RpbGetReq>(message) {
+            Ok(req) => req,
+            Err(error) => return Err(error),
+        };
+
+        let id = (bucket_type(req.has_field_type(), req.get_field_type()),
+                  req.get_bucket().to_vec(),
+                  req.get_key().to_vec());
+
+        let mut resp = RpbGetResp::new();
+        if let Some(object) = self.objects.get(&id) {
+            let current = vclock(object.version);
+            if req.has_if_modified() && req.get_if_modified() == &current[..] {
+                resp.set_unchanged(true);
+            } else {
+                let mut siblings = object.siblings.clone();
+                if req.get_head() {
+                    for sibling in &mut siblings {
+                        sibling.set_value(Vec::new());
+                    }
+                }
+                resp.set_content(RepeatedField::from_vec(siblings));
+            }
+            resp.set_vclock(current);
+        }
+
+        frame(codes::RpbGetResp, &resp).map(|frame| vec![frame])
+    }
+
+    fn put(&mut self, message: &[u8]) -> Result<Vec<(u8, Vec<u8>)>, String> {
+        let mut req = match parse::<RpbPutReq>(message) {
+            Ok(req) => req,
+            Err(error) => return Err(error),
+        };
+
+        let bucket_type = bucket_type(req.has_field_type(), req.get_field_type());
+        let bucket = req.take_bucket();
+        let allow_mult = self.props(&bucket_type, Some(&bucket)).get_allow_mult();
+        let version = self.next_version();
+
+        // Riak generates a key when none is given
+        let generated_key = !req.has_key();
+        let key = if generated_key {
+            format!("mockkey{}", version).into_bytes()
+        } else {
+            req.take_key()
+        };
+        let id = (bucket_type, bucket, key.clone());
+
+        // check the preconditions against the current version of the object
+        let current = self.objects.get(&id).map(|object| vclock(object.version));
+        if req.get_if_none_match() && current.is_some() {
+            return Err("match_found".to_string());
+        }
+        let current_vclock = current.as_ref().map(|vclock| &vclock[..]);
+        if req.get_if_not_modified() && current_vclock != Some(req.get_vclock()) {
+            return Err("modified".to_string());
+        }
+
+        // stamp the new content the way Riak would
+        let mut content = req.take_content();
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+        content.set_last_mod(now.as_secs() as u32);
+        content.set_last_mod_usecs(now.subsec_micros());
+        content.set_vtag(format!("mockvtag{}", version).into_bytes());
+
+        // a store without the current vclock becomes a sibling when allow_mult is set
+        let replaces = req.has_vclock() && current_vclock == Some(req.get_vclock());
+        let mut siblings = match self.objects.remove(&id) {
+            Some(ref object) if allow_mult && !replaces => object.siblings.clone(),
+            _ => Vec::new(),
+        };
+        siblings.push(content);
+
+        let mut resp = RpbPutResp::new();
+        if generated_key {
+            resp.set_key(key);
+        }
+        if req.get_return_body() || req.get_return_head() {
+            let mut returned = siblings.clone();
+            if !req.get_return_body() {
+                for sibling in &mut returned {
+                    sibling.set_value(Vec::new());
+                }
+            }
+            resp.set_content(RepeatedField::from_vec(returned));
+            resp.set_vclock(vclock(version));
+        }
+
+        self.objects.insert(id,
+                            MockObject {
+                                version: version,
+                                siblings: siblings,
+                            });
+
+        frame(codes::RpbPutResp, &resp).map(|frame| vec![frame])
+    }
+
+    fn delete(&mut self, message: &[u8]) -> Result<Vec<(u8, Vec<u8>)>, String> {
+        let req = match parse::<RpbDelReq>(message) {
+            Ok(req) => req,
+            Err(error) => return Err(error),
+        };
+
+        let id = (bucket_type(req.has_field_type(), req.get_field_type()),
+                  req.get_bucket().to_vec(),
+                  req.get_key().to_vec());
+        self.objects.remove(&id);
+        Ok(vec![(codes::RpbDelResp, Vec::new())])
+    }
+
+    fn list_buckets(&self, message: &[u8]) -> Result<Vec<(u8, Vec<u8>)>, String> {
+        let req = match parse::<RpbListBucketsReq>(message) {
+            Ok(req) => req,
+            Err(error) => return Err(error),
+        };
+
+        let bucket_type = bucket_type(req.has_field_type(), req.get_field_type());
+
+        let mut buckets: BTreeSet<Vec<u8>> = BTreeSet::new();
+        for (object_type, bucket, _) in self.objects.keys() {
+            if *object_type == bucket_type {
+                buckets.insert(bucket.clone());
+            }
+        }
+
+        let mut resp = RpbListBucketsResp::new();
+        resp.set_buckets(RepeatedField::from_vec(buckets.into_iter().collect()));
+        if req.get_stream() {
+            resp.set_done(true);
+        }
+        frame(codes::RpbListBucketsResp, &resp).map(|frame| vec![frame])
+    }
+
+    fn list_keys(&self, message: &[u8]) -> Result<Vec<(u8, Vec<u8>)>, String> {
+        let req = match parse::<RpbListKeysReq>(message) {
+            Ok(req) => req,
+            Err(error) => return Err(error),
+        };
+
+        let bucket_type = bucket_type(req.has_field_type(), req.get_field_type());
+
+        let mut keys: Vec<Vec<u8>> = Vec::new();
+        for (object_type, bucket, key) in self.objects.keys() {
+            if *object_type == bucket_type && bucket == req.get_bucket() {
+                keys.push(key.clone());
+            }
+        }
+
+        // like Riak, the keys are followed by a separate response marking the end
+        let mut frames = Vec::new();
+        if !keys.is_empty() {
+            let mut resp = RpbListKeysResp::new();
+            resp.set_keys(RepeatedField::from_vec(keys));
+            match frame(codes::RpbListKeysResp, &resp) {
+                Ok(frame) => frames.push(frame),
+                Err(error) => return Err(error),
+            };
+        }
+        let mut done = RpbListKeysResp::new();
+        done.set_done(true);
+        match frame(codes::RpbListKeysResp, &done) {
+            Ok(frame) => frames.push(frame),
+            Err(error) => return Err(error),
+        };
+
+        Ok(frames)
+    }
+
+    fn preflist(&self, message: &[u8]) -> Result<Vec<(u8, Vec<u8>)>, String> {
+        let req = match parse::<RpbGetBucketKeyPreflistReq>(message) {
+            Ok(req) => req,
+            Err(error) => return Err(error),
+        };
+
+        let bucket_type = bucket_type(req.has_field_type(), req.get_field_type());
+        let n_val = self.props(&bucket_type, Some(req.get_bucket())).get_n_val() as u64;
+
+        // hash the bucket and key onto the ring
+        let hash = fnv_hash(&[&bucket_type, req.get_bucket(), req.get_key()]);
+
+        let mut preflist = Vec::new();
+        for offset in 0..n_val {
+            let mut item = RpbBucketKeyPreflistItem::new();
+            item.set_partition((hash.wrapping_add(offset) % RING_SIZE) as i64);
+            item.set_node(NODE_NAME.as_bytes().to_vec());
+            item.set_primary(true);
+            preflist.push(item);
+        }
+
+        let mut resp = RpbGetBucketKeyPreflistResp::new();
+        resp.set_preflist(RepeatedField::from_vec(preflist));
+        frame(codes::RpbGetBucketKeyPreflistResp, &resp).map(|frame| vec![frame])
+    }
+
+    fn coverage(&self, message: &[u8]) -> Result<Vec<(u8, Vec<u8>)>, String> {
+        let req = match parse::<RpbCoverageReq>(message) {
+            Ok(req) => req,
+            Err(error) => return Err(error),
+        };
+
+        // every part is served by this server, so a replacement is the same part again
+        let covers: Vec<(u64, u64)> = if req.has_replace_cover() {
+            match parse_cover_context(req.get_replace_cover()) {
+                Some(cover) => vec![cover],
+                None => return Err("invalid cover context".to_string()),
+            }
+        } else {
+            let parts = if req.get_min_partitions() > 0 {
+                req.get_min_partitions() as u64
+            } else {
+                COVERAGE_PARTS
+            };
+            (0..parts).map(|part| (part, parts)).collect()
+        };
+
+        let mut entries = Vec::new();
+        for (part, parts) in covers {
+            let mut entry = RpbCoverageEntry::new();
+            entry.set_ip(self.addr.ip().to_string().into_bytes());
+            entry.set_port(self.addr.port() as u32);
+            entry.set_keyspace_desc(format!("mock part {} of {}", part + 1, parts).into_bytes());
+            entry.set_cover_context(format!("{}{}:{}", COVER_CONTEXT_PREFIX, part, parts)
+                .into_bytes());
+            entries.push(entry);
+        }
+
+        let mut resp = RpbCoverageResp::new();
+        resp.set_entries(RepeatedField::from_vec(entries));
+        frame(codes::RpbCoverageResp, &resp).map(|frame| vec![frame])
+    }
+
+    fn index(&self, message: &[u8]) -> Result<Vec<(u8, Vec<u8>)>, String> {
+        let req = match parse::<RpbIndexReq>(message) {
+            Ok(req) => req,
+            Err(error) => return Err(error),
+        };
+
+        let bucket_type = bucket_type(req.has_field_type(), req.get_field_type());
+        let cover = if req.has_cover_context() {
+            match parse_cover_context(req.get_cover_context()) {
+                Some(cover) => Some(cover),
+                None => return Err("invalid cover context".to_string()),
+            }
+        } else {
+            None
+        };
+        let eq = req.get_qtype() == RpbIndexReq_IndexQueryType::eq;
+
+        let mut keys: Vec<Vec<u8>> = Vec::new();
+        for (object_type, bucket, key) in self.objects.keys() {
+            if *object_type != bucket_type || bucket.as_slice() != req.get_bucket() {
+                continue;
+            }
+
+            let matched = match req.get_index() {
+                b"$bucket" => eq && req.get_key() == bucket.as_slice(),
+                b"$key" if eq => req.get_key() == key.as_slice(),
+                b"$key" => {
+                    req.get_range_min() <= key.as_slice() && key.as_slice() <= req.get_range_max()
+                }
+                _ => return Err("only the $bucket and $key indexes are supported".to_string()),
+            };
+            let covered = match cover {
+                Some((part, parts)) => fnv_hash(&[key]) % parts == part,
+                None => true,
+            };
+            let after_continuation = !req.has_continuation() ||
+                                     key.as_slice() > req.get_continuation();
+
+            if matched && covered && after_continuation {
+                keys.push(key.clone());
+            }
+        }
+
+        // the continuation is the last key of a page, when there are more
+        let mut continuation = None;
+        let max_results = req.get_max_results() as usize;
+        if max_results > 0 && keys.len() > max_results {
+            keys.truncate(max_results);
+            continuation = keys.last().cloned();
+        }
+
+        // streams end with a separate response, which holds the continuation
+        if req.get_return_body() {
+            let mut objects: Vec<RpbIndexObject> = Vec::new();
+            for key in keys {
+                let id = (bucket_type.clone(), req.get_bucket().to_vec(), key);
+                if let Some(object) = self.objects.get(&id) {
+                    objects.push(index_object(&id.2, object));
+                }
+            }
+
+            let mut resp = RpbIndexBodyResp::new();
+            resp.set_objects(RepeatedField::from_vec(objects));
+            let mut done = RpbIndexBodyResp::new();
+            done.set_done(true);
+            if let Some(continuation) = continuation {
+                if req.get_stream() {
+                    done.set_continuation(continuation);
+                } else {
+                    resp.set_continuation(continuation);
+                }
+            }
+            index_frames(codes::RpbIndexBodyResp, &resp, &done, req.get_stream())
+        } else {
+            let mut resp = RpbIndexResp::new();
+            resp.set_keys(RepeatedField::from_vec(keys));
+            let mut done = RpbIndexResp::new();
+            done.set_done(true);
+            if let Some(continuation) = continuation {
+                if req.get_stream() {
+                    done.set_continuation(continuation);
+                } else {
+                    resp.set_continuation(continuation);
+                }
+            }
+            index_frames(codes::RpbIndexResp, &resp, &done, req.get_stream())
+        }
+    }
+
+    fn fold_objects(&self, message: &[u8]) -> Result<Vec<(u8, Vec<u8>)>, String> {
+        let req = match parse::<RpbCSBucketReq>(message) {
+            Ok(req) => req,
+            Err(error) => return Err(error),
+        };
+
+        let bucket_type = bucket_type(req.has_field_type(), req.get_field_type());
+
+        let mut objects: Vec<RpbIndexObject> = Vec::new();
+        for ((object_type, bucket, key), object) in &self.objects {
+            if *object_type != bucket_type || bucket.as_slice() != req.get_bucket() {
+                continue;
+            }
+
+            // like Riak, the start key is included and the end key is not unless asked otherwise
+            let key = key.as_slice();
+            let after_start = key > req.get_start_key() ||
+                              (key == req.get_start_key() && req.get_start_incl());
+            let before_end = key < req.get_end_key() ||
+                             (key == req.get_end_key() && req.get_end_incl());
+            let after_continuation = !req.has_continuation() || key > req.get_continuation();
+
+            if after_start && before_end && after_continuation {
+                objects.push(index_object(key, object));
+            }
+        }
+
+        // the continuation is the last key of a page, when there are more
+        let mut continuation = None;
+        let max_results = req.get_max_results() as usize;
+        if max_results > 0 && objects.len() > max_results {
+            objects.truncate(max_results);
+            continuation = objects.last().map(|object| object.get_key().to_vec());
+        }
+
+        // the objects are followed by a separate response marking the end, with the continuation
+        let mut resp = RpbCSBucketResp::new();
+        resp.set_objects(RepeatedField::from_vec(objects));
+        let mut done = RpbCSBucketResp::new();
+        done.set_done(true);
+        if let Some(continuation) = continuation {
+            done.set_continuation(continuation);
+        }
+        let mut frames = Vec::new();
+        for resp in &[resp, done] {
+            match frame(codes::RpbCSBucketResp, resp) {
+                Ok(frame) => frames.push(frame),
+                Err(error) => return Err(error),
+            };
+        }
+        Ok(frames)
+    }
+
+    fn update_counter(&mut self, message: &[u8]) -> Result<Vec<(u8, Vec<u8>)>, String> {
+        let req = match parse::<RpbCounterUpdateReq>(message) {
+            Ok(req) => req,
+            Err(error) => return Err(error),
+        };
+
+        match self.check_counter_bucket(req.get_bucket()) {
+            Ok(()) => (),
+            Err(error) => return Err(error),
+        };
+
+        let id = (req.get_bucket().to_vec(), req.get_key().to_vec());
+        let value = self.counters.entry(id).or_insert(0);
+        *value += req.get_amount();
+
+        let mut resp = RpbCounterUpdateResp::new();
+        if req.get_returnvalue() {
+            resp.set_value(*value);
+        }
+        frame(codes::RpbCounterUpdateResp, &resp).map(|frame| vec![frame])
+    }
+
+    fn get_counter(&self, message: &[u8]) -> Result<Vec<(u8, Vec<u8>)>, String> {
+        let req = match parse::<RpbCounterGetReq>(message) {
+            Ok(req) => req,
+            Err(error) => return Err(error),
+        };
+
+        match self.check_counter_bucket(req.get_bucket()) {
+            Ok(()) => (),
+            Err(error) => return Err(error),
+        };
+
+        let mut resp = RpbCounterGetResp::new();
+        let id = (req.get_bucket().to_vec(), req.get_key().to_vec());
+        if let Some(value) = self.counters.get(&id) {
+            resp.set_value(*value);
+        }
+        frame(codes::RpbCounterGetResp, &resp).map(|frame| vec![frame])
+    }
+
+    // Legacy counters live in the default bucket type, in buckets with "allow_mult" set.
+    fn check_counter_bucket(&self, bucket: &[u8]) -> Result<(), String> {
+        if self.props(DEFAULT_BUCKET_TYPE, Some(bucket)).get_allow_mult() {
+            Ok(())
+        } else {
+            Err("Counters require bucket property 'allow_mult=true'".to_string())
+        }
+    }
+
+    fn put_schema(&mut self, message: &[u8]) -> Result<Vec<(u8, Vec<u8>)>, String> {
+        let mut req = match parse::<RpbYokozunaSchemaPutReq>(message) {
+            Ok(req) => req,
+            Err(error) => return Err(error),
+        };
+
+        let mut schema = req.take_schema();
+        self.schemas.insert(schema.take_name(), schema.take_content());
+        Ok(vec![(codes::RpbPutResp, Vec::new())])
+    }
+
+    fn get_schema(&self, message: &[u8]) -> Result<Vec<(u8, Vec<u8>)>, String> {
+        let req = match parse::<RpbYokozunaSchemaGetReq>(message) {
+            Ok(req) => req,
+            Err(error) => return Err(error),
+        };
+
+        let content = match self.schemas.get(req.get_name()) {
+            Some(content) => content.clone(),
+            None => return Err("notfound".to_string()),
+        };
+
+        let mut schema = RpbYokozunaSchema::new();
+        schema.set_name(req.get_name().to_vec());
+        schema.set_content(content);
+        let mut resp = RpbYokozunaSchemaGetResp::new();
+        resp.set_schema(schema);
+        frame(codes::RpbYokozunaSchemaGetResp, &resp).map(|frame| vec![frame])
+    }
+
+    fn put_index(&mut self, message: &[u8]) -> Result<Vec<(u8, Vec<u8>)>, String> {
+        let mut req = match parse::<RpbYokozunaIndexPutReq>(message) {
+            Ok(req) => req,
+            Err(error) => return Err(error),
+        };
+
+        // the schema has to exist before an index can use it
+        let mut index = req.take_index();
+        if !index.has_schema() {
+            index.set_schema(DEFAULT_SCHEMA.to_vec());
+        }
+        if index.get_schema() != DEFAULT_SCHEMA && !self.schemas.contains_key(index.get_schema()) {
+            return Err("schema not found".to_string());
+        }
+        if !index.has_n_val() {
+            index.set_n_val(3);
+        }
+
+        self.indexes.insert(index.get_name().to_vec(), index);
+        Ok(vec![(codes::RpbPutResp, Vec::new())])
+    }
+
+    fn get_index(&self, message: &[u8]) -> Result<Vec<(u8, Vec<u8>)>, String> {
+        let req = match parse::<RpbYokozunaIndexGetReq>(message) {
+            Ok(req) => req,
+            Err(error) => return Err(error),
+        };
+
+        // every index is returned when no name is given
+        let indexes: Vec<RpbYokozunaIndex> = if req.has_name() {
+            match self.indexes.get(req.get_name()) {
+                Some(index) => vec![index.clone()],
+                None => return Err("notfound".to_string()),
+            }
+        } else {
+            self.indexes.values().cloned().collect()
+        };
+
+        let mut resp = RpbYokozunaIndexGetResp::new();
+        resp.set_index(RepeatedField::from_vec(indexes));
+        frame(codes::RpbYokozunaIndexGetResp, &resp).map(|frame| vec![frame])
+    }
+
+    fn delete_index(&mut self, message: &[u8]) -> Result<Vec<(u8, Vec<u8>)>, String> {
+        let req = match parse::<RpbYokozunaIndexDeleteReq>(message) {
+            Ok(req) => req,
+            Err(error) => return Err(error),
+        };
+
+        match self.indexes.remove(req.get_name()) {
+            Some(_) => Ok(vec![(codes::RpbDelResp, Vec::new())]),
+            None => Err("notfound".to_string()),
+        }
+    }
+}
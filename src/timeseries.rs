@@ -0,0 +1,71 @@
+/// Riak TS related structs for storing and querying time series data.
+///
+/// For more information: https://docs.basho.com/riak/ts/latest/using/
+
+/// `ColumnType` is the type of a column in a Riak TS table.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ColumnType {
+    Varchar,
+    Sint64,
+    Double,
+    Timestamp,
+    Boolean,
+}
+
+/// `TsCell` is a single typed value within a Riak TS row.
+///
+/// Timestamps are represented as milliseconds since the UNIX epoch, and `Null` represents a
+/// missing value in a column that allows nulls.
+#[derive(Clone, Debug, PartialEq)]
+pub enum TsCell {
+    Varchar(Vec<u8>),
+    Sint64(i64),
+    Double(f64),
+    Timestamp(i64),
+    Boolean(bool),
+    Null,
+}
+
+/// `Column` describes a column in a Riak TS table by name and type.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Column {
+    pub name: Vec<u8>,
+    pub column_type: ColumnType,
+}
+
+impl Column {
+    pub fn new<T: Into<Vec<u8>>>(name: T, column_type: ColumnType) -> Column {
+        Column {
+            name: name.into(),
+            column_type: column_type,
+        }
+    }
+}
+
+/// `Row` represents a single row of a Riak TS table, with cells in column order.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Row {
+    pub cells: Vec<TsCell>,
+}
+
+impl Row {
+    pub fn new(cells: Vec<TsCell>) -> Row {
+        Row { cells: cells }
+    }
+}
+
+/// `QueryResults` represents the columns and rows returned by a Riak TS get or query.
+#[derive(Clone, Debug, PartialEq)]
+pub struct QueryResults {
+    pub columns: Vec<Column>,
+    pub rows: Vec<Row>,
+}
+
+impl QueryResults {
+    pub fn new(columns: Vec<Column>, rows: Vec<Row>) -> QueryResults {
+        QueryResults {
+            columns: columns,
+            rows: rows,
+        }
+    }
+}
@@ -0,0 +1,632 @@
+/// Transports carry the messages of Riak's Protocol Buffers API between a `Client` and Riak.
+///
+/// Every message is a message code followed by a Protocol Buffers encoded body. A `Client` sends
+/// requests and receives responses through a `Transport`, by default a `TcpTransport` which
+/// connects to a Riak node over TCP, optionally upgraded to TLS.
+///
+/// Other transports can be used with `Client::from_transport`:
+///
+/// * `InMemoryTransport` passes messages over in-memory channels, to test code without Riak
+/// * `RecordingTransport` wraps another transport and records every message sent and received
+///
+/// Implementing `Transport` for a type that wraps another transport is a way to add metrics or
+/// tracing to every request.
+///
+/// # Examples
+///
+/// ```
+/// use riak::Client;
+/// use riak::transport::{InMemoryListener, Transport};
+/// use std::thread;
+///
+/// let listener = InMemoryListener::new();
+/// let mut riak = Client::from_transport(listener.connect());
+///
+/// // answer a single ping, as Riak would
+/// let server = thread::spawn(move || {
+///     let mut connection = listener.accept().unwrap();
+///     let (code, _) = connection.receive().unwrap();
+///     assert_eq!(code, 1);
+///     connection.send(2, &[]).unwrap();
+/// });
+///
+/// riak.ping().unwrap();
+/// server.join().unwrap();
+/// ```
+
+use DEFAULT_TIMEOUT;
+#[cfg(feature = "tls")]
+use connection::RiakConn;
+use errors::RiakErr;
+#[cfg(feature = "tls")]
+use native_tls::TlsStream;
+#[cfg(feature = "tls")]
+use private_traits::SecurityConfigPrivate;
+#[cfg(feature = "tls")]
+use rpb::codes;
+#[cfg(feature = "tls")]
+use security::SecurityConfig;
+use std::io;
+use std::io::{Read, Write};
+use std::net::{Shutdown, SocketAddr, TcpStream, ToSocketAddrs};
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender};
+use std::time::{Duration, Instant};
+
+/// `Transport` sends and receives the messages of Riak's Protocol Buffers API
+pub trait Transport: Sized {
+    /// sends a message with the given message code
+    fn send(&mut self, code: u8, message: &[u8]) -> Result<(), RiakErr>;
+
+    /// receives the next message along with its message code
+    fn receive(&mut self) -> Result<(u8, Vec<u8>), RiakErr>;
+
+    /// opens another connection to the same place with the same settings, streams use one of
+    /// their own so the `Client` stays usable while they are read
+    fn duplicate(&self) -> Result<Self, RiakErr>;
+
+    /// closes the connection, any responses still in flight are discarded
+    fn shutdown(&mut self) -> Result<(), RiakErr>;
+
+    /// get the timeout (in seconds) allowed for responses
+    fn get_timeout(&self) -> u32;
+}
+
+// `RiakStream` is the underlying stream of a `TcpTransport`, either plain TCP or TLS.
+#[derive(Debug)]
+enum RiakStream {
+    Plain(TcpStream),
+    #[cfg(feature = "tls")]
+    Tls(TlsStream<TcpStream>),
+}
+
+impl RiakStream {
+    // Closes the stream in both directions, sending a TLS close_notify first if encrypted.
+    fn shutdown(&mut self) -> io::Result<()> {
+        match *self {
+            RiakStream::Plain(ref mut stream) => stream.shutdown(Shutdown::Both),
+            #[cfg(feature = "tls")]
+            RiakStream::Tls(ref mut stream) => {
+                match stream.shutdown() {
+                    Ok(()) => (),
+                    Err(error) => debug!("failure sending TLS close_notify: {:?}", error),
+                };
+                stream.get_ref().shutdown(Shutdown::Both)
+            }
+        }
+    }
+}
+
+impl Read for RiakStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match *self {
+            RiakStream::Plain(ref mut stream) => stream.read(buf),
+            #[cfg(feature = "tls")]
+            RiakStream::Tls(ref mut stream) => stream.read(buf),
+        }
+    }
+}
+
+impl Write for RiakStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match *self {
+            RiakStream::Plain(ref mut stream) => stream.write(buf),
+            #[cfg(feature = "tls")]
+            RiakStream::Tls(ref mut stream) => stream.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match *self {
+            RiakStream::Plain(ref mut stream) => stream.flush(),
+            #[cfg(feature = "tls")]
+            RiakStream::Tls(ref mut stream) => stream.flush(),
+        }
+    }
+}
+
+/// `TcpTransport` is a connection to a Riak node over TCP, optionally upgraded to TLS
+#[derive(Debug)]
+pub struct TcpTransport {
+    peer_addr: SocketAddr,
+    timeout: u32,
+    tcpstream: RiakStream,
+    #[cfg(feature = "tls")]
+    security: Option<SecurityConfig>,
+}
+
+impl TcpTransport {
+    /// connects to a Riak node with a timeout (in seconds) for reads and writes
+    pub fn new<A: ToSocketAddrs>(addr: A, timeout: u32) -> Result<TcpTransport, RiakErr> {
+        debug!("trying to connect to Riak...");
+        match TcpStream::connect(addr) {
+            Ok(tcpstream) => {
+                debug!("connection established!");
+
+                let timeout_duration = Duration::new(timeout as u64, 0);
+                match tcpstream.set_read_timeout(Some(timeout_duration)) {
+                    Ok(()) => (),
+                    Err(error) => return Err(RiakErr::IoError(error)),
+                };
+                match tcpstream.set_write_timeout(Some(timeout_duration)) {
+                    Ok(()) => (),
+                    Err(error) => return Err(RiakErr::IoError(error)),
+                };
+
+                // get the peer_addr
+                let peer_addr = match tcpstream.peer_addr() {
+                    Ok(peer_addr) => peer_addr,
+                    Err(error) => return Err(RiakErr::IoError(error)),
+                };
+
+                // return the connection
+                Ok(TcpTransport {
+                    peer_addr: peer_addr,
+                    tcpstream: RiakStream::Plain(tcpstream),
+                    timeout: timeout,
+                    #[cfg(feature = "tls")]
+                    security: None,
+                })
+            }
+            Err(err) => {
+                debug!("error while connecting to Riak: {:?}", err);
+                Err(RiakErr::IoError(err))
+            }
+        }
+    }
+
+    /// connects to a Riak node with security enabled, upgrading the connection to TLS with
+    /// `RpbStartTls` and then authenticating with `RpbAuthReq`
+    ///
+    /// This requires the "tls" feature.
+    #[cfg(feature = "tls")]
+    pub fn new_secure<A: ToSocketAddrs>(addr: A,
+                                        timeout: u32,
+                                        security: &SecurityConfig)
+                                        -> Result<TcpTransport, RiakErr> {
+        let transport = match TcpTransport::new(addr, timeout) {
+            Ok(transport) => transport,
+            Err(error) => return Err(error),
+        };
+
+        // build the connector first so configuration errors are reported before talking to Riak
+        let connector = match security.connector() {
+            Ok(connector) => connector,
+            Err(error) => return Err(error),
+        };
+
+        // ask Riak to start TLS, it responds with the same code once it is ready
+        debug!("requesting TLS upgrade...");
+        let mut conn = RiakConn::new(transport);
+        match conn.exchange(codes::RpbStartTls, codes::RpbStartTls, &[]) {
+            Ok(_) => (),
+            Err(error) => return Err(error),
+        };
+        let transport = conn.into_transport();
+
+        let tcpstream = match transport.tcpstream {
+            RiakStream::Plain(tcpstream) => tcpstream,
+            RiakStream::Tls(_) => return Err(RiakErr::TlsError("already upgraded".to_string())),
+        };
+
+        let domain = match security.get_domain() {
+            Some(domain) => domain,
+            None => transport.peer_addr.ip().to_string(),
+        };
+
+        let tlsstream = match connector.connect(&domain, tcpstream) {
+            Ok(tlsstream) => tlsstream,
+            Err(error) => {
+                debug!("TLS handshake failed: {:?}", error);
+                return Err(RiakErr::TlsError(error.to_string()));
+            }
+        };
+        debug!("TLS established!");
+
+        let mut conn = RiakConn::new(TcpTransport {
+            peer_addr: transport.peer_addr,
+            timeout: transport.timeout,
+            tcpstream: RiakStream::Tls(tlsstream),
+            security: Some(security.clone()),
+        });
+
+        // authenticate now that the connection is encrypted
+        let auth_req = match security.auth_req_bytes() {
+            Ok(bytes) => bytes,
+            Err(error) => return Err(error),
+        };
+        match conn.exchange(codes::RpbAuthReq, codes::RpbAuthResp, &auth_req) {
+            Ok(_) => debug!("authenticated successfully!"),
+            Err(error) => return Err(error),
+        };
+
+        Ok(conn.into_transport())
+    }
+
+    /// get the address of the Riak node connected to
+    pub fn get_peer_addr(&self) -> SocketAddr {
+        self.peer_addr
+    }
+}
+
+impl Transport for TcpTransport {
+    fn send(&mut self, code: u8, message: &[u8]) -> Result<(), RiakErr> {
+        // The first thing sent to Riak's Protocol Buffers API is a "header" of 5 bytes.
+        //
+        // https://docs.basho.com/riak/kv/latest/developing/api/protocol-buffers/#protocol
+        //
+        // Bytes 1 through 4 inform Riak of the number of bytes being sent, and byte 5
+        // is the protocol buffer code of the message being sent. Here we record the
+        // number of bytes we intend to send to Riak to generate this "header".
+        let mut send_header: [u8; 5] = [0u8; 5];
+        let send_bytes: u32 = (message.len() as u32) + 1;
+        send_header[0] = (send_bytes >> 24) as u8;
+        send_header[1] = (send_bytes >> 16) as u8;
+        send_header[2] = (send_bytes >> 8) as u8;
+        send_header[3] = send_bytes as u8;
+        send_header[4] = code;
+        debug!("header was {:?}", send_header);
+
+        // Send the header over the `TcpStream`, followed by the data. Each write may send only
+        // part of its buffer, over TLS at most a single record, so the whole of each is written.
+        match self.tcpstream.write_all(&send_header) {
+            Ok(()) => debug!("wrote header successfully!"),
+            Err(err) => {
+                debug!("could not write header error was: {:?}", err);
+                return Err(RiakErr::IoError(err));
+            }
+        };
+        match self.tcpstream.write_all(message) {
+            Ok(()) => debug!("wrote data of size {}", message.len()),
+            Err(err) => {
+                debug!("could not write data error was: {:?}", err);
+                return Err(RiakErr::IoError(err));
+            }
+        };
+        match self.tcpstream.flush() {
+            Ok(())   => debug!("wrote header and data successfully!"),
+            Err(err) => {
+                debug!("could not write header and data error was: {:?}", err);
+                return Err(RiakErr::IoError(err));
+            }
+        };
+
+        Ok(())
+    }
+
+    fn receive(&mut self) -> Result<(u8, Vec<u8>), RiakErr> {
+        // Retrieve the header from the server
+        let mut recv_header = [0u8; 4];
+        match self.tcpstream.read_exact(&mut recv_header) {
+            Ok(())   => debug!("received response header successfully!"),
+            Err(err) => {
+                debug!("could not receive response header error was: {:?}", err);
+                return Err(RiakErr::IoError(err));
+            }
+        };
+
+        // Convert the header to a u32.
+        // This number tells us how many bytes the server will be sending
+        let mut recv_bytes: u32 = 0;
+        recv_bytes |= ((recv_header[0] as u32) << 24) | ((recv_header[1] as u32) << 16) |
+                      ((recv_header[2] as u32) << 8) |
+                      (recv_header[3] as u32);
+
+        // Retrieve the code, the code is the first byte after the header
+        let mut recv_code = [0u8; 1];
+        match self.tcpstream.read_exact(&mut recv_code) {
+            Ok(())   => debug!("received response code {}", recv_code[0]),
+            Err(err) => {
+                debug!("could not receive response code error was: {:?}", err);
+                return Err(RiakErr::IoError(err));
+            }
+        };
+
+        // Retrieve the protocol buffer encoded data
+        let mut response = vec![0u8; (recv_bytes - 1) as usize];
+        match self.tcpstream.read_exact(&mut response) {
+            Ok(()) => debug!("received response of size {}", (recv_bytes - 1)),
+            Err(err) => {
+                debug!("failed to receive response from server of {} bytes error was: {:?}",
+                       recv_bytes,
+                       err);
+                return Err(RiakErr::IoError(err));
+            }
+        }
+
+        Ok((recv_code[0], response))
+    }
+
+    #[cfg(not(feature = "tls"))]
+    fn duplicate(&self) -> Result<TcpTransport, RiakErr> {
+        TcpTransport::new(self.peer_addr, self.timeout)
+    }
+
+    #[cfg(feature = "tls")]
+    fn duplicate(&self) -> Result<TcpTransport, RiakErr> {
+        match self.security {
+            Some(ref security) => TcpTransport::new_secure(self.peer_addr, self.timeout, security),
+            None => TcpTransport::new(self.peer_addr, self.timeout),
+        }
+    }
+
+    fn shutdown(&mut self) -> Result<(), RiakErr> {
+        match self.tcpstream.shutdown() {
+            Ok(()) => Ok(()),
+            Err(error) => Err(RiakErr::IoError(error)),
+        }
+    }
+
+    fn get_timeout(&self) -> u32 {
+        self.timeout
+    }
+}
+
+/// `InMemoryListener` accepts the other end of `InMemoryTransport` connections
+///
+/// Code under test is given a `Client` using a transport from `connect`, while the test answers
+/// its requests on the transports returned by `accept`. Streams open connections of their own,
+/// those are accepted the same way.
+#[derive(Debug)]
+pub struct InMemoryListener {
+    connector: Sender<InMemoryTransport>,
+    incoming: Receiver<InMemoryTransport>,
+    timeout: u32,
+}
+
+impl InMemoryListener {
+    /// constructs a new `InMemoryListener` with the default timeout
+    pub fn new() -> InMemoryListener {
+        let (connector, incoming) = channel();
+        InMemoryListener {
+            connector: connector,
+            incoming: incoming,
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+
+    /// get the timeout (in seconds) of connections made to this listener
+    pub fn get_timeout(&self) -> u32 {
+        self.timeout
+    }
+
+    /// set the timeout (in seconds) of connections made to this listener from now on, and how
+    /// long `accept` waits for a connection
+    pub fn set_timeout(&mut self, timeout: u32) {
+        self.timeout = timeout;
+    }
+
+    /// opens a new connection, the other end is returned by `accept`
+    pub fn connect(&self) -> InMemoryTransport {
+        InMemoryTransport::connect(&self.connector, self.timeout)
+    }
+
+    /// waits for the next connection and returns its other end
+    ///
+    /// # Errors
+    ///
+    /// Returns `RiakErr::IoError` if no connection is made within the timeout.
+    pub fn accept(&self) -> Result<InMemoryTransport, RiakErr> {
+        match self.incoming.recv_timeout(Duration::new(self.timeout as u64, 0)) {
+            Ok(transport) => Ok(transport),
+            Err(_) => {
+                Err(RiakErr::IoError(io::Error::new(io::ErrorKind::TimedOut,
+                                                    "timed out waiting for a connection")))
+            }
+        }
+    }
+}
+
+/// `InMemoryTransport` is one end of a connection that passes messages over in-memory channels
+///
+/// Connections are made with an `InMemoryListener`.
+#[derive(Debug)]
+pub struct InMemoryTransport {
+    sender: Option<Sender<(u8, Vec<u8>)>>,
+    receiver: Receiver<(u8, Vec<u8>)>,
+    connector: Sender<InMemoryTransport>,
+    timeout: u32,
+}
+
+impl InMemoryTransport {
+    // Makes a new pair of connected transports, passing the other end to a listener.
+    fn connect(connector: &Sender<InMemoryTransport>, timeout: u32) -> InMemoryTransport {
+        let (local_sender, remote_receiver) = channel();
+        let (remote_sender, local_receiver) = channel();
+
+        let remote = InMemoryTransport {
+            sender: Some(remote_sender),
+            receiver: remote_receiver,
+            connector: connector.clone(),
+            timeout: timeout,
+        };
+        match connector.send(remote) {
+            Ok(()) => (),
+            Err(_) => debug!("in-memory listener is gone, the connection will not be answered"),
+        };
+
+        InMemoryTransport {
+            sender: Some(local_sender),
+            receiver: local_receiver,
+            connector: connector.clone(),
+            timeout: timeout,
+        }
+    }
+}
+
+impl Transport for InMemoryTransport {
+    fn send(&mut self, code: u8, message: &[u8]) -> Result<(), RiakErr> {
+        let sent = match self.sender {
+            Some(ref sender) => sender.send((code, message.to_vec())).is_ok(),
+            None => false,
+        };
+        if sent {
+            Ok(())
+        } else {
+            Err(RiakErr::IoError(io::Error::new(io::ErrorKind::BrokenPipe, "connection closed")))
+        }
+    }
+
+    fn receive(&mut self) -> Result<(u8, Vec<u8>), RiakErr> {
+        match self.receiver.recv_timeout(Duration::new(self.timeout as u64, 0)) {
+            Ok(received) => Ok(received),
+            Err(RecvTimeoutError::Timeout) => {
+                Err(RiakErr::IoError(io::Error::new(io::ErrorKind::TimedOut,
+                                                    "timed out waiting for a message")))
+            }
+            Err(RecvTimeoutError::Disconnected) => {
+                Err(RiakErr::IoError(io::Error::new(io::ErrorKind::UnexpectedEof,
+                                                    "connection closed")))
+            }
+        }
+    }
+
+    fn duplicate(&self) -> Result<InMemoryTransport, RiakErr> {
+        Ok(InMemoryTransport::connect(&self.connector, self.timeout))
+    }
+
+    fn shutdown(&mut self) -> Result<(), RiakErr> {
+        self.sender = None;
+        Ok(())
+    }
+
+    fn get_timeout(&self) -> u32 {
+        self.timeout
+    }
+}
+
+/// `Direction` is whether a recorded message was sent or received
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    Sent,
+    Received,
+}
+
+/// `RecordedMessage` is a message sent or received through a `RecordingTransport`
+#[derive(Clone, Debug)]
+pub struct RecordedMessage {
+    pub direction: Direction,
+    pub code: u8,
+    pub message: Vec<u8>,
+    pub at: Instant,
+}
+
+/// `Recording` holds the messages recorded by a `RecordingTransport` and the transports
+/// duplicated from it, in the order they were sent or received
+#[derive(Clone, Debug)]
+pub struct Recording(Arc<Mutex<Vec<RecordedMessage>>>);
+
+impl Recording {
+    /// get the messages recorded so far
+    pub fn get_messages(&self) -> Vec<RecordedMessage> {
+        match self.0.lock() {
+            Ok(messages) => messages.clone(),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    /// discards the messages recorded so far
+    pub fn clear(&self) {
+        if let Ok(mut messages) = self.0.lock() {
+            messages.clear();
+        }
+    }
+
+    fn record(&self, direction: Direction, code: u8, message: &[u8]) {
+        if let Ok(mut messages) = self.0.lock() {
+            messages.push(RecordedMessage {
+                direction: direction,
+                code: code,
+                message: message.to_vec(),
+                at: Instant::now(),
+            });
+        }
+    }
+}
+
+/// `RecordingTransport` wraps another `Transport` and records every message that passes through
+/// it successfully
+///
+/// # Examples
+///
+/// ```
+/// use riak::Client;
+/// use riak::transport::{Direction, RecordingTransport, TcpTransport};
+///
+/// let transport = RecordingTransport::new(TcpTransport::new("10.0.0.2:8087", 3600).unwrap());
+/// let recording = transport.get_recording();
+///
+/// let mut riak = Client::from_transport(transport);
+/// riak.ping().unwrap();
+///
+/// let messages = recording.get_messages();
+/// assert_eq!(messages[0].direction, Direction::Sent);
+/// assert_eq!(messages[1].direction, Direction::Received);
+/// ```
+#[derive(Debug)]
+pub struct RecordingTransport<T: Transport> {
+    inner: T,
+    recording: Recording,
+}
+
+impl<T: Transport> RecordingTransport<T> {
+    /// constructs a new `RecordingTransport` around another transport
+    pub fn new(inner: T) -> RecordingTransport<T> {
+        RecordingTransport {
+            inner: inner,
+            recording: Recording(Arc::new(Mutex::new(Vec::new()))),
+        }
+    }
+
+    /// get the `Recording` of the messages passing through this transport
+    pub fn get_recording(&self) -> Recording {
+        self.recording.clone()
+    }
+
+    /// get the transport being recorded
+    pub fn get_inner(&self) -> &T {
+        &self.inner
+    }
+}
+
+impl<T: Transport> Transport for RecordingTransport<T> {
+    fn send(&mut self, code: u8, message: &[u8]) -> Result<(), RiakErr> {
+        match self.inner.send(code, message) {
+            Ok(()) => {
+                self.recording.record(Direction::Sent, code, message);
+                Ok(())
+            }
+            Err(error) => Err(error),
+        }
+    }
+
+    fn receive(&mut self) -> Result<(u8, Vec<u8>), RiakErr> {
+        match self.inner.receive() {
+            Ok((code, message)) => {
+                self.recording.record(Direction::Received, code, &message);
+                Ok((code, message))
+            }
+            Err(error) => Err(error),
+        }
+    }
+
+    fn duplicate(&self) -> Result<RecordingTransport<T>, RiakErr> {
+        match self.inner.duplicate() {
+            Ok(inner) => {
+                Ok(RecordingTransport {
+                    inner: inner,
+                    recording: self.recording.clone(),
+                })
+            }
+            Err(error) => Err(error),
+        }
+    }
+
+    fn shutdown(&mut self) -> Result<(), RiakErr> {
+        self.inner.shutdown()
+    }
+
+    fn get_timeout(&self) -> u32 {
+        self.inner.get_timeout()
+    }
+}
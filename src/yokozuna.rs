@@ -3,6 +3,8 @@ use private_traits::YokozunaIndexPrivate;
 use protobuf::{Message, RepeatedField};
 use rpb::riak_yokozuna::{RpbYokozunaIndex, RpbYokozunaIndexPutReq};
 use rpb::riak_search::RpbSearchQueryReq;
+use rpb::utils::RpbGenerator;
+use std::collections::HashMap;
 
 pub struct YokozunaIndex(RpbYokozunaIndex);
 
@@ -211,3 +213,52 @@ impl SearchQuery {
         self.0.set_presort(presort.into());
     }
 }
+
+impl RpbGenerator for SearchQuery {
+    fn write_to_bytes(&self) -> Result<Vec<u8>, RiakErr> {
+        match self.0.write_to_bytes() {
+            Ok(bytes) => Ok(bytes),
+            Err(error) => Err(RiakErr::ProtobufError(error)),
+        }
+    }
+}
+
+/// `SearchResults` represents the documents matched by a `SearchQuery`.
+///
+/// Each document is a map of field names to the values found for that field, multi-valued
+/// fields will have more than one value.
+#[derive(Clone, Debug)]
+pub struct SearchResults {
+    docs: Vec<HashMap<Vec<u8>, Vec<Vec<u8>>>>,
+    max_score: Option<f32>,
+    num_found: Option<u32>,
+}
+
+impl SearchResults {
+    /// constructs a new `SearchResults`
+    pub fn new(docs: Vec<HashMap<Vec<u8>, Vec<Vec<u8>>>>,
+               max_score: Option<f32>,
+               num_found: Option<u32>)
+               -> SearchResults {
+        SearchResults {
+            docs: docs,
+            max_score: max_score,
+            num_found: num_found,
+        }
+    }
+
+    /// get the documents found by the query
+    pub fn get_docs(&self) -> Vec<HashMap<Vec<u8>, Vec<Vec<u8>>>> {
+        self.docs.clone()
+    }
+
+    /// get the highest score of any document found by the query
+    pub fn get_max_score(&self) -> Option<f32> {
+        self.max_score
+    }
+
+    /// get the total number of documents matching the query
+    pub fn get_num_found(&self) -> Option<u32> {
+        self.num_found
+    }
+}
@@ -0,0 +1,223 @@
+#![cfg(feature = "async")]
+
+extern crate bytes;
+extern crate futures;
+extern crate riak;
+extern crate tokio;
+extern crate tokio_util;
+
+use bytes::BytesMut;
+use futures::future::join;
+use futures::TryStreamExt;
+use riak::async_client::{AsyncClient, RiakCodec};
+use riak::errors::RiakErr;
+use riak::index::IndexQuery;
+use riak::object::FetchObjectReq;
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+use tokio::runtime::{Builder, Runtime};
+use tokio_util::codec::{Decoder, Encoder};
+
+fn read_frame(stream: &mut TcpStream) -> Option<(u8, Vec<u8>)> {
+    let mut header = [0u8; 5];
+    if stream.read_exact(&mut header).is_err() {
+        return None;
+    }
+    let len = ((header[0] as usize) << 24) | ((header[1] as usize) << 16) |
+              ((header[2] as usize) << 8) | (header[3] as usize);
+    let mut body = vec![0u8; len - 1];
+    if stream.read_exact(&mut body).is_err() {
+        return None;
+    }
+    Some((header[4], body))
+}
+
+fn write_frame(stream: &mut TcpStream, code: u8, body: &[u8]) {
+    let len = (body.len() + 1) as u32;
+    let mut frame = vec![(len >> 24) as u8, (len >> 16) as u8, (len >> 8) as u8, len as u8, code];
+    frame.extend_from_slice(body);
+    stream.write_all(&frame).unwrap();
+}
+
+// Starts a stand-in server that hands each connection to `serve`.
+fn start_server(serve: fn(TcpStream)) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap().to_string();
+    thread::spawn(move || for stream in listener.incoming() {
+        let stream = stream.unwrap();
+        thread::spawn(move || serve(stream));
+    });
+    addr
+}
+
+fn runtime() -> Runtime {
+    Builder::new_current_thread().enable_io().build().unwrap()
+}
+
+#[test]
+fn test_requests_are_pipelined() {
+    let addr = start_server(|mut stream| {
+        // both requests arrive before either is answered
+        let (first, _) = read_frame(&mut stream).unwrap();
+        let (second, _) = read_frame(&mut stream).unwrap();
+        assert_eq!((first, second), (1, 9));
+
+        write_frame(&mut stream, 2, &[]);
+        // an RpbGetResp with a single sibling holding "value"
+        write_frame(&mut stream,
+                    10,
+                    &[0x0a, 0x07, 0x0a, 0x05, b'v', b'a', b'l', b'u', b'e']);
+    });
+
+    let runtime = runtime();
+    let riak = runtime.block_on(AsyncClient::connect(addr)).unwrap();
+
+    // clones share the connection
+    let other = riak.clone();
+    let ping = riak.ping();
+    let fetch = other.fetch_object(&FetchObjectReq::new("testbucket", "testkey"));
+    let (ping, fetch) = runtime.block_on(join(ping, fetch));
+
+    ping.unwrap();
+    let content = fetch.unwrap().get_content();
+    assert_eq!(content.len(), 1);
+    assert_eq!(content[0].get_value(), b"value".to_vec());
+}
+
+#[test]
+fn test_server_errors_are_returned() {
+    let addr = start_server(|mut stream| {
+        read_frame(&mut stream).unwrap();
+        // RpbErrorResp with errmsg "overload" and errcode 1
+        let mut error = vec![0x0a, 0x08];
+        error.extend_from_slice(b"overload");
+        error.extend_from_slice(&[0x10, 0x01]);
+        write_frame(&mut stream, 0, &error);
+
+        // the connection is still usable afterwards
+        read_frame(&mut stream).unwrap();
+        write_frame(&mut stream, 2, &[]);
+    });
+
+    let runtime = runtime();
+    let riak = runtime.block_on(AsyncClient::connect(addr)).unwrap();
+
+    match runtime.block_on(riak.ping()) {
+        Err(RiakErr::ServerError(error)) => assert!(error.is_overload()),
+        other => panic!("expected a server error, got {:?}", other),
+    };
+    runtime.block_on(riak.ping()).unwrap();
+}
+
+#[test]
+fn test_stream_keys() {
+    let addr = start_server(|mut stream| {
+        let (code, body) = read_frame(&mut stream).unwrap();
+        assert_eq!(code, 17);
+        assert!(body.windows(6).any(|window| window == b"mytype"));
+
+        // RpbListKeysResp frames, the last one has done set
+        write_frame(&mut stream, 18, &[0x0a, 0x01, b'a', 0x0a, 0x01, b'b']);
+        write_frame(&mut stream, 18, &[0x0a, 0x01, b'c', 0x10, 0x01]);
+
+        read_frame(&mut stream).unwrap();
+        write_frame(&mut stream, 2, &[]);
+    });
+
+    let runtime = runtime();
+    let riak = runtime.block_on(AsyncClient::connect(addr)).unwrap();
+
+    let mut keystream = riak.stream_keys("testbucket");
+    keystream.set_bucket_type("mytype");
+    let keys: Vec<Vec<u8>> = runtime.block_on(keystream.try_concat()).unwrap();
+    assert_eq!(keys, vec![b"a".to_vec(), b"b".to_vec(), b"c".to_vec()]);
+
+    runtime.block_on(riak.ping()).unwrap();
+}
+
+#[test]
+fn test_stream_keys_default_timeout() {
+    let addr = start_server(|mut stream| {
+        let (code, body) = read_frame(&mut stream).unwrap();
+        assert_eq!(code, 17);
+        // timeout (field 2) of the client's 3600 seconds, as 3600000 milliseconds
+        assert!(body.windows(5).any(|window| window == [0x10, 0x80, 0xdd, 0xdb, 0x01]));
+        write_frame(&mut stream, 18, &[0x0a, 0x01, b'a', 0x10, 0x01]);
+    });
+
+    let runtime = runtime();
+    let riak = runtime.block_on(AsyncClient::connect(addr)).unwrap();
+
+    let keys: Vec<Vec<u8>> = runtime.block_on(riak.stream_keys("testbucket").try_concat()).unwrap();
+    assert_eq!(keys, vec![b"a".to_vec()]);
+}
+
+#[test]
+fn test_index_queries_return_body() {
+    let addr = start_server(|mut stream| {
+        // an RpbIndexObject with key "a" and a single sibling holding "v"
+        let object = [0x0a, 0x0a, 0x0a, 0x01, b'a', 0x12, 0x05, 0x0a, 0x03, 0x0a, 0x01, b'v'];
+
+        // Riak answers queries with "return_body" with RpbIndexBodyResp
+        read_frame(&mut stream).unwrap();
+        write_frame(&mut stream, 42, &object);
+
+        // streamed, the last RpbIndexBodyResp has done set
+        read_frame(&mut stream).unwrap();
+        write_frame(&mut stream, 42, &object);
+        write_frame(&mut stream, 42, &[0x18, 0x01]);
+
+        read_frame(&mut stream).unwrap();
+        write_frame(&mut stream, 2, &[]);
+    });
+
+    let runtime = runtime();
+    let riak = runtime.block_on(AsyncClient::connect(addr)).unwrap();
+
+    let mut query = IndexQuery::new_range("testbucket", "$key", "a", "b");
+    query.set_return_body(true);
+    let results = runtime.block_on(riak.query_index(&query)).unwrap();
+    let objects = results.get_objects();
+    assert_eq!(objects.len(), 1);
+    assert_eq!(objects[0].0, b"a".to_vec());
+    assert_eq!(objects[0].1.get_content()[0].get_value(), b"v".to_vec());
+
+    let pages: Vec<_> = runtime.block_on(riak.stream_index(&query).try_collect()).unwrap();
+    assert_eq!(pages.len(), 2);
+    assert_eq!(pages[0].get_objects().len(), 1);
+
+    runtime.block_on(riak.ping()).unwrap();
+}
+
+#[test]
+fn test_closed_connection_fails_pending_requests() {
+    let addr = start_server(|mut stream| {
+        read_frame(&mut stream).unwrap();
+    });
+
+    let runtime = runtime();
+    let riak = runtime.block_on(AsyncClient::connect(addr)).unwrap();
+
+    match runtime.block_on(riak.ping()) {
+        Err(RiakErr::IoError(_)) => (),
+        other => panic!("expected an I/O error, got {:?}", other),
+    };
+    assert!(runtime.block_on(riak.ping()).is_err());
+}
+
+#[test]
+fn test_codec_round_trip() {
+    let mut codec = RiakCodec::new();
+    let mut buffer = BytesMut::new();
+    codec.encode((9, b"request".to_vec()), &mut buffer).unwrap();
+    assert_eq!(&buffer[..5], &[0, 0, 0, 8, 9]);
+
+    // a partial frame is left in the buffer
+    let mut partial = buffer.split_to(6);
+    assert_eq!(codec.decode(&mut partial).unwrap(), None);
+
+    partial.unsplit(buffer);
+    assert_eq!(codec.decode(&mut partial).unwrap(), Some((9, b"request".to_vec())));
+    assert!(partial.is_empty());
+}
@@ -0,0 +1,154 @@
+#![cfg(feature = "testing")]
+
+extern crate riak;
+
+use riak::cluster::{Cluster, ClusterConfig, LoadBalancing};
+use riak::errors::RiakErr;
+use riak::object::{ObjectContent, StoreObjectReq};
+use riak::pool::PoolConfig;
+use riak::testing::{Fault, MockServer, codes};
+use std::net::SocketAddr;
+use std::thread;
+use std::time::Duration;
+
+fn cluster_config(load_balancing: LoadBalancing) -> ClusterConfig {
+    // health checks would ping idle connections and use up the faults meant for requests
+    let mut pool_config = PoolConfig::new();
+    pool_config.set_health_check(false);
+
+    let mut config = ClusterConfig::new();
+    config.set_load_balancing(load_balancing);
+    config.set_probe_interval(Duration::from_secs(3600));
+    config.set_pool_config(pool_config);
+    config
+}
+
+fn store_keys(cluster: &Cluster, keys: &[&str]) {
+    for key in keys {
+        let mut req = StoreObjectReq::new("testbucket", ObjectContent::new("value"));
+        req.set_key(*key);
+        cluster.store_object(&req).unwrap();
+    }
+}
+
+fn key_count(server: &MockServer) -> usize {
+    server.client().unwrap().list_keys("testbucket").unwrap().len()
+}
+
+fn stopped_addr() -> SocketAddr {
+    let server = MockServer::start().unwrap();
+    let addr = server.get_addr();
+    drop(server);
+    // give the server a moment to stop listening
+    thread::sleep(Duration::from_millis(100));
+    addr
+}
+
+#[test]
+fn test_round_robin() {
+    let first = MockServer::start().unwrap();
+    let second = MockServer::start().unwrap();
+    let addrs = [first.get_addr(), second.get_addr()];
+    let cluster = Cluster::new(&addrs, cluster_config(LoadBalancing::RoundRobin)).unwrap();
+
+    store_keys(&cluster, &["a", "b", "c", "d"]);
+    assert_eq!(key_count(&first), 2);
+    assert_eq!(key_count(&second), 2);
+}
+
+#[test]
+fn test_least_outstanding() {
+    let busy = MockServer::start().unwrap();
+    let idle = MockServer::start().unwrap();
+    let addrs = [busy.get_addr(), idle.get_addr()];
+    let cluster = Cluster::new(&addrs, cluster_config(LoadBalancing::LeastOutstanding)).unwrap();
+
+    // keep a slow request in flight on the first node
+    busy.inject_fault(codes::RpbPingReq, Fault::Delay(Duration::from_millis(500)));
+    let pinging = cluster.clone();
+    let handle = thread::spawn(move || pinging.ping());
+    thread::sleep(Duration::from_millis(100));
+
+    store_keys(&cluster, &["a", "b", "c"]);
+    handle.join().unwrap().unwrap();
+    assert_eq!(key_count(&busy), 0);
+    assert_eq!(key_count(&idle), 3);
+}
+
+#[test]
+fn test_failover_to_running_node() {
+    let running = MockServer::start().unwrap();
+    let addrs = [stopped_addr(), running.get_addr()];
+    let cluster = Cluster::new(&addrs, cluster_config(LoadBalancing::RoundRobin)).unwrap();
+
+    store_keys(&cluster, &["a", "b", "c"]);
+    assert_eq!(key_count(&running), 3);
+    assert_eq!(cluster.nodes(), vec![(addrs[0], false), (addrs[1], true)]);
+}
+
+#[test]
+fn test_failover_and_probe_after_disconnect() {
+    let first = MockServer::start().unwrap();
+    let second = MockServer::start().unwrap();
+    let addrs = [first.get_addr(), second.get_addr()];
+    let mut config = cluster_config(LoadBalancing::RoundRobin);
+    config.set_probe_interval(Duration::from_millis(500));
+    let cluster = Cluster::new(&addrs, config).unwrap();
+
+    // the first request goes to the first node, which drops the connection
+    first.inject_fault(codes::RpbPutReq, Fault::Disconnect);
+    store_keys(&cluster, &["a"]);
+    assert_eq!(key_count(&first), 0);
+    assert_eq!(key_count(&second), 1);
+    assert_eq!(cluster.nodes(), vec![(addrs[0], false), (addrs[1], true)]);
+
+    // requests avoid the node that is down until the probe interval has passed
+    store_keys(&cluster, &["b", "c"]);
+    assert_eq!(key_count(&second), 3);
+
+    // the next request probes the node, which rejoins
+    thread::sleep(Duration::from_millis(600));
+    store_keys(&cluster, &["d", "e"]);
+    assert_eq!(cluster.nodes(), vec![(addrs[0], true), (addrs[1], true)]);
+    assert_eq!(key_count(&first), 1);
+}
+
+#[test]
+fn test_retries_are_limited() {
+    let first = MockServer::start().unwrap();
+    let second = MockServer::start().unwrap();
+    let addrs = [first.get_addr(), second.get_addr()];
+    let mut config = cluster_config(LoadBalancing::RoundRobin);
+    config.set_max_retries(0);
+    let cluster = Cluster::new(&addrs, config).unwrap();
+
+    first.inject_fault(codes::RpbPingReq, Fault::Disconnect);
+    match cluster.ping() {
+        Err(RiakErr::IoError(_)) => (),
+        other => panic!("expected an I/O error, got {:?}", other),
+    };
+    cluster.ping().unwrap();
+}
+
+#[test]
+fn test_no_nodes_available() {
+    let addrs = [stopped_addr(), stopped_addr()];
+    let cluster = Cluster::new(&addrs, cluster_config(LoadBalancing::RoundRobin)).unwrap();
+
+    // the first request finds every node down, later ones don't try them until they're probed
+    match cluster.ping() {
+        Err(RiakErr::IoError(_)) => (),
+        other => panic!("expected an I/O error, got {:?}", other),
+    };
+    assert_eq!(cluster.nodes(), vec![(addrs[0], false), (addrs[1], false)]);
+    match cluster.ping() {
+        Err(RiakErr::NoNodesAvailable) => (),
+        other => panic!("expected no nodes to be available, got {:?}", other),
+    };
+
+    let empty: [SocketAddr; 0] = [];
+    match Cluster::new(&empty, ClusterConfig::new()) {
+        Err(RiakErr::NoNodesAvailable) => (),
+        other => panic!("expected no nodes to be available, got {:?}", other),
+    };
+}
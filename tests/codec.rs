@@ -0,0 +1,116 @@
+#![cfg(all(feature = "serde", feature = "testing"))]
+
+extern crate riak;
+#[macro_use]
+extern crate serde_derive;
+
+use riak::Client;
+use riak::codec::{CborCodec, Codec, JsonCodec, MessagePackCodec, decode_content};
+use riak::errors::RiakErr;
+use riak::object::{FetchObjectReq, ObjectContent, StoreObjectReq};
+use riak::testing::MockServer;
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct User {
+    name: String,
+    age: u32,
+    tags: Vec<String>,
+}
+
+fn alice() -> User {
+    User {
+        name: "Alice".to_string(),
+        age: 30,
+        tags: vec!["admin".to_string()],
+    }
+}
+
+fn store_typed<D: Codec>(riak: &mut Client, key: &str, codec: &D) {
+    let mut req = StoreObjectReq::new("users", ObjectContent::new(Vec::new()));
+    req.set_key(key);
+    riak.store_typed(&req, &alice(), codec).unwrap();
+}
+
+#[test]
+fn test_codecs_round_trip() {
+    let server = MockServer::start().unwrap();
+    let mut riak = server.client().unwrap();
+    store_typed(&mut riak, "json", &JsonCodec);
+    store_typed(&mut riak, "cbor", &CborCodec);
+    store_typed(&mut riak, "msgpack", &MessagePackCodec);
+
+    // each object is decoded with the codec it was stored with
+    for key in &["json", "cbor", "msgpack"] {
+        let user: Option<User> = riak.fetch_typed(&FetchObjectReq::new("users", *key)).unwrap();
+        assert_eq!(user, Some(alice()));
+    }
+
+    let resp = riak.fetch_object(&FetchObjectReq::new("users", "json")).unwrap();
+    let content = &resp.get_content()[0];
+    assert_eq!(content.get_content_type(), Some(b"application/json".to_vec()));
+    assert_eq!(content.get_charset(), Some(b"UTF-8".to_vec()));
+
+    let resp = riak.fetch_object(&FetchObjectReq::new("users", "msgpack")).unwrap();
+    let content = &resp.get_content()[0];
+    assert_eq!(content.get_content_type(), Some(b"application/msgpack".to_vec()));
+    assert_eq!(content.get_charset(), None);
+}
+
+#[test]
+fn test_store_typed_keeps_metadata() {
+    let server = MockServer::start().unwrap();
+    let mut riak = server.client().unwrap();
+
+    let mut content = ObjectContent::new("replaced");
+    content.add_usermeta("team", "red");
+    let mut req = StoreObjectReq::new("users", content);
+    req.set_key("alice");
+    riak.store_typed(&req, &alice(), &CborCodec).unwrap();
+
+    let resp = riak.fetch_object(&FetchObjectReq::new("users", "alice")).unwrap();
+    let content = &resp.get_content()[0];
+    assert_eq!(content.get_usermeta().get(&b"team"[..]), Some(&b"red".to_vec()));
+    assert_eq!(decode_content::<User>(content).unwrap(), alice());
+}
+
+#[test]
+fn test_fetch_typed_missing_and_undecodable() {
+    let server = MockServer::start().unwrap();
+    let mut riak = server.client().unwrap();
+
+    let user: Option<User> = riak.fetch_typed(&FetchObjectReq::new("users", "nobody")).unwrap();
+    assert_eq!(user, None);
+
+    // objects without a content type of one of the codecs can't be decoded
+    let mut req = StoreObjectReq::new("users", ObjectContent::new("plain"));
+    req.set_key("plain");
+    riak.store_object(&req).unwrap();
+    match riak.fetch_typed::<User>(&FetchObjectReq::new("users", "plain")) {
+        Err(RiakErr::CodecError(_)) => (),
+        other => panic!("expected a codec error, got {:?}", other),
+    };
+
+    let mut content = ObjectContent::new("{not json");
+    content.set_content_type("application/json; charset=utf-8");
+    assert!(decode_content::<User>(&content).is_err());
+}
+
+#[test]
+fn test_fetch_typed_siblings() {
+    let server = MockServer::start().unwrap();
+    let mut riak = server.client().unwrap();
+
+    for _ in 0..2 {
+        let mut req = StoreObjectReq::new("users", ObjectContent::new(Vec::new()));
+        req.set_bucket_type("siblings");
+        req.set_key("alice");
+        riak.store_typed(&req, &alice(), &JsonCodec).unwrap();
+    }
+
+    let mut req = FetchObjectReq::new("users", "alice");
+    req.set_bucket_type("siblings");
+    match riak.fetch_typed::<User>(&req) {
+        Err(RiakErr::CodecError(message)) => assert!(message.contains("2 siblings")),
+        other => panic!("expected a codec error, got {:?}", other),
+    };
+}
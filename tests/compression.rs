@@ -0,0 +1,204 @@
+#![cfg(all(feature = "compression", feature = "testing"))]
+
+extern crate riak;
+
+use riak::Client;
+use riak::compression::{Compression, CompressionConfig, decompress_contents};
+use riak::errors::RiakErr;
+use riak::object::{FetchObjectReq, ObjectContent, StoreObjectReq};
+use riak::resolver::LongestValue;
+use riak::testing::MockServer;
+
+fn large_value() -> Vec<u8> {
+    "{\"blob\": \"riak\"}".repeat(200).into_bytes()
+}
+
+fn store(riak: &mut Client, key: &str, content: ObjectContent) {
+    let mut req = StoreObjectReq::new("testbucket", content);
+    req.set_key(key);
+    riak.store_object(&req).unwrap();
+}
+
+// Reads an object as Riak stores it, `fold_objects` leaves values compressed.
+fn stored_content(riak: &mut Client, key: &str) -> ObjectContent {
+    let mut fold = riak.fold_objects("testbucket", key, key).unwrap();
+    fold.set_end_incl(true);
+    let (_, object) = fold.next().unwrap().unwrap();
+    object.get_content()[0].clone()
+}
+
+fn fetched_content(riak: &mut Client, key: &str) -> ObjectContent {
+    let resp = riak.fetch_object(&FetchObjectReq::new("testbucket", key)).unwrap();
+    resp.get_content()[0].clone()
+}
+
+#[test]
+fn test_values_compressed_above_threshold() {
+    let server = MockServer::start().unwrap();
+    let mut riak = server.client().unwrap();
+
+    let algorithms: Vec<(Compression, &str, &[u8])> =
+        vec![(Compression::Gzip, "gzip", &[0x1f, 0x8b]),
+             (Compression::Zstd, "zstd", &[0x28, 0xb5, 0x2f, 0xfd]),
+             (Compression::Snappy, "snappy", &[0xff, 0x06, 0x00, 0x00])];
+    for (compression, encoding, magic) in algorithms {
+        riak.set_compression(CompressionConfig::new(compression, 1024));
+        store(&mut riak, encoding, ObjectContent::new(large_value()));
+
+        let stored = stored_content(&mut riak, encoding);
+        assert_eq!(stored.get_content_encoding(), Some(encoding.as_bytes().to_vec()));
+        assert!(stored.get_value().starts_with(magic));
+        assert!(stored.get_value().len() < large_value().len());
+
+        let fetched = fetched_content(&mut riak, encoding);
+        assert_eq!(fetched.get_content_encoding(), None);
+        assert_eq!(fetched.get_value(), large_value());
+    }
+
+    // values below the threshold are stored as they are
+    store(&mut riak, "small", ObjectContent::new("small"));
+    assert_eq!(stored_content(&mut riak, "small").get_content_encoding(), None);
+}
+
+#[test]
+fn test_fetch_decompresses_without_compression_enabled() {
+    let server = MockServer::start().unwrap();
+    let mut writer = server.client().unwrap();
+    writer.set_compression(CompressionConfig::new(Compression::Zstd, 0));
+    store(&mut writer, "testkey", ObjectContent::new(large_value()));
+
+    let mut reader = server.client().unwrap();
+    assert_eq!(fetched_content(&mut reader, "testkey").get_value(), large_value());
+
+    // values read by other requests can be decompressed the same way
+    let stored = stored_content(&mut reader, "testkey");
+    let decompressed = decompress_contents(&[stored]).unwrap();
+    assert_eq!(decompressed[0].get_value(), large_value());
+}
+
+#[test]
+fn test_existing_encodings_left_alone() {
+    let server = MockServer::start().unwrap();
+    let mut riak = server.client().unwrap();
+    riak.set_compression(CompressionConfig::new(Compression::Gzip, 0));
+
+    // an encoding that is not recognised is neither compressed again nor decompressed
+    let mut content = ObjectContent::new(large_value());
+    content.set_content_encoding("br");
+    store(&mut riak, "brotli", content);
+    let fetched = fetched_content(&mut riak, "brotli");
+    assert_eq!(fetched.get_content_encoding(), Some(b"br".to_vec()));
+    assert_eq!(fetched.get_value(), large_value());
+
+    // values already compressed by the caller are decompressed on fetch
+    let compressed = Compression::Gzip.compress(&large_value()).unwrap();
+    let mut content = ObjectContent::new(compressed);
+    content.set_content_encoding("x-gzip");
+    store(&mut riak, "gzipped", content);
+    assert_eq!(fetched_content(&mut riak, "gzipped").get_value(), large_value());
+}
+
+#[test]
+fn test_return_body_decompressed() {
+    let server = MockServer::start().unwrap();
+    let mut riak = server.client().unwrap();
+    riak.set_compression(CompressionConfig::new(Compression::Snappy, 16));
+
+    let mut req = StoreObjectReq::new("testbucket", ObjectContent::new(large_value()));
+    req.set_key("testkey");
+    req.set_return_body(true);
+    let resp = riak.store_object(&req).unwrap();
+    assert_eq!(resp.get_content()[0].get_value(), large_value());
+    assert_eq!(resp.get_content()[0].get_content_encoding(), None);
+}
+
+#[test]
+fn test_head_responses_keep_encoding() {
+    let server = MockServer::start().unwrap();
+    let mut riak = server.client().unwrap();
+
+    for compression in &[Compression::Gzip, Compression::Zstd, Compression::Snappy] {
+        riak.set_compression(CompressionConfig::new(*compression, 16));
+        let encoding = compression.get_content_encoding();
+
+        // only the metadata is returned, the empty value is not decompressed
+        let mut req = StoreObjectReq::new("testbucket", ObjectContent::new(large_value()));
+        req.set_key(encoding);
+        req.set_return_head(true);
+        let stored = riak.store_object(&req).unwrap();
+        assert!(stored.get_content()[0].get_value().is_empty());
+        assert_eq!(stored.get_content()[0].get_content_encoding(),
+                   Some(encoding.as_bytes().to_vec()));
+
+        let mut req = FetchObjectReq::new("testbucket", encoding);
+        req.set_head(true);
+        let fetched = riak.fetch_object(&req).unwrap();
+        assert!(fetched.get_content()[0].get_value().is_empty());
+        assert_eq!(fetched.get_content()[0].get_content_encoding(),
+                   Some(encoding.as_bytes().to_vec()));
+
+        let mut req = FetchObjectReq::new("testbucket", encoding);
+        req.set_if_modified(fetched.get_vclock());
+        assert_eq!(riak.fetch_object(&req).unwrap().get_unchanged(), Some(true));
+    }
+}
+
+#[test]
+fn test_update_object_compressed() {
+    let server = MockServer::start().unwrap();
+    let mut riak = server.client().unwrap();
+    riak.set_compression(CompressionConfig::new(Compression::Gzip, 16));
+
+    for _ in 0..2 {
+        riak.update_object("testbucket", "testkey", |current| {
+            let mut value = current.map(|content| content.get_value()).unwrap_or_default();
+            value.extend_from_slice(&large_value());
+            ObjectContent::new(value)
+        }).unwrap();
+    }
+
+    let mut expected = large_value();
+    expected.extend_from_slice(&large_value());
+    assert_eq!(fetched_content(&mut riak, "testkey").get_value(), expected);
+    assert_eq!(stored_content(&mut riak, "testkey").get_content_encoding(),
+               Some(b"gzip".to_vec()));
+}
+
+#[test]
+fn test_fetch_resolved_compressed() {
+    let server = MockServer::start().unwrap();
+    let mut riak = server.client().unwrap();
+    riak.set_compression(CompressionConfig::new(Compression::Zstd, 16));
+
+    // siblings in a bucket type that allows them
+    for value in &[large_value(), b"short".to_vec()] {
+        let mut req = StoreObjectReq::new("testbucket", ObjectContent::new(value.clone()));
+        req.set_bucket_type("mytype");
+        req.set_key("testkey");
+        riak.store_object(&req).unwrap();
+    }
+
+    let mut req = FetchObjectReq::new("testbucket", "testkey");
+    req.set_bucket_type("mytype");
+    let resolved = riak.fetch_resolved(&req, &LongestValue, true).unwrap();
+    assert_eq!(resolved.get_content()[0].get_value(), large_value());
+
+    let fetched = riak.fetch_object(&req).unwrap();
+    assert_eq!(fetched.get_content().len(), 1);
+    assert_eq!(fetched.get_content()[0].get_value(), large_value());
+}
+
+#[test]
+fn test_corrupt_value() {
+    let server = MockServer::start().unwrap();
+    let mut riak = server.client().unwrap();
+
+    let mut content = ObjectContent::new("not gzip");
+    content.set_content_encoding("gzip");
+    store(&mut riak, "corrupt", content);
+
+    match riak.fetch_object(&FetchObjectReq::new("testbucket", "corrupt")) {
+        Err(RiakErr::CompressionError(_)) => (),
+        other => panic!("expected a compression error, got {:?}", other),
+    };
+}
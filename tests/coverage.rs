@@ -0,0 +1,84 @@
+#![cfg(feature = "testing")]
+
+extern crate riak;
+
+use riak::Client;
+use riak::coverage::CoverageScanner;
+use riak::object::{ObjectContent, StoreObjectReq};
+use riak::testing::{Fault, MockServer, codes};
+
+fn store_keys(riak: &mut Client, count: usize) -> Vec<Vec<u8>> {
+    let mut keys = Vec::new();
+    for i in 0..count {
+        let key = format!("key{:03}", i);
+        let mut req = StoreObjectReq::new("testbucket", ObjectContent::new("value"));
+        req.set_key(key.as_str());
+        riak.store_object(&req).unwrap();
+        keys.push(key.into_bytes());
+    }
+    keys
+}
+
+#[test]
+fn test_coverage_plan() {
+    let server = MockServer::start().unwrap();
+    let mut riak = server.client().unwrap();
+
+    let plan = riak.coverage_plan("default", "testbucket", 8).unwrap();
+    assert_eq!(plan.len(), 8);
+    for entry in &plan {
+        assert_eq!(entry.port, server.get_addr().port());
+        assert!(!entry.cover_context.is_empty());
+    }
+
+    let replacements = riak.replace_cover("default",
+                                          "testbucket",
+                                          &plan[0].cover_context,
+                                          &[plan[0].cover_context.clone()])
+        .unwrap();
+    assert_eq!(replacements.len(), 1);
+}
+
+#[test]
+fn test_scanner_lists_every_key() {
+    let server = MockServer::start().unwrap();
+    let mut riak = server.client().unwrap();
+    let mut expected = store_keys(&mut riak, 50);
+
+    let mut scanner = CoverageScanner::new("default", "testbucket");
+    scanner.set_min_partitions(4);
+    scanner.set_page_size(3);
+    let mut keys = scanner.list_keys(&mut riak).unwrap();
+
+    keys.sort();
+    expected.sort();
+    assert_eq!(keys, expected);
+}
+
+#[test]
+fn test_scanner_replaces_unavailable_covers() {
+    let server = MockServer::start().unwrap();
+    let mut riak = server.client().unwrap();
+    let mut expected = store_keys(&mut riak, 20);
+
+    // the first worker to query loses its connection, and its part is read again
+    server.inject_fault(codes::RpbIndexReq, Fault::Disconnect);
+    let scanner = CoverageScanner::new("default", "testbucket");
+    let mut keys = scanner.list_keys(&mut riak).unwrap();
+
+    keys.sort();
+    expected.sort();
+    assert_eq!(keys, expected);
+}
+
+#[test]
+fn test_scanner_gives_up_after_max_replacements() {
+    let server = MockServer::start().unwrap();
+    let mut riak = server.client().unwrap();
+    store_keys(&mut riak, 5);
+
+    server.inject_fault(codes::RpbIndexReq, Fault::ErrorResp("overload".to_string()));
+    let mut scanner = CoverageScanner::new("default", "testbucket");
+    scanner.set_max_replacements(0);
+    assert!(scanner.list_keys(&mut riak).is_err());
+}
@@ -0,0 +1,64 @@
+extern crate riak;
+
+use riak::errors::ServerError;
+
+// Encodes an `RpbErrorResp` with the given message and an error code of 1, as Riak sends them.
+fn error_resp(message: &str) -> ServerError {
+    let mut data = vec![0x0a, message.len() as u8];
+    data.extend_from_slice(message.as_bytes());
+    data.extend_from_slice(&[0x10, 0x01]);
+    ServerError::new(0, data)
+}
+
+#[test]
+fn test_decodes_error_resp() {
+    let error = error_resp("notfound");
+    assert_eq!(error.get_code(), 0);
+    assert_eq!(error.get_message(), "notfound");
+    assert_eq!(error.get_errcode(), Some(1));
+    assert_eq!(error.to_string(), "received error code 1, error was: notfound");
+
+    // other responses keep their raw data as the message
+    let error = ServerError::new(10, "unexpected");
+    assert_eq!(error.get_message(), "unexpected");
+    assert_eq!(error.get_errcode(), None);
+    assert_eq!(error.to_string(), "received code 10, error was: unexpected");
+}
+
+#[test]
+fn test_not_found() {
+    assert!(error_resp("notfound").is_not_found());
+    assert!(error_resp("Bucket type not found").is_not_found());
+    assert!(error_resp("{error,not_found}").is_not_found());
+    assert!(!error_resp("timeout").is_not_found());
+}
+
+#[test]
+fn test_timeout_and_overload() {
+    assert!(error_resp("timeout").is_timeout());
+    assert!(error_resp("{error,timeout}").is_timeout());
+    assert!(!error_resp("overload").is_timeout());
+
+    assert!(error_resp("overload").is_overload());
+    assert!(error_resp("{error,overload}").is_overload());
+    assert!(!error_resp("timeout").is_overload());
+}
+
+#[test]
+fn test_precondition_failed() {
+    assert!(error_resp("match_found").is_precondition_failed());
+    assert!(error_resp("modified").is_precondition_failed());
+    assert!(!error_resp("notfound").is_precondition_failed());
+    assert!(!error_resp("{precommit_fail,modified}").is_precondition_failed());
+}
+
+#[test]
+fn test_retryable() {
+    assert!(error_resp("timeout").is_retryable());
+    assert!(error_resp("overload").is_retryable());
+    assert!(error_resp("{insufficient_vnodes,1,need,2}").is_retryable());
+    assert!(error_resp("all_nodes_down").is_retryable());
+    assert!(!error_resp("notfound").is_retryable());
+    assert!(!error_resp("match_found").is_retryable());
+    assert!(!error_resp("modified").is_retryable());
+}
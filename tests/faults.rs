@@ -0,0 +1,126 @@
+#![cfg(feature = "testing")]
+
+extern crate riak;
+
+use riak::Client;
+use riak::errors::RiakErr;
+use riak::object::{FetchObjectReq, ObjectContent, StoreObjectReq};
+use riak::testing::{Fault, MockServer, codes};
+use std::time::Duration;
+
+fn store(riak: &mut Client, key: &str) {
+    let mut req = StoreObjectReq::new("testbucket", ObjectContent::new("value"));
+    req.set_key(key);
+    riak.store_object(&req).unwrap();
+}
+
+#[test]
+fn test_truncated_frame() {
+    let server = MockServer::start().unwrap();
+    server.inject_fault(codes::RpbGetReq, Fault::TruncatedFrame);
+
+    let mut riak = server.client().unwrap();
+    store(&mut riak, "testkey");
+    match riak.fetch_object(&FetchObjectReq::new("testbucket", "testkey")) {
+        Err(RiakErr::IoError(_)) => (),
+        other => panic!("expected an I/O error, got {:?}", other),
+    };
+
+    // the fault only applies once
+    riak.reconnect().unwrap();
+    riak.fetch_object(&FetchObjectReq::new("testbucket", "testkey")).unwrap();
+}
+
+#[test]
+fn test_wrong_response_code() {
+    let server = MockServer::start().unwrap();
+    server.inject_fault(codes::RpbPingReq, Fault::WrongResponseCode(codes::RpbGetResp));
+
+    let mut riak = server.client().unwrap();
+    match riak.ping() {
+        Err(RiakErr::ServerError(error)) => assert_eq!(error.get_code(), codes::RpbGetResp),
+        other => panic!("expected a server error, got {:?}", other),
+    };
+    riak.ping().unwrap();
+}
+
+#[test]
+fn test_error_responses_in_order() {
+    let server = MockServer::start().unwrap();
+    server.inject_fault(codes::RpbPutReq, Fault::ErrorResp("timeout".to_string()));
+    server.inject_fault(codes::RpbPutReq, Fault::ErrorResp("overload".to_string()));
+
+    let mut riak = server.client().unwrap();
+    let req = StoreObjectReq::new("testbucket", ObjectContent::new("value"));
+    match riak.store_object(&req) {
+        Err(RiakErr::ServerError(error)) => assert!(error.is_timeout()),
+        other => panic!("expected a timeout, got {:?}", other),
+    };
+    match riak.store_object(&req) {
+        Err(RiakErr::ServerError(error)) => assert!(error.is_overload()),
+        other => panic!("expected an overload error, got {:?}", other),
+    };
+    riak.store_object(&req).unwrap();
+
+    // the failed stores were never handled
+    assert_eq!(riak.list_keys("testbucket").unwrap().len(), 1);
+}
+
+#[test]
+fn test_slow_response_exceeds_timeout() {
+    let server = MockServer::start().unwrap();
+    server.inject_fault(codes::RpbPingReq, Fault::Delay(Duration::from_millis(1500)));
+
+    let mut riak = Client::new_with_timeout(server.get_addr(), 1).unwrap();
+    match riak.ping() {
+        Err(RiakErr::IoError(_)) => (),
+        other => panic!("expected the read to time out, got {:?}", other),
+    };
+}
+
+#[test]
+fn test_disconnect() {
+    let server = MockServer::start().unwrap();
+    server.inject_fault(codes::RpbPingReq, Fault::Disconnect);
+
+    let mut riak = server.client().unwrap();
+    assert!(riak.ping().is_err());
+    riak.reconnect().unwrap();
+    riak.ping().unwrap();
+}
+
+#[test]
+fn test_key_stream_interrupted() {
+    let server = MockServer::start().unwrap();
+    let mut riak = server.client().unwrap();
+    store(&mut riak, "a");
+    store(&mut riak, "b");
+
+    // the keys arrive, the response marking the end does not
+    server.inject_fault(codes::RpbListKeysReq, Fault::DisconnectAfter(1));
+    let mut keys = riak.stream_keys("testbucket").unwrap().items();
+    assert_eq!(keys.next().unwrap().unwrap(), b"a".to_vec());
+    assert_eq!(keys.next().unwrap().unwrap(), b"b".to_vec());
+    assert!(keys.next().unwrap().is_err());
+    assert!(keys.next().is_none());
+}
+
+#[test]
+fn test_bucket_stream_errors() {
+    let server = MockServer::start().unwrap();
+    let mut riak = server.client().unwrap();
+    store(&mut riak, "testkey");
+
+    server.inject_fault(codes::RpbListBucketsReq, Fault::ErrorResp("overload".to_string()));
+    let mut buckets = riak.stream_buckets().unwrap();
+    match buckets.next() {
+        Some(Err(RiakErr::ServerError(error))) => assert!(error.is_overload()),
+        other => panic!("expected an overload error, got {:?}", other),
+    };
+    assert!(buckets.next().is_none());
+
+    server.inject_fault(codes::RpbListBucketsReq, Fault::TruncatedFrame);
+    let mut buckets = riak.stream_buckets().unwrap();
+    assert!(buckets.next().unwrap().is_err());
+    assert!(buckets.next().is_none());
+}
@@ -0,0 +1,113 @@
+#![cfg(feature = "testing")]
+
+extern crate riak;
+
+use riak::Client;
+use riak::object::{ObjectContent, StoreObjectReq};
+use riak::testing::{Fault, MockServer, codes};
+use riak::transport::{Direction, RecordingTransport, TcpTransport};
+
+fn store_keys(riak: &mut Client, keys: &[&str]) {
+    for key in keys {
+        let content = ObjectContent::new(format!("{}value", key));
+        let mut req = StoreObjectReq::new("testbucket", content);
+        req.set_key(*key);
+        riak.store_object(&req).unwrap();
+    }
+}
+
+fn keys_of(riak: &mut Client, start_key: &str, end_key: &str, page_size: u32) -> Vec<Vec<u8>> {
+    let mut fold = riak.fold_objects("testbucket", start_key, end_key).unwrap();
+    fold.set_page_size(page_size);
+    fold.map(|object| object.unwrap().0).collect()
+}
+
+#[test]
+fn test_fold_objects_in_pages() {
+    let server = MockServer::start().unwrap();
+    let mut riak = server.client().unwrap();
+    store_keys(&mut riak, &["a", "b", "c", "d", "e", "f", "g"]);
+
+    let mut fold = riak.fold_objects("testbucket", "b", "f").unwrap();
+    fold.set_page_size(2);
+    let objects = fold.all().unwrap();
+
+    // the end key is excluded by default
+    let keys: Vec<Vec<u8>> = objects.iter().map(|(key, _)| key.clone()).collect();
+    assert_eq!(keys, vec![b"b".to_vec(), b"c".to_vec(), b"d".to_vec(), b"e".to_vec()]);
+    assert_eq!(objects[0].1.get_content()[0].get_value(), b"bvalue".to_vec());
+    assert!(!objects[0].1.get_vclock().is_empty());
+
+    assert_eq!(keys_of(&mut riak, "a", "z", 3).len(), 7);
+    assert_eq!(keys_of(&mut riak, "x", "z", 3).len(), 0);
+}
+
+#[test]
+fn test_fold_objects_bounds() {
+    let server = MockServer::start().unwrap();
+    let mut riak = server.client().unwrap();
+    store_keys(&mut riak, &["a", "b", "c"]);
+
+    let mut fold = riak.fold_objects("testbucket", "a", "c").unwrap();
+    fold.set_start_incl(false);
+    fold.set_end_incl(true);
+    let keys: Vec<Vec<u8>> = fold.map(|object| object.unwrap().0).collect();
+    assert_eq!(keys, vec![b"b".to_vec(), b"c".to_vec()]);
+}
+
+#[test]
+fn test_fold_objects_restarts_from_continuation() {
+    let server = MockServer::start().unwrap();
+    let mut riak = server.client().unwrap();
+    store_keys(&mut riak, &["a", "b", "c", "d", "e", "f", "g"]);
+
+    // the first two pages are read, then requesting the third one fails
+    let mut fold = riak.fold_objects("testbucket", "a", "z").unwrap();
+    fold.set_page_size(3);
+    for _ in 0..4 {
+        fold.next().unwrap().unwrap();
+    }
+    assert_eq!(fold.get_continuation(), Some(b"c".to_vec()));
+
+    server.inject_fault(codes::RpbCSBucketReq, Fault::ErrorResp("timeout".to_string()));
+    for _ in 0..2 {
+        fold.next().unwrap().unwrap();
+    }
+    assert!(fold.next().unwrap().is_err());
+    assert!(fold.next().is_none());
+    let continuation = fold.get_continuation().unwrap();
+    assert_eq!(continuation, b"f".to_vec());
+
+    // the restarted fold reads the failed page again
+    let mut fold = riak.fold_objects("testbucket", "a", "z").unwrap();
+    fold.set_page_size(3);
+    fold.set_continuation(continuation);
+    let keys: Vec<Vec<u8>> = fold.map(|object| object.unwrap().0).collect();
+    assert_eq!(keys, vec![b"g".to_vec()]);
+}
+
+#[test]
+fn test_fold_objects_timeout_in_milliseconds() {
+    let server = MockServer::start().unwrap();
+    store_keys(&mut server.client().unwrap(), &["a"]);
+    let transport = RecordingTransport::new(TcpTransport::new(server.get_addr(), 3600).unwrap());
+    let recording = transport.get_recording();
+    let mut riak = Client::from_transport(transport);
+
+    riak.fold_objects("testbucket", "a", "z").unwrap().next().unwrap().unwrap();
+    let mut fold = riak.fold_objects("testbucket", "a", "z").unwrap();
+    fold.set_timeout(1234);
+    fold.next().unwrap().unwrap();
+
+    let timeouts: Vec<Vec<u8>> = recording.get_messages()
+        .into_iter()
+        .filter(|message| {
+            message.direction == Direction::Sent && message.code == codes::RpbCSBucketReq
+        })
+        .map(|message| message.message)
+        .collect();
+    assert_eq!(timeouts.len(), 2);
+    // timeout (field 8) of the client's 3600 seconds as 3600000 milliseconds, then the one set
+    assert!(timeouts[0].windows(5).any(|field| field == [0x40, 0x80, 0xdd, 0xdb, 0x01]));
+    assert!(timeouts[1].windows(3).any(|field| field == [0x40, 0xd2, 0x09]));
+}
@@ -0,0 +1,119 @@
+extern crate riak;
+
+use riak::Client;
+use riak::errors::RiakErr;
+use riak::mapreduce::{KeyFilter, MapReduceFunction, MapReduceInputs, MapReduceJob,
+                      MapReducePhase};
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use std::thread;
+
+// Encode an RpbMapRedResp frame by hand.
+fn mapred_resp(phase: Option<u8>, response: Option<&[u8]>, done: bool) -> Vec<u8> {
+    let mut body = Vec::new();
+    if let Some(phase) = phase {
+        body.extend_from_slice(&[0x08, phase]);
+    }
+    if let Some(response) = response {
+        body.extend_from_slice(&[0x12, response.len() as u8]);
+        body.extend_from_slice(response);
+    }
+    if done {
+        body.extend_from_slice(&[0x18, 0x01]);
+    }
+    let len = (body.len() + 1) as u32;
+    let mut frame = vec![(len >> 24) as u8, (len >> 16) as u8, (len >> 8) as u8, len as u8, 24];
+    frame.extend_from_slice(&body);
+    frame
+}
+
+#[test]
+fn test_mapreduce_job_json() {
+    let mut year = KeyFilter::new("tokenize");
+    year.add_string_arg("-");
+    year.add_int_arg(1);
+    let mut eq = KeyFilter::new("eq");
+    eq.add_string_arg("2017");
+    let inputs = MapReduceInputs::KeyFilters {
+        bucket: b"logs".to_vec(),
+        filters: vec![year, KeyFilter::not(vec![eq])],
+    };
+
+    let mut job = MapReduceJob::new(inputs);
+    let mut map =
+        MapReducePhase::map(MapReduceFunction::JavaScriptSource("function(v) { return [\"a\"]; }"
+            .to_string()));
+    map.set_keep(true);
+    job.add_phase(map);
+    job.add_phase(MapReducePhase::link(Some(b"people".to_vec()), None));
+    let mut reduce = MapReducePhase::reduce(MapReduceFunction::ErlangModFun("riak_kv_mapreduce"
+                                                                                 .to_string(),
+                                                                             "reduce_sum"
+                                                                                 .to_string()));
+    reduce.set_arg("{\"limit\":10}");
+    job.add_phase(reduce);
+    job.set_timeout(60000);
+
+    assert_eq!(job.to_json().unwrap(),
+               "{\"inputs\":{\"bucket\":\"logs\",\"key_filters\":[[\"tokenize\",\"-\",1],\
+                [\"not\",[[\"eq\",\"2017\"]]]]},\"query\":[{\"map\":{\"language\":\
+                \"javascript\",\"source\":\"function(v) { return [\\\"a\\\"]; }\",\"keep\":\
+                true}},{\"link\":{\"bucket\":\"people\"}},{\"reduce\":{\"language\":\"erlang\",\
+                \"module\":\"riak_kv_mapreduce\",\"function\":\"reduce_sum\",\"arg\":\
+                {\"limit\":10}}}],\"timeout\":60000}");
+}
+
+#[test]
+fn test_mapreduce_job_json_rejects_invalid_values() {
+    let job = MapReduceJob::new(MapReduceInputs::Keys(vec![(b"logs".to_vec(),
+                                                            b"caf\xe9".to_vec())]));
+    match job.to_json() {
+        Err(RiakErr::InvalidMapReduce(_)) => (),
+        other => panic!("expected an invalid mapreduce job, got {:?}", other),
+    };
+
+    for value in &[f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+        let mut filter = KeyFilter::new("greater_than");
+        filter.add_float_arg(*value);
+        let job = MapReduceJob::new(MapReduceInputs::KeyFilters {
+            bucket: b"logs".to_vec(),
+            filters: vec![filter],
+        });
+        match job.to_json() {
+            Err(RiakErr::InvalidMapReduce(_)) => (),
+            other => panic!("expected an invalid mapreduce job, got {:?}", other),
+        };
+    }
+}
+
+#[test]
+fn test_mapreduce_stream() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    thread::spawn(move || for stream in listener.incoming() {
+        let mut stream = stream.unwrap();
+        thread::spawn(move || {
+            let mut header = [0u8; 5];
+            if stream.read_exact(&mut header).is_err() {
+                return;
+            }
+            let len = ((header[2] as usize) << 8) | (header[3] as usize);
+            let mut body = vec![0u8; len - 1];
+            stream.read_exact(&mut body).unwrap();
+            assert_eq!(header[4], 23);
+
+            stream.write_all(&mapred_resp(Some(0), Some(b"[1,2]"), false)).unwrap();
+            stream.write_all(&mapred_resp(Some(1), Some(b"[3]"), false)).unwrap();
+            stream.write_all(&mapred_resp(None, None, true)).unwrap();
+        });
+    });
+
+    let mut riak = Client::new(addr).unwrap();
+    let job = MapReduceJob::new(MapReduceInputs::Bucket(b"testbucket".to_vec()));
+
+    let chunks: Vec<(u32, Vec<u8>)> = riak.mapreduce(&job)
+        .unwrap()
+        .map(|chunk| chunk.unwrap())
+        .collect();
+    assert_eq!(chunks, vec![(0, b"[1,2]".to_vec()), (1, b"[3]".to_vec())]);
+}
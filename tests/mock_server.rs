@@ -0,0 +1,374 @@
+#![cfg(feature = "testing")]
+
+extern crate riak;
+
+use riak::bucket::BucketProps;
+use riak::counter::{GetCounterReq, IncrementCounterReq};
+use riak::errors::RiakErr;
+use riak::index::IndexQuery;
+use riak::object::{DeleteObjectReq, FetchObjectReq, ObjectContent, StoreObjectReq};
+use riak::resolver::LongestValue;
+use riak::testing::{Fault, MockServer, codes};
+use riak::yokozuna::YokozunaIndex;
+
+fn store_req(bucket_type: &str, key: &str, value: &str) -> StoreObjectReq {
+    let mut req = StoreObjectReq::new("testbucket", ObjectContent::new(value));
+    req.set_bucket_type(bucket_type);
+    req.set_key(key);
+    req
+}
+
+fn fetch_req(bucket_type: &str, key: &str) -> FetchObjectReq {
+    let mut req = FetchObjectReq::new("testbucket", key);
+    req.set_bucket_type(bucket_type);
+    req
+}
+
+fn values(content: Vec<ObjectContent>) -> Vec<Vec<u8>> {
+    let mut values: Vec<Vec<u8>> = content.iter().map(|sibling| sibling.get_value()).collect();
+    values.sort();
+    values
+}
+
+fn assert_server_error<T, F>(result: Result<T, RiakErr>, check: F)
+    where F: Fn(&riak::errors::ServerError) -> bool
+{
+    match result {
+        Err(RiakErr::ServerError(ref error)) if check(error) => (),
+        Err(error) => panic!("unexpected error {:?}", error),
+        Ok(_) => panic!("expected a server error"),
+    };
+}
+
+#[test]
+fn test_ping_and_server_info() {
+    let server = MockServer::start().unwrap();
+    let mut riak = server.client().unwrap();
+    riak.ping().unwrap();
+    let (node, _) = riak.server_info().unwrap();
+    assert_eq!(node, "riak@127.0.0.1");
+}
+
+#[test]
+fn test_store_replaces_in_default_bucket_type() {
+    let server = MockServer::start().unwrap();
+    let mut riak = server.client().unwrap();
+
+    riak.store_object(&store_req("default", "testkey", "one")).unwrap();
+    riak.store_object(&store_req("default", "testkey", "two")).unwrap();
+
+    let resp = riak.fetch_object(&fetch_req("default", "testkey")).unwrap();
+    assert_eq!(values(resp.get_content()), vec![b"two".to_vec()]);
+    assert!(resp.get_content()[0].get_vtag().is_some());
+    assert!(resp.get_content()[0].get_last_mod().is_some());
+}
+
+#[test]
+fn test_siblings_in_other_bucket_types() {
+    let server = MockServer::start().unwrap();
+    let mut riak = server.client().unwrap();
+
+    riak.store_object(&store_req("mytype", "testkey", "one")).unwrap();
+    let vclock = riak.fetch_object(&fetch_req("mytype", "testkey")).unwrap().get_vclock();
+    riak.store_object(&store_req("mytype", "testkey", "three")).unwrap();
+
+    let resp = riak.fetch_object(&fetch_req("mytype", "testkey")).unwrap();
+    assert_eq!(values(resp.get_content()), vec![b"one".to_vec(), b"three".to_vec()]);
+
+    // the stale vclock does not replace the siblings
+    let mut req = store_req("mytype", "testkey", "two");
+    req.set_vclock(vclock);
+    riak.store_object(&req).unwrap();
+    let resp = riak.fetch_object(&fetch_req("mytype", "testkey")).unwrap();
+    assert_eq!(resp.get_content().len(), 3);
+
+    // resolving and storing with the current vclock leaves a single value
+    riak.fetch_resolved(&fetch_req("mytype", "testkey"), &LongestValue, true).unwrap();
+    let resp = riak.fetch_object(&fetch_req("mytype", "testkey")).unwrap();
+    assert_eq!(values(resp.get_content()), vec![b"three".to_vec()]);
+}
+
+#[test]
+fn test_fetch_resolved_head_request() {
+    let server = MockServer::start().unwrap();
+    let mut riak = server.client().unwrap();
+
+    riak.store_object(&store_req("mytype", "testkey", "short")).unwrap();
+    riak.store_object(&store_req("mytype", "testkey", "a much longer value")).unwrap();
+    let vclock = riak.fetch_object(&fetch_req("mytype", "testkey")).unwrap().get_vclock();
+
+    // the values are fetched to resolve them, even when the request only asks for metadata
+    let mut req = fetch_req("mytype", "testkey");
+    req.set_head(true);
+    req.set_if_modified(vclock);
+    let resolved = riak.fetch_resolved(&req, &LongestValue, true).unwrap();
+    assert_eq!(values(resolved.get_content()), vec![b"a much longer value".to_vec()]);
+
+    let resp = riak.fetch_object(&fetch_req("mytype", "testkey")).unwrap();
+    assert_eq!(values(resp.get_content()), vec![b"a much longer value".to_vec()]);
+}
+
+#[test]
+fn test_update_object_retries_after_modified() {
+    let server = MockServer::start().unwrap();
+    let mut riak = server.client().unwrap();
+    riak.store_object(&store_req("default", "testkey", "1")).unwrap();
+
+    // another client changes the object between the first fetch and store
+    let mut other = server.client().unwrap();
+    let mut calls = 0;
+    riak.update_object("testbucket", "testkey", |current| {
+            calls += 1;
+            if calls == 1 {
+                other.store_object(&store_req("default", "testkey", "10")).unwrap();
+            }
+            let value = String::from_utf8(current.unwrap().get_value()).unwrap();
+            ObjectContent::new((value.parse::<u32>().unwrap() + 1).to_string())
+        })
+        .unwrap();
+    assert_eq!(calls, 2);
+
+    let resp = riak.fetch_object(&fetch_req("default", "testkey")).unwrap();
+    assert_eq!(values(resp.get_content()), vec![b"11".to_vec()]);
+}
+
+#[test]
+fn test_update_object_retry_limit() {
+    let server = MockServer::start().unwrap();
+    let mut riak = server.client().unwrap();
+    riak.set_update_retries(2);
+
+    for _ in 0..2 {
+        server.inject_fault(codes::RpbPutReq, Fault::ErrorResp("modified".to_string()));
+    }
+    let mut calls = 0;
+    riak.update_object("testbucket", "testkey", |current| {
+            calls += 1;
+            assert!(current.is_none());
+            ObjectContent::new("created")
+        })
+        .unwrap();
+    assert_eq!(calls, 3);
+
+    // once the retries are used up the precondition failure is returned
+    for _ in 0..3 {
+        server.inject_fault(codes::RpbPutReq, Fault::ErrorResp("modified".to_string()));
+    }
+    let mut calls = 0;
+    assert_server_error(riak.update_object("testbucket", "testkey", |_| {
+                            calls += 1;
+                            ObjectContent::new("updated")
+                        }),
+                        |error| error.is_precondition_failed());
+    assert_eq!(calls, 3);
+
+    let resp = riak.fetch_object(&fetch_req("default", "testkey")).unwrap();
+    assert_eq!(values(resp.get_content()), vec![b"created".to_vec()]);
+}
+
+#[test]
+fn test_update_object_with_type() {
+    let server = MockServer::start().unwrap();
+    let mut riak = server.client().unwrap();
+    riak.store_object(&store_req("mytype", "testkey", "short")).unwrap();
+    riak.store_object(&store_req("mytype", "testkey", "longest")).unwrap();
+
+    riak.update_object_with_type("mytype", "testbucket", "testkey", &LongestValue, |current| {
+            let mut value = current.unwrap().get_value();
+            value.extend_from_slice(b"!");
+            ObjectContent::new(value)
+        })
+        .unwrap();
+
+    let resp = riak.fetch_object(&fetch_req("mytype", "testkey")).unwrap();
+    assert_eq!(values(resp.get_content()), vec![b"longest!".to_vec()]);
+    let resp = riak.fetch_object(&fetch_req("default", "testkey")).unwrap();
+    assert!(resp.get_content().is_empty());
+}
+
+#[test]
+fn test_preconditions() {
+    let server = MockServer::start().unwrap();
+    let mut riak = server.client().unwrap();
+
+    let mut req = store_req("default", "testkey", "one");
+    req.set_if_none_match(true);
+    riak.store_object(&req).unwrap();
+    assert_server_error(riak.store_object(&req), |error| error.is_precondition_failed());
+
+    let vclock = riak.fetch_object(&fetch_req("default", "testkey")).unwrap().get_vclock();
+    riak.store_object(&store_req("default", "testkey", "two")).unwrap();
+
+    let mut req = store_req("default", "testkey", "three");
+    req.set_vclock(vclock);
+    req.set_if_not_modified(true);
+    assert_server_error(riak.store_object(&req), |error| error.is_precondition_failed());
+}
+
+#[test]
+fn test_generated_keys_and_return_body() {
+    let server = MockServer::start().unwrap();
+    let mut riak = server.client().unwrap();
+
+    let mut req = StoreObjectReq::new("testbucket", ObjectContent::new("value"));
+    req.set_return_body(true);
+    let resp = riak.store_object(&req).unwrap();
+
+    let key = resp.get_key().unwrap();
+    assert!(resp.get_vclock().is_some());
+    assert_eq!(values(resp.get_content()), vec![b"value".to_vec()]);
+
+    let fetched = riak.fetch_object(&FetchObjectReq::new(b"testbucket".to_vec(), key)).unwrap();
+    assert_eq!(values(fetched.get_content()), vec![b"value".to_vec()]);
+}
+
+#[test]
+fn test_delete_object() {
+    let server = MockServer::start().unwrap();
+    let mut riak = server.client().unwrap();
+
+    riak.store_object(&store_req("default", "testkey", "value")).unwrap();
+    riak.delete_object(&DeleteObjectReq::new("testbucket", "testkey")).unwrap();
+
+    let resp = riak.fetch_object(&fetch_req("default", "testkey")).unwrap();
+    assert!(resp.get_content().is_empty());
+}
+
+#[test]
+fn test_bucket_properties() {
+    let server = MockServer::start().unwrap();
+    let mut riak = server.client().unwrap();
+
+    assert_eq!(riak.get_bucket_properties("testbucket").unwrap().get_n_val(), Some(3));
+
+    let mut props = BucketProps::new();
+    props.set_n_val(5);
+    props.set_allow_mult(true);
+    riak.set_bucket_properties("testbucket", &props).unwrap();
+
+    let props = riak.get_bucket_properties("testbucket").unwrap();
+    assert_eq!(props.get_n_val(), Some(5));
+    assert_eq!(props.get_allow_mult(), Some(true));
+
+    riak.reset_bucket("default", "testbucket").unwrap();
+    let props = riak.get_bucket_properties("testbucket").unwrap();
+    assert_eq!(props.get_n_val(), Some(3));
+    assert_eq!(props.get_allow_mult(), Some(false));
+
+    assert_eq!(riak.get_bucket_type_properties("maps").unwrap().get_allow_mult(),
+               Some(true));
+}
+
+#[test]
+fn test_legacy_counters() {
+    let server = MockServer::start().unwrap();
+    let mut riak = server.client().unwrap();
+
+    // legacy counters need "allow_mult"
+    let mut req = IncrementCounterReq::new("testbucket", "testcounter", 5);
+    match riak.increment_counter(&req) {
+        Err(RiakErr::ServerError(error)) => assert!(error.get_message().contains("allow_mult")),
+        other => panic!("expected a server error, got {:?}", other),
+    };
+
+    let mut props = BucketProps::new();
+    props.set_allow_mult(true);
+    riak.set_bucket_properties("testbucket", &props).unwrap();
+
+    let get_req = GetCounterReq::new("testbucket", "testcounter");
+    assert_eq!(riak.get_counter(&get_req).unwrap(), None);
+
+    req.set_w(2);
+    assert_eq!(riak.increment_counter(&req).unwrap(), None);
+    req.set_amount(-2);
+    req.set_returnvalue(true);
+    assert_eq!(riak.increment_counter(&req).unwrap(), Some(3));
+    assert_eq!(riak.get_counter(&get_req).unwrap(), Some(3));
+}
+
+#[test]
+fn test_list_keys_and_buckets() {
+    let server = MockServer::start().unwrap();
+    let mut riak = server.client().unwrap();
+
+    riak.store_object(&store_req("default", "b", "value")).unwrap();
+    riak.store_object(&store_req("default", "a", "value")).unwrap();
+    let mut other = StoreObjectReq::new("otherbucket", ObjectContent::new("value"));
+    other.set_key("c");
+    riak.store_object(&other).unwrap();
+
+    let mut keys = riak.list_keys("testbucket").unwrap();
+    keys.sort();
+    assert_eq!(keys, vec![b"a".to_vec(), b"b".to_vec()]);
+
+    let mut buckets = riak.list_buckets().unwrap();
+    buckets.sort();
+    assert_eq!(buckets, vec![b"otherbucket".to_vec(), b"testbucket".to_vec()]);
+}
+
+#[test]
+fn test_index_queries_return_body() {
+    let server = MockServer::start().unwrap();
+    let mut riak = server.client().unwrap();
+
+    riak.store_object(&store_req("default", "a", "avalue")).unwrap();
+    riak.store_object(&store_req("default", "b", "bvalue")).unwrap();
+    riak.store_object(&store_req("default", "c", "cvalue")).unwrap();
+
+    let mut query = IndexQuery::new_range("testbucket", "$key", "a", "b");
+    let results = riak.query_index(&query).unwrap();
+    assert_eq!(results.get_keys().len(), 2);
+    assert!(results.get_objects().is_empty());
+
+    // Riak answers queries with "return_body" using a different message code
+    query.set_return_body(true);
+    let results = riak.query_index(&query).unwrap();
+    let objects = results.get_objects();
+    assert_eq!(objects.len(), 2);
+    assert_eq!(objects[0].0, b"a".to_vec());
+    assert_eq!(objects[0].1.get_content()[0].get_value(), b"avalue".to_vec());
+
+    query.set_max_results(1);
+    let results = riak.stream_index(&query).unwrap().all().unwrap();
+    assert_eq!(results.get_objects().len(), 1);
+    assert_eq!(results.get_continuation(), Some(b"a".to_vec()));
+}
+
+#[test]
+fn test_preflist() {
+    let server = MockServer::start().unwrap();
+    let mut riak = server.client().unwrap();
+
+    let preflist = riak.fetch_preflist("testbucket", "testkey").unwrap();
+    assert_eq!(preflist.len(), 3);
+    assert!(preflist.iter().all(|item| item.is_primary && item.node == "riak@127.0.0.1"));
+
+    // the same key always maps to the same partitions
+    let again = riak.fetch_preflist("testbucket", "testkey").unwrap();
+    let partitions: Vec<i64> = preflist.iter().map(|item| item.partition).collect();
+    assert_eq!(partitions, again.iter().map(|item| item.partition).collect::<Vec<i64>>());
+}
+
+#[test]
+fn test_yokozuna_schemas_and_indexes() {
+    let server = MockServer::start().unwrap();
+    let mut riak = server.client().unwrap();
+
+    assert_server_error(riak.get_yokozuna_schema("myschema"),
+                        |error| error.is_not_found());
+    riak.set_yokozuna_schema("myschema", "<schema/>").unwrap();
+    assert_eq!(riak.get_yokozuna_schema("myschema").unwrap(), b"<schema/>".to_vec());
+
+    let mut index = YokozunaIndex::new("myindex");
+    index.set_schema("myschema");
+    riak.set_yokozuna_index(index).unwrap();
+    riak.set_yokozuna_index(YokozunaIndex::new("otherindex")).unwrap();
+
+    let indexes = riak.get_yokozuna_index("myindex").unwrap();
+    assert_eq!(indexes.len(), 1);
+    assert_eq!(indexes[0].get_schema(), Some(b"myschema".to_vec()));
+    assert_eq!(indexes[0].get_n_val(), Some(3));
+
+    riak.delete_yokozuna_index("myindex").unwrap();
+    assert_server_error(riak.get_yokozuna_index("myindex"), |error| error.is_not_found());
+}
@@ -0,0 +1,132 @@
+#![cfg(feature = "testing")]
+
+extern crate riak;
+
+use riak::errors::RiakErr;
+use riak::pool::{Pool, PoolConfig};
+use riak::testing::{Fault, MockServer, codes};
+use std::io::ErrorKind;
+use std::thread;
+use std::time::Duration;
+
+fn pool_config(min_size: usize, max_size: usize) -> PoolConfig {
+    let mut config = PoolConfig::new();
+    config.set_min_size(min_size);
+    config.set_max_size(max_size);
+    config.set_checkout_timeout(Duration::from_millis(200));
+    config
+}
+
+#[test]
+fn test_pool_reuses_returned_connections() {
+    let server = MockServer::start().unwrap();
+    let pool = Pool::new(server.get_addr(), pool_config(1, 2)).unwrap();
+    assert_eq!(pool.size(), 1);
+    assert_eq!(pool.idle_count(), 1);
+
+    let mut first = pool.get().unwrap();
+    first.ping().unwrap();
+    assert_eq!(pool.size(), 1);
+    assert_eq!(pool.idle_count(), 0);
+
+    // the pool grows while every connection is checked out
+    let second = pool.get().unwrap();
+    assert_eq!(pool.size(), 2);
+
+    drop(first);
+    assert_eq!(pool.idle_count(), 1);
+
+    // the returned connection is handed out again rather than opening a new one
+    let mut third = pool.get().unwrap();
+    third.ping().unwrap();
+    assert_eq!(pool.size(), 2);
+    assert_eq!(pool.idle_count(), 0);
+
+    drop(second);
+    drop(third);
+    assert_eq!(pool.size(), 2);
+    assert_eq!(pool.idle_count(), 2);
+}
+
+#[test]
+fn test_pool_min_size_larger_than_max_size() {
+    let server = MockServer::start().unwrap();
+    match Pool::new(server.get_addr(), pool_config(3, 2)) {
+        Err(RiakErr::IoError(ref error)) if error.kind() == ErrorKind::InvalidInput => (),
+        other => panic!("expected an invalid input error, got {:?}", other),
+    };
+
+    // equal sizes are fine
+    let pool = Pool::new(server.get_addr(), pool_config(2, 2)).unwrap();
+    assert_eq!(pool.size(), 2);
+}
+
+#[test]
+fn test_pool_checkout_timeout() {
+    let server = MockServer::start().unwrap();
+    let pool = Pool::new(server.get_addr(), pool_config(0, 1)).unwrap();
+    assert_eq!(pool.size(), 0);
+
+    let held = pool.get().unwrap();
+    match pool.get() {
+        Err(RiakErr::PoolTimeout) => (),
+        other => panic!("expected a pool timeout, got {:?}", other),
+    };
+    assert_eq!(pool.size(), 1);
+
+    // a checkout waiting at the maximum size gets the next connection returned
+    let waiting = pool.clone();
+    let handle = thread::spawn(move || waiting.get().map(|mut riak| riak.ping()));
+    thread::sleep(Duration::from_millis(50));
+    drop(held);
+    handle.join().unwrap().unwrap().unwrap();
+    assert_eq!(pool.size(), 1);
+}
+
+#[test]
+fn test_pool_discard_frees_slot() {
+    let server = MockServer::start().unwrap();
+    let pool = Pool::new(server.get_addr(), pool_config(0, 1)).unwrap();
+
+    let riak = pool.get().unwrap();
+    riak.discard();
+    assert_eq!(pool.size(), 0);
+    assert_eq!(pool.idle_count(), 0);
+
+    pool.get().unwrap().ping().unwrap();
+    assert_eq!(pool.size(), 1);
+}
+
+#[test]
+fn test_pool_reconnects_broken_connections() {
+    let server = MockServer::start().unwrap();
+    let pool = Pool::new(server.get_addr(), pool_config(1, 1)).unwrap();
+
+    // the health check ping fails, so the idle connection is reconnected before it's handed out
+    server.inject_fault(codes::RpbPingReq, Fault::Disconnect);
+    let mut riak = pool.get().unwrap();
+    riak.ping().unwrap();
+    assert_eq!(pool.size(), 1);
+}
+
+#[test]
+fn test_pool_evicts_idle_connections() {
+    let server = MockServer::start().unwrap();
+    let mut config = pool_config(1, 3);
+    config.set_idle_timeout(Duration::from_millis(50));
+    let pool = Pool::new(server.get_addr(), config).unwrap();
+
+    let first = pool.get().unwrap();
+    let second = pool.get().unwrap();
+    let third = pool.get().unwrap();
+    drop(first);
+    drop(second);
+    drop(third);
+    assert_eq!(pool.size(), 3);
+
+    // idle connections are closed down to the minimum size
+    thread::sleep(Duration::from_millis(100));
+    pool.evict_idle();
+    assert_eq!(pool.size(), 1);
+    assert_eq!(pool.idle_count(), 1);
+}
@@ -0,0 +1,41 @@
+extern crate riak;
+
+use riak::object::ObjectContent;
+use riak::resolver::{ClosureResolver, ConflictResolver, LastModifiedWins, LongestValue};
+
+fn sibling(value: &str, last_mod: u32, last_mod_usecs: u32) -> ObjectContent {
+    let mut content = ObjectContent::new(value);
+    content.set_last_mod(last_mod);
+    content.set_last_mod_usecs(last_mod_usecs);
+    content
+}
+
+#[test]
+fn test_last_modified_wins() {
+    let siblings = vec![sibling("old", 100, 900),
+                        sibling("newest", 101, 5),
+                        sibling("newer", 101, 4),
+                        ObjectContent::new("unknown")];
+    assert_eq!(LastModifiedWins.resolve(&siblings).get_value(), b"newest".to_vec());
+}
+
+#[test]
+fn test_longest_value() {
+    let siblings = vec![ObjectContent::new("ab"),
+                        ObjectContent::new("abcd"),
+                        ObjectContent::new("wxyz")];
+    assert_eq!(LongestValue.resolve(&siblings).get_value(), b"abcd".to_vec());
+}
+
+#[test]
+fn test_closure_resolver() {
+    let resolver = ClosureResolver::new(|siblings: &[ObjectContent]| {
+        let mut values: Vec<Vec<u8>> = siblings.iter().map(|s| s.get_value()).collect();
+        values.sort();
+        ObjectContent::new(values.join(&b','))
+    });
+
+    let siblings = vec![ObjectContent::new("b"), ObjectContent::new("a")];
+    let resolved = resolver.resolve(&siblings);
+    assert_eq!(resolved.get_value(), b"a,b".to_vec());
+}
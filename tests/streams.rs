@@ -0,0 +1,150 @@
+extern crate riak;
+
+use riak::Client;
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc::{channel, Sender};
+use std::thread;
+
+fn read_frame(stream: &mut TcpStream) -> Option<(u8, Vec<u8>)> {
+    let mut header = [0u8; 5];
+    if stream.read_exact(&mut header).is_err() {
+        return None;
+    }
+    let len = ((header[0] as usize) << 24) | ((header[1] as usize) << 16) |
+              ((header[2] as usize) << 8) | (header[3] as usize);
+    let mut body = vec![0u8; len - 1];
+    if stream.read_exact(&mut body).is_err() {
+        return None;
+    }
+    Some((header[4], body))
+}
+
+// Encode an RpbListKeysResp or RpbListBucketsResp frame by hand, both use the same fields.
+fn list_resp(code: u8, items: &[&[u8]], done: bool) -> Vec<u8> {
+    let mut body = Vec::new();
+    for item in items {
+        body.extend_from_slice(&[0x0a, item.len() as u8]);
+        body.extend_from_slice(item);
+    }
+    if done {
+        body.extend_from_slice(&[0x10, 0x01]);
+    }
+    let len = (body.len() + 1) as u32;
+    let mut frame = vec![(len >> 24) as u8, (len >> 16) as u8, (len >> 8) as u8, len as u8, code];
+    frame.extend_from_slice(&body);
+    frame
+}
+
+// Starts a stand-in server that passes each connection and its first request to `serve`.
+fn start_server<F>(serve: F) -> String
+    where F: Fn(TcpStream, u8, Vec<u8>) + Send + Sync + Copy + 'static
+{
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap().to_string();
+    thread::spawn(move || for stream in listener.incoming() {
+        let mut stream = stream.unwrap();
+        thread::spawn(move || if let Some((code, body)) = read_frame(&mut stream) {
+            serve(stream, code, body);
+        });
+    });
+    addr
+}
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack.windows(needle.len()).any(|window| window == needle)
+}
+
+#[test]
+fn test_stream_keys_with_bucket_type() {
+    let addr = start_server(|mut stream, code, body| {
+        assert_eq!(code, 17);
+        assert!(contains(&body, b"mytype"));
+        // timeout (field 2) of 1234 milliseconds
+        assert!(contains(&body, &[0x10, 0xd2, 0x09]));
+        stream.write_all(&list_resp(18, &[b"a", b"b"], false)).unwrap();
+        stream.write_all(&list_resp(18, &[], false)).unwrap();
+        stream.write_all(&list_resp(18, &[b"c"], true)).unwrap();
+    });
+
+    let mut riak = Client::new(addr.as_str()).unwrap();
+    let mut keystream = riak.stream_keys("testbucket").unwrap();
+    keystream.set_bucket_type("mytype");
+    keystream.set_timeout(1234);
+
+    let keys: Vec<Vec<u8>> = keystream.items().map(|key| key.unwrap()).collect();
+    assert_eq!(keys, vec![b"a".to_vec(), b"b".to_vec(), b"c".to_vec()]);
+}
+
+#[test]
+fn test_stream_buckets_groups() {
+    let addr = start_server(|mut stream, code, body| {
+        assert_eq!(code, 15);
+        assert!(contains(&body, b"maps"));
+        stream.write_all(&list_resp(16, &[b"one"], false)).unwrap();
+        stream.write_all(&list_resp(16, &[b"two", b"three"], true)).unwrap();
+    });
+
+    let mut riak = Client::new(addr.as_str()).unwrap();
+    let mut bucketstream = riak.stream_buckets().unwrap();
+    bucketstream.set_bucket_type("maps");
+
+    let groups: Vec<Vec<Vec<u8>>> = bucketstream.map(|buckets| buckets.unwrap()).collect();
+    assert_eq!(groups,
+               vec![vec![b"one".to_vec()], vec![b"two".to_vec(), b"three".to_vec()]]);
+}
+
+#[test]
+fn test_stream_ends_after_error() {
+    let addr = start_server(|stream, _, _| drop(stream));
+
+    let mut riak = Client::new(addr.as_str()).unwrap();
+    let mut keystream = riak.stream_keys("testbucket").unwrap();
+
+    assert!(keystream.next().unwrap().is_err());
+    assert!(keystream.next().is_none());
+}
+
+#[test]
+fn test_dropped_stream_closes_connection() {
+    let (closed_tx, closed_rx) = channel();
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    thread::spawn(move || for stream in listener.incoming() {
+        let closed_tx: Sender<bool> = closed_tx.clone();
+        let mut stream = stream.unwrap();
+        thread::spawn(move || {
+            if read_frame(&mut stream).is_none() {
+                return;
+            }
+            stream.write_all(&list_resp(18, &[b"a"], false)).unwrap();
+            // the stream never finishes, the client has to close the connection
+            let mut buf = [0u8; 1];
+            closed_tx.send(stream.read(&mut buf).unwrap() == 0).unwrap();
+        });
+    });
+
+    let mut riak = Client::new(addr).unwrap();
+    {
+        let mut keys = riak.stream_keys("testbucket").unwrap().items();
+        assert_eq!(keys.next().unwrap().unwrap(), b"a".to_vec());
+    }
+
+    assert!(closed_rx.recv().unwrap());
+}
+
+#[test]
+fn test_stream_default_timeout_in_milliseconds() {
+    let addr = start_server(|mut stream, code, body| {
+        assert_eq!(code, 17);
+        // timeout (field 2) of the client's 3600 seconds, as 3600000 milliseconds
+        assert!(contains(&body, &[0x10, 0x80, 0xdd, 0xdb, 0x01]));
+        stream.write_all(&list_resp(18, &[b"a"], true)).unwrap();
+    });
+
+    let mut riak = Client::new(addr.as_str()).unwrap();
+    let keystream = riak.stream_keys("testbucket").unwrap();
+
+    let keys: Vec<Vec<u8>> = keystream.items().map(|key| key.unwrap()).collect();
+    assert_eq!(keys, vec![b"a".to_vec()]);
+}
@@ -0,0 +1,161 @@
+extern crate riak;
+
+use riak::Client;
+use riak::timeseries::{Column, ColumnType, QueryResults, Row, TsCell};
+use riak::transport::{InMemoryListener, InMemoryTransport, Transport};
+use std::sync::mpsc::{channel, Receiver};
+use std::thread;
+
+// Encodes a length delimited field, every message in these tests is shorter than 128 bytes.
+fn field(tag: u8, bytes: &[u8]) -> Vec<u8> {
+    let mut encoded = vec![tag, bytes.len() as u8];
+    encoded.extend_from_slice(bytes);
+    encoded
+}
+
+// A TsRow holding each of the encoded TsCells.
+fn row(cells: &[Vec<u8>]) -> Vec<u8> {
+    let mut encoded = Vec::new();
+    for cell in cells {
+        encoded.extend(field(0x0a, cell));
+    }
+    encoded
+}
+
+fn varchar(value: &[u8]) -> Vec<u8> {
+    field(0x0a, value)
+}
+
+// A TsCell with timestamp_value (field 3) set, zigzag encoded as a sint64.
+fn timestamp(value: u8) -> Vec<u8> {
+    vec![0x18, value << 1]
+}
+
+// A TsCell with no value set is a null.
+fn null() -> Vec<u8> {
+    Vec::new()
+}
+
+// Hands the code and body of each request to the test, and answers it with the frames
+// `respond` returns for its code.
+fn serve(listener: InMemoryListener,
+         respond: fn(u8) -> Vec<(u8, Vec<u8>)>)
+         -> Receiver<(u8, Vec<u8>)> {
+    let (requests_tx, requests_rx) = channel();
+    thread::spawn(move || {
+        let mut connection: InMemoryTransport = listener.accept().unwrap();
+        while let Ok((code, body)) = connection.receive() {
+            requests_tx.send((code, body)).unwrap();
+            for (code, body) in respond(code) {
+                connection.send(code, &body).unwrap();
+            }
+        }
+    });
+    requests_rx
+}
+
+#[test]
+fn test_ts_put() {
+    let listener = InMemoryListener::new();
+    let mut riak = Client::from_transport(listener.connect());
+    let requests = serve(listener, |_| vec![(93, Vec::new())]);
+
+    let rows = [Row::new(vec![TsCell::Varchar(b"snow".to_vec()), TsCell::Timestamp(5)]),
+                Row::new(vec![TsCell::Varchar(b"rain".to_vec()), TsCell::Null])];
+    riak.ts_put("GeoCheckin", &rows).unwrap();
+
+    // table (field 1) and rows (field 3), the null is a cell with no value
+    let mut expected = field(0x0a, b"GeoCheckin");
+    expected.extend(field(0x1a, &row(&[varchar(b"snow"), timestamp(5)])));
+    expected.extend(field(0x1a, &row(&[varchar(b"rain"), null()])));
+    assert_eq!(requests.recv().unwrap(), (92, expected));
+}
+
+#[test]
+fn test_ts_get() {
+    let listener = InMemoryListener::new();
+    let mut riak = Client::from_transport(listener.connect());
+    let requests = serve(listener, |_| {
+        // columns (field 1) named with their types, then a row (field 2) with a null
+        let mut resp = field(0x0a, &[0x0a, 0x04, b't', b'i', b'm', b'e', 0x10, 0x03]);
+        resp.extend(field(0x0a, &[0x0a, 0x04, b'w', b'i', b'n', b'd', 0x10, 0x00]));
+        resp.extend(field(0x12, &row(&[timestamp(5), null()])));
+        vec![(97, resp)]
+    });
+
+    let results = riak.ts_get("GeoCheckin", &[TsCell::Timestamp(5)]).unwrap();
+    assert_eq!(results,
+               QueryResults::new(vec![Column::new("time", ColumnType::Timestamp),
+                                      Column::new("wind", ColumnType::Varchar)],
+                                 vec![Row::new(vec![TsCell::Timestamp(5), TsCell::Null])]));
+
+    // table (field 1) and key (field 2)
+    let mut expected = field(0x0a, b"GeoCheckin");
+    expected.extend(field(0x12, &timestamp(5)));
+    assert_eq!(requests.recv().unwrap(), (96, expected));
+}
+
+#[test]
+fn test_ts_delete() {
+    let listener = InMemoryListener::new();
+    let mut riak = Client::from_transport(listener.connect());
+    let requests = serve(listener, |_| vec![(95, Vec::new())]);
+
+    riak.ts_delete("GeoCheckin", &[TsCell::Varchar(b"snow".to_vec()), TsCell::Timestamp(5)])
+        .unwrap();
+
+    let mut expected = field(0x0a, b"GeoCheckin");
+    expected.extend(field(0x12, &varchar(b"snow")));
+    expected.extend(field(0x12, &timestamp(5)));
+    assert_eq!(requests.recv().unwrap(), (94, expected));
+}
+
+#[test]
+fn test_ts_query() {
+    let listener = InMemoryListener::new();
+    let mut riak = Client::from_transport(listener.connect());
+    let requests = serve(listener, |_| {
+        // the first response has the columns and done (field 3) unset, the last leaves done out
+        // which defaults to true
+        let mut first = field(0x0a, &[0x0a, 0x04, b'w', b'i', b'n', b'd', 0x10, 0x00]);
+        first.extend(field(0x12, &row(&[varchar(b"calm")])));
+        first.extend(&[0x18, 0x00]);
+        let last = field(0x12, &row(&[null()]));
+        vec![(91, first), (91, last)]
+    });
+
+    let results = riak.ts_query("SELECT wind FROM GeoCheckin").unwrap();
+    assert_eq!(results,
+               QueryResults::new(vec![Column::new("wind", ColumnType::Varchar)],
+                                 vec![Row::new(vec![TsCell::Varchar(b"calm".to_vec())]),
+                                      Row::new(vec![TsCell::Null])]));
+
+    // query (field 1) is a TsInterpolation with the query as its base (field 1)
+    let expected = field(0x0a, &field(0x0a, b"SELECT wind FROM GeoCheckin"));
+    assert_eq!(requests.recv().unwrap(), (90, expected));
+}
+
+#[test]
+fn test_ts_list_keys() {
+    let listener = InMemoryListener::new();
+    let mut riak = Client::from_transport(listener.connect());
+    let requests = serve(listener, |_| {
+        // keys (field 1) arrive until a response has done (field 2) set
+        let first = field(0x0a, &row(&[timestamp(1)]));
+        let mut last = field(0x0a, &row(&[timestamp(2)]));
+        last.extend(field(0x0a, &row(&[timestamp(3)])));
+        last.extend(&[0x10, 0x01]);
+        vec![(99, first), (99, Vec::new()), (99, last)]
+    });
+
+    let keys = riak.ts_list_keys("GeoCheckin").unwrap();
+    assert_eq!(keys,
+               vec![Row::new(vec![TsCell::Timestamp(1)]),
+                    Row::new(vec![TsCell::Timestamp(2)]),
+                    Row::new(vec![TsCell::Timestamp(3)])]);
+
+    // table (field 1) and the client's 3600 second timeout (field 2) in milliseconds
+    let mut expected = field(0x0a, b"GeoCheckin");
+    expected.extend(&[0x10, 0x80, 0xdd, 0xdb, 0x01]);
+    assert_eq!(requests.recv().unwrap(), (98, expected));
+}
@@ -0,0 +1,304 @@
+#![cfg(feature = "tls")]
+
+extern crate native_tls;
+extern crate openssl;
+extern crate riak;
+
+use native_tls::{Identity, TlsAcceptor};
+use openssl::nid::Nid;
+use openssl::pkey::PKey;
+use openssl::ssl::{SslAcceptor, SslMethod, SslVerifyMode};
+use openssl::x509::X509;
+use riak::Client;
+use riak::errors::RiakErr;
+use riak::object::{FetchObjectReq, ObjectContent, StoreObjectReq};
+use riak::pool::{Pool, PoolConfig};
+use riak::security::SecurityConfig;
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::Arc;
+use std::thread;
+
+static CA: &[u8] = include_bytes!("certs/ca.pem");
+static SERVER_CERT: &[u8] = include_bytes!("certs/server.pem");
+static SERVER_KEY: &[u8] = include_bytes!("certs/server.key");
+static CLIENT_CERT: &[u8] = include_bytes!("certs/client.pem");
+static CLIENT_KEY: &[u8] = include_bytes!("certs/client.key");
+
+fn read_frame<S: Read>(stream: &mut S) -> Option<(u8, Vec<u8>)> {
+    let mut header = [0u8; 5];
+    if stream.read_exact(&mut header).is_err() {
+        return None;
+    }
+    let len = ((header[0] as usize) << 24) | ((header[1] as usize) << 16) |
+              ((header[2] as usize) << 8) | (header[3] as usize);
+    let mut body = vec![0u8; len - 1];
+    if stream.read_exact(&mut body).is_err() {
+        return None;
+    }
+    Some((header[4], body))
+}
+
+fn write_frame<S: Write>(stream: &mut S, code: u8, body: &[u8]) {
+    let len = (body.len() + 1) as u32;
+    let mut frame = vec![(len >> 24) as u8, (len >> 16) as u8, (len >> 8) as u8, len as u8, code];
+    frame.extend_from_slice(body);
+    stream.write_all(&frame).unwrap();
+}
+
+// Split an RpbAuthReq into its user and password fields.
+fn parse_auth_req(body: &[u8]) -> (Vec<u8>, Vec<u8>) {
+    let user_len = body[1] as usize;
+    let user = body[2..2 + user_len].to_vec();
+    let password_len = body[3 + user_len] as usize;
+    let password = body[4 + user_len..4 + user_len + password_len].to_vec();
+    (user, password)
+}
+
+fn read_varint(body: &[u8], pos: &mut usize) -> usize {
+    let mut value = 0;
+    let mut shift = 0;
+    loop {
+        let byte = body[*pos];
+        *pos += 1;
+        value |= ((byte & 0x7f) as usize) << shift;
+        if byte & 0x80 == 0 {
+            return value;
+        }
+        shift += 7;
+    }
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: usize) {
+    while value >= 0x80 {
+        out.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+// Get the RpbContent (field 4) of an RpbPutReq, every other field is length delimited.
+fn put_content(body: &[u8]) -> Vec<u8> {
+    let mut pos = 0;
+    while pos < body.len() {
+        let field = body[pos] >> 3;
+        pos += 1;
+        let len = read_varint(body, &mut pos);
+        if field == 4 {
+            return body[pos..pos + len].to_vec();
+        }
+        pos += len;
+    }
+    Vec::new()
+}
+
+// Answers a StartTls request, the TLS handshake comes next.
+fn start_tls(stream: &mut TcpStream) -> bool {
+    match read_frame(stream) {
+        Some((255, _)) => {
+            write_frame(stream, 255, &[]);
+            true
+        }
+        _ => false,
+    }
+}
+
+// Stands in for a Riak node with security enabled once the TLS handshake is done, accepting
+// "riakuser" / "riakpass". It answers pings, and fetches with the last value stored.
+fn serve_secure<S: Read + Write>(mut stream: S) {
+    match read_frame(&mut stream) {
+        Some((253, body)) => {
+            let (user, password) = parse_auth_req(&body);
+            if user == b"riakuser" && password == b"riakpass" {
+                write_frame(&mut stream, 254, &[]);
+            } else {
+                let errmsg = b"Authentication failed";
+                let mut error = vec![0x0a, errmsg.len() as u8];
+                error.extend_from_slice(errmsg);
+                error.extend_from_slice(&[0x10, 0x00]);
+                write_frame(&mut stream, 0, &error);
+                return;
+            }
+        }
+        _ => return,
+    };
+
+    let mut stored: Vec<u8> = Vec::new();
+    while let Some((code, body)) = read_frame(&mut stream) {
+        match code {
+            1 => write_frame(&mut stream, 2, &[]),
+            11 => {
+                stored = put_content(&body);
+                write_frame(&mut stream, 12, &[]);
+            }
+            9 => {
+                // an RpbGetResp with the stored RpbContent as its only sibling
+                let mut resp = vec![0x0a];
+                write_varint(&mut resp, stored.len());
+                resp.extend_from_slice(&stored);
+                write_frame(&mut stream, 10, &resp);
+            }
+            _ => return,
+        }
+    }
+}
+
+fn serve(mut stream: TcpStream, acceptor: Arc<TlsAcceptor>) {
+    if !start_tls(&mut stream) {
+        return;
+    }
+    if let Ok(stream) = acceptor.accept(stream) {
+        serve_secure(stream);
+    }
+}
+
+fn start_server() -> String {
+    let identity = Identity::from_pkcs8(SERVER_CERT, SERVER_KEY).unwrap();
+    let acceptor = Arc::new(TlsAcceptor::new(identity).unwrap());
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap().to_string();
+    thread::spawn(move || for stream in listener.incoming() {
+        let stream = stream.unwrap();
+        let acceptor = acceptor.clone();
+        thread::spawn(move || serve(stream, acceptor));
+    });
+    addr
+}
+
+// Starts a server that requires a client certificate signed by the test CA, the acceptor of
+// `native_tls` can not ask for one.
+fn start_server_requiring_client_cert() -> String {
+    let mut builder = SslAcceptor::mozilla_intermediate_v5(SslMethod::tls()).unwrap();
+    builder.set_certificate(&X509::from_pem(SERVER_CERT).unwrap()).unwrap();
+    builder.set_private_key(&PKey::private_key_from_pem(SERVER_KEY).unwrap()).unwrap();
+    builder.cert_store_mut().add_cert(X509::from_pem(CA).unwrap()).unwrap();
+    builder.set_verify(SslVerifyMode::PEER | SslVerifyMode::FAIL_IF_NO_PEER_CERT);
+    let acceptor = Arc::new(builder.build());
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap().to_string();
+    thread::spawn(move || for stream in listener.incoming() {
+        let mut stream = stream.unwrap();
+        let acceptor = acceptor.clone();
+        thread::spawn(move || {
+            if !start_tls(&mut stream) {
+                return;
+            }
+            if let Ok(stream) = acceptor.accept(stream) {
+                let subject = stream.ssl()
+                    .peer_certificate()
+                    .and_then(|certificate| {
+                        certificate.subject_name()
+                            .entries_by_nid(Nid::COMMONNAME)
+                            .next()
+                            .map(|entry| entry.data().as_slice().to_vec())
+                    });
+                assert_eq!(subject, Some(b"riakuser".to_vec()));
+                serve_secure(stream);
+            }
+        });
+    });
+    addr
+}
+
+fn security(password: &str) -> SecurityConfig {
+    let mut security = SecurityConfig::new("riakuser", password);
+    security.set_domain("localhost");
+    security.add_root_certificate(CA);
+    security.set_client_identity(CLIENT_CERT, CLIENT_KEY);
+    security
+}
+
+#[test]
+fn test_secure_connection() {
+    let addr = start_server();
+
+    let mut riak = Client::new_secure(addr.as_str(), &security("riakpass")).unwrap();
+    riak.ping().unwrap();
+    riak.reconnect().unwrap();
+    riak.ping().unwrap();
+
+    let mut config = PoolConfig::new();
+    config.set_security(security("riakpass"));
+    let pool = Pool::new(addr.as_str(), config).unwrap();
+    pool.get().unwrap().ping().unwrap();
+}
+
+#[test]
+fn test_secure_connection_rejects_bad_credentials() {
+    let addr = start_server();
+
+    match Client::new_secure(addr.as_str(), &security("wrongpass")) {
+        Err(RiakErr::ServerError(error)) => {
+            assert_eq!(error.get_message(), "Authentication failed");
+            assert_eq!(error.get_errcode(), Some(0));
+        }
+        other => panic!("expected a server error, got {:?}", other),
+    };
+}
+
+#[test]
+fn test_secure_connection_rejects_untrusted_server() {
+    let addr = start_server();
+
+    let mut security = SecurityConfig::new("riakuser", "riakpass");
+    security.set_domain("localhost");
+
+    match Client::new_secure(addr.as_str(), &security) {
+        Err(RiakErr::TlsError(_)) => (),
+        other => panic!("expected a TLS error, got {:?}", other),
+    };
+}
+
+#[test]
+fn test_secure_large_values() {
+    let addr = start_server();
+    // a value sent only in part would leave the server waiting for the rest until this timeout
+    let mut riak = Client::new_secure_with_timeout(addr.as_str(), &security("riakpass"), 5)
+        .unwrap();
+
+    // larger than a single TLS record
+    let value: Vec<u8> = (0..100000).map(|i| (i % 251) as u8).collect();
+    let mut req = StoreObjectReq::new("testbucket", ObjectContent::new(value.clone()));
+    req.set_key("testkey");
+    riak.store_object(&req).unwrap();
+
+    let resp = riak.fetch_object(&FetchObjectReq::new("testbucket", "testkey")).unwrap();
+    assert_eq!(resp.get_content()[0].get_value(), value);
+}
+
+#[test]
+fn test_secure_connection_with_client_certificate() {
+    let addr = start_server_requiring_client_cert();
+
+    let mut riak = Client::new_secure(addr.as_str(), &security("riakpass")).unwrap();
+    riak.ping().unwrap();
+
+    // without the client certificate the server ends the handshake
+    let mut security = SecurityConfig::new("riakuser", "riakpass");
+    security.set_domain("localhost");
+    security.add_root_certificate(CA);
+    match Client::new_secure(addr.as_str(), &security) {
+        Err(RiakErr::TlsError(_)) |
+        Err(RiakErr::IoError(_)) => (),
+        other => panic!("expected a TLS or I/O error, got {:?}", other),
+    };
+}
+
+#[test]
+fn test_debug_output_redacts_credentials() {
+    let security = security("riakpass");
+    let password = format!("{:?}", b"riakpass".to_vec());
+    let key = format!("{:?}", CLIENT_KEY.to_vec());
+
+    let output = format!("{:?}", security);
+    assert!(output.contains("riakuser"));
+    assert!(!output.contains(&password));
+    assert!(!output.contains(&key));
+
+    let mut config = PoolConfig::new();
+    config.set_security(security);
+    let output = format!("{:?}", config);
+    assert!(!output.contains(&password));
+    assert!(!output.contains(&key));
+}
@@ -0,0 +1,139 @@
+extern crate riak;
+
+use riak::Client;
+use riak::errors::RiakErr;
+use riak::object::FetchObjectReq;
+use riak::transport::{Direction, InMemoryListener, InMemoryTransport, RecordingTransport,
+                      Transport};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+// Answers pings and key listings on every connection made to the listener, anything else fails.
+fn serve(listener: InMemoryListener) {
+    thread::spawn(move || while let Ok(mut connection) = listener.accept() {
+        thread::spawn(move || while let Ok((code, _)) = connection.receive() {
+            match code {
+                1 => connection.send(2, &[]).unwrap(),
+                17 => {
+                    connection.send(18, &[0x0a, 0x01, b'a', 0x0a, 0x01, b'b']).unwrap();
+                    connection.send(18, &[0x10, 0x01]).unwrap();
+                }
+                // an RpbErrorResp with errmsg "bad" and errcode 1
+                _ => connection.send(0, &[0x0a, 0x03, b'b', b'a', b'd', 0x10, 0x01]).unwrap(),
+            }
+        });
+    });
+}
+
+#[test]
+fn test_in_memory_transport() {
+    let listener = InMemoryListener::new();
+    let mut riak = Client::from_transport(listener.connect());
+    serve(listener);
+
+    riak.ping().unwrap();
+    match riak.fetch_object(&FetchObjectReq::new("testbucket", "testkey")) {
+        Err(RiakErr::ServerError(error)) => assert_eq!(error.get_message(), "bad"),
+        other => panic!("expected a server error, got {:?}", other),
+    };
+
+    // streams open a connection of their own through the listener
+    assert_eq!(riak.list_keys("testbucket").unwrap(), vec![b"a".to_vec(), b"b".to_vec()]);
+    riak.ping().unwrap();
+}
+
+#[test]
+fn test_in_memory_shutdown() {
+    let listener = InMemoryListener::new();
+    let mut client_end = listener.connect();
+    let mut server_end = listener.accept().unwrap();
+
+    client_end.send(1, &[]).unwrap();
+    client_end.shutdown().unwrap();
+    assert!(client_end.send(1, &[]).is_err());
+
+    // messages sent before the shutdown still arrive
+    assert_eq!(server_end.receive().unwrap(), (1, Vec::new()));
+    assert!(server_end.receive().is_err());
+}
+
+#[test]
+fn test_recording_transport() {
+    let listener = InMemoryListener::new();
+    let transport = RecordingTransport::new(listener.connect());
+    let recording = transport.get_recording();
+    let mut riak = Client::from_transport(transport);
+    serve(listener);
+
+    riak.ping().unwrap();
+    riak.list_keys("testbucket").unwrap();
+
+    let messages = recording.get_messages();
+    let summary: Vec<(Direction, u8)> = messages.iter()
+        .map(|message| (message.direction, message.code))
+        .collect();
+    assert_eq!(summary,
+               vec![(Direction::Sent, 1),
+                    (Direction::Received, 2),
+                    (Direction::Sent, 17),
+                    (Direction::Received, 18),
+                    (Direction::Received, 18)]);
+
+    recording.clear();
+    assert!(recording.get_messages().is_empty());
+}
+
+// A transport counting the requests sent through it, as one adding metrics would.
+#[derive(Debug)]
+struct CountingTransport {
+    inner: InMemoryTransport,
+    requests: Arc<AtomicUsize>,
+}
+
+impl Transport for CountingTransport {
+    fn send(&mut self, code: u8, message: &[u8]) -> Result<(), RiakErr> {
+        self.requests.fetch_add(1, Ordering::SeqCst);
+        self.inner.send(code, message)
+    }
+
+    fn receive(&mut self) -> Result<(u8, Vec<u8>), RiakErr> {
+        self.inner.receive()
+    }
+
+    fn duplicate(&self) -> Result<CountingTransport, RiakErr> {
+        match self.inner.duplicate() {
+            Ok(inner) => {
+                Ok(CountingTransport {
+                    inner: inner,
+                    requests: self.requests.clone(),
+                })
+            }
+            Err(error) => Err(error),
+        }
+    }
+
+    fn shutdown(&mut self) -> Result<(), RiakErr> {
+        self.inner.shutdown()
+    }
+
+    fn get_timeout(&self) -> u32 {
+        self.inner.get_timeout()
+    }
+}
+
+#[test]
+fn test_custom_transport() {
+    let listener = InMemoryListener::new();
+    let requests = Arc::new(AtomicUsize::new(0));
+    let mut riak = Client::from_transport(CountingTransport {
+        inner: listener.connect(),
+        requests: requests.clone(),
+    });
+    serve(listener);
+
+    riak.ping().unwrap();
+    riak.ping().unwrap();
+    riak.list_keys("testbucket").unwrap();
+    assert_eq!(requests.load(Ordering::SeqCst), 3);
+}